@@ -0,0 +1,51 @@
+//! Measures a full step of the model on a grid of 120 000 households with their devices, lines and links. Run with
+//! `cargo bench --bench step`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use scaling_up_staying_secure::agent::{EvChargerParam, HeatPumpParam};
+use scaling_up_staying_secure::model::{Model, ModelParameters};
+use scaling_up_staying_secure::util::{Steps, Watt, WattHour};
+
+fn step_once(c: &mut Criterion) {
+    let mut param = ModelParameters::test();
+    param.enable_output = false;
+    param.grid.n_areas = 10;
+    param.grid.ns_per_a = (200, 201);
+    param.grid.hs_per_ns = (60, 61);
+    param.grid.ns_line_capacity = Watt(1_000_000);
+    param.grid.area_line_capacity = Watt(100_000_000);
+    param.grid.line_loss_factor = 0.02;
+    param.grid.ns_link_density = 0.1;
+    param.grid.ns_link_capacity = Watt(100_000);
+    param.grid.battery_adoption = 0.3;
+    param.grid.ev_charger = Some(EvChargerParam {
+        adoption:       0.3,
+        arrival:        (Steps(72), Steps(4)),
+        departure:      (Steps(28), Steps(4)),
+        energy_demand:  (WattHour(10_000), WattHour(2_000)),
+        charging_power: vec![Watt(3_700), Watt(11_000)],
+        smart_share:    0.5,
+    });
+    param.grid.heat_pump = Some(HeatPumpParam {
+        adoption:         0.3,
+        electrical_power: Watt(3_000),
+        cop:              3.0,
+        deadband:         1.0,
+        setpoint:         (20.0, 1.0),
+        resistance:       (0.005, 0.001),
+        capacitance:      (2.0e7, 1.0e6),
+    });
+    let mut model = Model::new(param).expect("Error in creating the model");
+    assert_eq!(model.households.len(), 120_000);
+
+    c.bench_function("step_once", move |b| {
+        b.iter(|| model.step_once().expect("Error in taking a step"))
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = step_once
+}
+criterion_main!(benches);
@@ -12,6 +12,11 @@
 //! enum. The behaviour during each step of the model needs to be defined in the model part.
 
 mod area;
+mod battery;
+mod connection;
+mod evcharger;
+mod generator;
+mod heatpump;
 mod household;
 mod netstation;
 mod root;
@@ -21,6 +26,11 @@ use std::sync::Arc;
 
 use apache_avro::{AvroSchema, Schema};
 pub use area::*;
+pub use battery::*;
+pub use connection::*;
+pub use evcharger::*;
+pub use generator::*;
+pub use heatpump::*;
 pub use household::*;
 use log::trace;
 pub use netstation::*;
@@ -46,6 +56,31 @@ pub type NetstationRef = Arc<RwLock<Netstation>>;
 /// A vector of the [NetstationRef] type.
 pub type NetstationList = Vec<NetstationRef>;
 
+/// The [Connection] struct within an Arc and RwLock.
+pub type ConnectionRef = Arc<RwLock<Connection>>;
+/// A vector of the [ConnectionRef] type.
+pub type ConnectionList = Vec<ConnectionRef>;
+
+/// The [EvCharger] struct within an Arc and RwLock.
+pub type EvChargerRef = Arc<RwLock<EvCharger>>;
+/// A vector of the [EvChargerRef] type.
+pub type EvChargerList = Vec<EvChargerRef>;
+
+/// The [HeatPump] struct within an Arc and RwLock.
+pub type HeatPumpRef = Arc<RwLock<HeatPump>>;
+/// A vector of the [HeatPumpRef] type.
+pub type HeatPumpList = Vec<HeatPumpRef>;
+
+/// The [Battery] struct within an Arc and RwLock.
+pub type BatteryRef = Arc<RwLock<Battery>>;
+/// A vector of the [BatteryRef] type.
+pub type BatteryList = Vec<BatteryRef>;
+
+/// The [Generator] struct within an Arc and RwLock.
+pub type GeneratorRef = Arc<RwLock<Generator>>;
+/// A vector of the [GeneratorRef] type.
+pub type GeneratorList = Vec<GeneratorRef>;
+
 /// The [Household] struct within an Arc and RwLock.
 pub type HouseholdRef = Arc<RwLock<Household>>;
 /// A vector of the [HouseholdRef] type.
@@ -100,11 +135,11 @@ pub trait AgentTrait: Debug {
         for child in self.children() {
             res.manual_add(child.read_arc_recursive().powerstate());
         }
-        self.powerstate_mut().power_used = res.power_used;
-        self.powerstate_mut().power_reported = res.power_reported;
-        self.powerstate_mut().power_error = res.power_error;
-        self.powerstate_mut().power_generated = res.power_generated;
+        self.set_power_from_children(&res);
     }
+
+    /// Set the powerstate of the agent given the summed powerstate of its children.
+    fn set_power_from_children(&mut self, sum: &PowerState) { self.powerstate_mut().manual_set(sum); }
 }
 
 /// Agents and results in better readability of the code at the small expense of
@@ -113,7 +148,7 @@ pub trait AgentTrait: Debug {
 pub enum AgentKind {
     /// Root of the Grid
     Root,
-    /// Connections between other agents
+    /// Horizontal connections between netstations or areas.
     Connection,
     /// Area agent represents a larger geographical area.
     Area,
@@ -123,4 +158,12 @@ pub enum AgentKind {
     /// Represents a single household. It may have a PV system. However, that is
     /// determined by the link in the model graph.
     Household,
+    /// A conventional power plant that is part of the generation fleet. Attached to the Root or an Area.
+    Generator,
+    /// The charger of an electric vehicle. Attached to a Household.
+    EvCharger,
+    /// The heat pump of a house. Attached to a Household.
+    HeatPump,
+    /// The home battery of a house. Attached to a Household.
+    Battery,
 }
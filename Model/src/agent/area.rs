@@ -3,41 +3,96 @@ use std::fmt::{Debug, Display};
 use apache_avro::AvroSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::agent::{AgentKind, AgentList, AgentTrait};
+use crate::agent::{AgentKind, AgentList, AgentTrait, NetstationList};
 #[allow(unused_imports)]
 use crate::agent::{Netstation, Root};
-use crate::grid::PowerState;
-use crate::util::{BaseUint, Steps};
+use crate::grid::{AreaState, Boundaries, BoundaryAgentTrait, Grid, GridBoundaryState, GridWarning, InfectionCount, PowerState};
+use crate::util::{mPercent, BaseInt, BaseUint, Steps};
 
 /// The Area agent. A level below [Root] and above [Netstation].
 ///
 /// An Area is a representation of a big geographical region that is used by a grid operator to divide the grid in
-/// multiple pieces. As such it sits below the [Root] node and has Netstation] agents as its children.
+/// multiple pieces. As such it sits below the [Root] node and has [Netstation] agents as its children.
+///
+/// The area monitors the health of its region. The share of its netstations that are outside the normal voltage band
+/// is checked against the boundaries of the area, after the model has updated the state of the area with
+/// [Area::update_area_state].
 #[derive(Clone, Serialize, AvroSchema, Deserialize)]
 pub struct Area {
     /// Kind of agent.
-    pub kind:       AgentKind,
+    pub kind:          AgentKind,
     /// Index used in the graph.
-    pub index:      BaseUint,
+    pub index:         BaseUint,
     /// Current step of the model.
-    pub step:       Steps,
+    pub step:          Steps,
     #[serde(skip)]
     #[avro(skip)]
     /// List of children this Agent has according to the graph.
-    pub children:   AgentList,
+    pub children:      AgentList,
     /// The current power state of the agent. Changes each step.
-    pub powerstate: PowerState,
+    pub powerstate:    PowerState,
+    #[serde(skip)]
+    #[avro(skip)]
+    /// The netstations in the area.
+    pub netstations:   NetstationList,
+    /// Boundaries on the share of unhealthy netstations.
+    pub area_boundary: Boundaries<mPercent>,
+    /// The aggregated state of the area.
+    pub area_state:    AreaState,
+    /// Are all netstations in the area energised?
+    pub energised:     bool,
 }
 
 impl Area {
-    /// Create a new [Area] agent using the index provided by the graph of the model.
-    pub fn new(index: BaseUint) -> Self {
+    /// Create a new [Area] agent using the index provided by the graph of the model and the grid parameters.
+    pub fn new(index: BaseUint, grid_param: &Grid) -> Self {
         Self {
             kind: AgentKind::Area,
             index,
             step: Steps(0),
             children: vec![],
             powerstate: PowerState::new(),
+            netstations: vec![],
+            area_boundary: Boundaries::<mPercent>::from(grid_param),
+            area_state: AreaState::default(),
+            energised: true,
+        }
+    }
+
+    /// Determine the aggregated state of the area from its netstations and the infection states of the PV
+    /// installations and devices of its households.
+    pub fn update_area_state(&mut self, infection: InfectionCount) -> AreaState {
+        let (mut high, mut low) = (0, 0);
+        for ns in &self.netstations {
+            let state = ns.read_arc_recursive().volt_boundary.state;
+            match state {
+                GridBoundaryState::High | GridBoundaryState::TooHigh => high += 1,
+                GridBoundaryState::Low | GridBoundaryState::TooLow => low += 1,
+                GridBoundaryState::Normal => (),
+            }
+        }
+        let total = self.netstations.len() as BaseInt;
+
+        self.area_state = AreaState {
+            unhealthy: mPercent::share(high + low, total),
+            high:      mPercent::share(high, total),
+            low:       mPercent::share(low, total),
+            infected:  mPercent::share(infection.infected, infection.total()),
+            imbalance: self.powerstate.power_error,
+        };
+        self.area_state
+    }
+}
+
+impl BoundaryAgentTrait<mPercent> for Area {
+    fn boundary_check(&mut self) -> Option<GridWarning> {
+        if let Some(mut gw) = self.area_boundary.update(self.area_state.unhealthy) {
+            gw.agent_index = Some(self.index);
+            gw.agent_powerstate = Some(self.powerstate.clone());
+            gw.area_state = Some(self.area_state);
+            Some(gw)
+        } else {
+            None
         }
     }
 }
@@ -96,6 +151,7 @@ impl Debug for Area {
                 .field("Index", &self.index)
                 .field("Children", &self.children)
                 .field("PowerState", &self.powerstate)
+                .field("AreaState", &self.area_state)
                 .finish_non_exhaustive()
         } else {
             let children_index: Vec<u32> = self.children.iter().map(|c| *c.read_arc_recursive().index()).collect();
@@ -123,3 +179,44 @@ impl Display for Area {
             .finish_non_exhaustive()
     }
 }
+
+#[cfg(test)]
+mod area_tests {
+    use std::sync::Arc;
+
+    use parking_lot::RwLock;
+
+    use super::*;
+    use crate::model::ModelParameters;
+
+    #[test]
+    fn share_of_unhealthy_netstations() {
+        let param = ModelParameters::test();
+        let mut area = Area::new(117, &param.grid);
+        for index in 0..4 {
+            area.netstations
+                .push(Arc::new(RwLock::new(Netstation::new(index, &param.grid))));
+        }
+        area.netstations[0].write().volt_boundary.state = GridBoundaryState::High;
+        area.netstations[1].write().volt_boundary.state = GridBoundaryState::TooLow;
+
+        let area_state = area.update_area_state(InfectionCount::default());
+        assert_eq!(area_state.unhealthy, mPercent(50_000));
+        assert_eq!(area_state.high, mPercent(25_000));
+        assert_eq!(area_state.low, mPercent(25_000));
+        assert_eq!(area_state.infected, mPercent(0));
+    }
+
+    #[test]
+    fn share_of_infected_devices() {
+        let param = ModelParameters::test();
+        let mut area = Area::new(117, &param.grid);
+        let infection = InfectionCount {
+            not_vulnerable: 2,
+            vulnerable:     1,
+            infected:       1,
+            patched:        0,
+        };
+        assert_eq!(area.update_area_state(infection).infected, mPercent(25_000));
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt::{Debug, Display};
+
+use apache_avro::AvroSchema;
+use serde::{Deserialize, Serialize};
+
+#[allow(unused_imports)]
+use crate::agent::Household;
+use crate::agent::{AgentKind, AgentList, AgentTrait};
+use crate::grid::{InfectionState, PowerState};
+use crate::model::ModelParameters;
+use crate::util::{random_percentage, BaseUint, Steps, Watt, WattHour};
+
+/// The Battery agent. A level below [Household] and the home battery of the house.
+///
+/// By default the battery is used for self-consumption. A surplus of the household is used to charge the battery and a
+/// shortage is covered by discharging it, both within the power limits and the state of charge of the battery. An
+/// infected battery follows the [BatteryCommand] of the attacker instead, while it keeps reporting the flow of
+/// self-consumption.
+#[derive(Clone, Serialize, AvroSchema, Deserialize)]
+pub struct Battery {
+    /// Kind of agent.
+    pub kind:                AgentKind,
+    /// Index used in the graph.
+    pub index:               BaseUint,
+    /// Current step of the model.
+    pub step:                Steps,
+    #[serde(skip)]
+    #[avro(skip)]
+    /// List of children this Agent has according to the graph.
+    pub children:            AgentList,
+    /// The current power state of the agent. Changes each step.
+    pub powerstate:          PowerState,
+    /// Current state of infection. Also, the indication of the system if it is
+    /// vulnerable or not.
+    pub infection_state:     InfectionState,
+    /// Amount of energy the battery can store.
+    pub capacity:            WattHour,
+    /// Amount of energy currently stored.
+    pub state_of_charge:     WattHour,
+    /// Maximum power with which the battery can be charged.
+    pub max_charge_power:    Watt,
+    /// Maximum power with which the battery can be discharged.
+    pub max_discharge_power: Watt,
+    /// Current flow of power into the battery. Positive indicates charging, negative discharging.
+    pub flow:                Watt,
+}
+
+/// The commands an attacker can give to infected batteries.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatteryCommand {
+    /// Charge with the maximum power possible.
+    FullCharge,
+    /// Discharge with the maximum power possible.
+    FullDischarge,
+}
+
+impl Battery {
+    /// Creates a new battery agent, using the index of the graph and the battery parameters of the grid. The battery
+    /// starts half full.
+    pub fn new(index: BaseUint, param: &mut ModelParameters) -> Self {
+        let infection_state = if random_percentage(&mut param.seed) < param.attack.percentage_vuln_devices {
+            InfectionState::Vulnerable
+        } else {
+            InfectionState::NotVulnerable
+        };
+        Self {
+            kind: AgentKind::Battery,
+            index,
+            step: Steps(0),
+            children: vec![],
+            powerstate: PowerState::new(),
+            infection_state,
+            capacity: param.grid.battery_capacity,
+            state_of_charge: WattHour(param.grid.battery_capacity.0 / 2),
+            max_charge_power: param.grid.battery_power.0,
+            max_discharge_power: param.grid.battery_power.1,
+            flow: Watt(0),
+        }
+    }
+
+    /// Limit the requested flow to what the battery can deliver this step, given its power limits and state of charge.
+    pub fn limit(&self, requested: Watt) -> Watt {
+        if requested > Watt(0) {
+            let headroom = (self.capacity - self.state_of_charge).as_step_power();
+            requested.min(self.max_charge_power).min(headroom)
+        } else {
+            let available = self.state_of_charge.as_step_power();
+            requested.max(-self.max_discharge_power).max(-available)
+        }
+    }
+
+    /// The flow of the self-consumption strategy given the net consumption of the household. A surplus charges the
+    /// battery, a shortage discharges it.
+    pub fn self_consumption(&self, net_consumption: Watt) -> Watt { self.limit(-net_consumption) }
+
+    /// The flow when following the command of an attacker.
+    pub fn command(&self, command: BatteryCommand) -> Watt {
+        match command {
+            BatteryCommand::FullCharge => self.limit(self.max_charge_power),
+            BatteryCommand::FullDischarge => self.limit(-self.max_discharge_power),
+        }
+    }
+
+    /// Apply the flow for a single step and update the state of charge.
+    pub fn apply(&mut self, flow: Watt) {
+        self.flow = flow;
+        self.state_of_charge = (self.state_of_charge + WattHour::per_step(flow)).clamp(WattHour(0), self.capacity);
+    }
+
+    /// Operate the battery for this step given the net consumption of the household. The battery uses
+    /// self-consumption, unless it is infected and a command is given. The reported power is always that of
+    /// self-consumption.
+    pub fn operate(&mut self, net_consumption: Watt, command: Option<BatteryCommand>) {
+        let clean_flow = self.self_consumption(net_consumption);
+        let flow = match command {
+            Some(command) if self.infection_state == InfectionState::Infected => self.command(command),
+            _ => clean_flow,
+        };
+        self.apply(flow);
+
+        self.powerstate.power_used = flow.max(Watt(0));
+        self.powerstate.power_generated = (-flow).max(Watt(0));
+        self.powerstate.power_reported = clean_flow;
+        self.powerstate.power_error = clean_flow - flow;
+    }
+}
+
+impl AgentTrait for Battery {
+    fn kind(&self) -> &AgentKind { &self.kind }
+
+    fn step(&self) -> &Steps { &self.step }
+
+    fn index(&self) -> &BaseUint { &self.index }
+
+    fn powerstate(&self) -> &PowerState { &self.powerstate }
+
+    fn children(&self) -> &AgentList { &self.children }
+
+    fn kind_mut(&mut self) -> &mut super::AgentKind { &mut self.kind }
+
+    fn index_mut(&mut self) -> &mut crate::util::BaseUint { &mut self.index }
+
+    fn step_mut(&mut self) -> &mut crate::util::Steps { &mut self.step }
+
+    fn children_mut(&mut self) -> &mut super::AgentList { &mut self.children }
+
+    fn powerstate_mut(&mut self) -> &mut crate::grid::PowerState { &mut self.powerstate }
+
+    fn update_step(&mut self, step: crate::util::Steps) { self.step = step; }
+
+    fn get_json(&self) -> Result<Vec<u8>, crate::util::ModelError> {
+        match serde_json::to_vec(&self) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(crate::util::ModelError::LogStateErrorJson {
+                agent:  self.to_string(),
+                source: e,
+            }),
+        }
+    }
+
+    fn get_avro(&self) -> Result<(apache_avro::Schema, apache_avro::types::Value), crate::util::ModelError> {
+        let schema = Self::get_schema();
+        match apache_avro::to_value(self) {
+            Ok(v) => Ok((schema, v)),
+            Err(e) => Err(crate::util::ModelError::LogStateErrorAvro {
+                agent:  self.to_string(),
+                source: e,
+            }),
+        }
+    }
+}
+
+impl Debug for Battery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        if f.sign_plus() {
+            f.debug_struct("Battery")
+                .field("Kind", &self.kind)
+                .field("Step", &self.step)
+                .field("Index", &self.index)
+                .field("StateOfCharge", &self.state_of_charge)
+                .field("Flow", &self.flow)
+                .field("PowerState", &self.powerstate)
+                .finish_non_exhaustive()
+        } else {
+            f.debug_struct("Agent")
+                .field("Kind", &self.kind)
+                .field("Step", &self.step)
+                .field("Index", &self.index)
+                .finish_non_exhaustive()
+        }
+    }
+}
+
+impl Display for Battery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        let flow = self.flow.to_string();
+        f.debug_struct("Battery")
+            .field("Kind", &self.kind)
+            .field("Index", &self.index)
+            .field("Step", &self.step)
+            .field("Flow", &flow)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod battery_tests {
+    use super::*;
+
+    fn test_battery() -> Battery {
+        Battery {
+            kind:                AgentKind::Battery,
+            index:               117,
+            step:                Steps(0),
+            children:            vec![],
+            powerstate:          PowerState::new(),
+            infection_state:     InfectionState::Vulnerable,
+            capacity:            WattHour(10_000),
+            state_of_charge:     WattHour(5_000),
+            max_charge_power:    Watt(4_000),
+            max_discharge_power: Watt(3_000),
+            flow:                Watt(0),
+        }
+    }
+
+    #[test]
+    fn self_consumption_within_limits() {
+        let battery = test_battery();
+        assert_eq!(battery.self_consumption(Watt(-1_000)), Watt(1_000));
+        assert_eq!(battery.self_consumption(Watt(-10_000)), Watt(4_000));
+        assert_eq!(battery.self_consumption(Watt(1_000)), Watt(-1_000));
+        assert_eq!(battery.self_consumption(Watt(10_000)), Watt(-3_000));
+    }
+
+    #[test]
+    fn state_of_charge_limits_flow() {
+        let mut battery = test_battery();
+        battery.state_of_charge = WattHour(9_500);
+        assert_eq!(battery.command(BatteryCommand::FullCharge), Watt(2_000));
+        battery.apply(Watt(2_000));
+        assert_eq!(battery.state_of_charge, WattHour(10_000));
+        assert_eq!(battery.command(BatteryCommand::FullCharge), Watt(0));
+
+        battery.state_of_charge = WattHour(250);
+        assert_eq!(battery.command(BatteryCommand::FullDischarge), Watt(-1_000));
+        battery.apply(Watt(-1_000));
+        assert_eq!(battery.state_of_charge, WattHour(0));
+    }
+
+    #[test]
+    fn attacked_operation() {
+        let mut battery = test_battery();
+        battery.infection_state = InfectionState::Infected;
+        battery.operate(Watt(-1_000), Some(BatteryCommand::FullDischarge));
+        assert_eq!(battery.powerstate.power_generated, Watt(3_000));
+        assert_eq!(battery.powerstate.power_reported, Watt(1_000));
+        assert_eq!(battery.powerstate.power_error, Watt(4_000));
+    }
+}
@@ -4,23 +4,142 @@ use apache_avro::AvroSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::agent::{AgentKind, AgentList, AgentTrait};
+#[allow(unused_imports)]
+use crate::agent::{Area, Netstation, Root};
 use crate::grid::PowerState;
-use crate::util::{BaseUint, Steps};
+use crate::util::{BaseFloat, BaseUint, Steps, Watt};
 
+/// The Connection agent. A line element with a rated capacity and resistive losses.
+///
+/// A connection is either a line between two levels of the tree, or a link between two [Netstation] or two [Area]
+/// agents next to the tree.
+///
+/// A line between levels sits in the tree between the parent and its child, e.g. the feeder from an [Area] to a
+/// [Netstation]. The flow over the line is the power drawn by the child. The losses grow with the square of the flow
+/// and are consumed by the line, so they are part of the power used and the mismatch of the parent. A flow above the
+/// capacity overloads the line.
+///
+/// Over a closed horizontal link the two ends share their power mismatch, limited by the capacity of the link. The
+/// losses over the link are consumed by the receiving end. An open link does not share any power, like a normally open
+/// point in a medium voltage ring.
+///
+/// During a step, the flow over a connection is kept in the [GridLayout](crate::model::GridLayout). The agent is
+/// updated when the layout is stored.
 #[derive(Clone, Serialize, AvroSchema, Deserialize)]
 pub struct Connection {
     /// Kind of agent
-    pub kind:       AgentKind,
+    pub kind:        AgentKind,
     /// Index used by agents_vec
-    pub index:      BaseUint,
+    pub index:       BaseUint,
     /// Current step
-    pub step:       Steps,
+    pub step:        Steps,
     #[serde(skip)]
     #[avro(skip)]
     /// List of children indices
-    pub children:   AgentList,
+    pub children:    AgentList,
     /// The current power state of the agent. Changes each step.
-    pub powerstate: PowerState,
+    pub powerstate:  PowerState,
+    /// Index of the agent on one end of the connection.
+    pub from:        BaseUint,
+    /// Index of the agent on the other end of the connection.
+    pub to:          BaseUint,
+    /// Maximum power that can flow over the connection.
+    pub capacity:    Watt,
+    /// Is the connection closed, i.e. can power flow over it?
+    pub closed:      bool,
+    /// Current flow over the connection. Positive indicates a flow from `from` to `to`.
+    pub flow:        Watt,
+    /// Is the connection a line between two levels of the tree?
+    pub vertical:    bool,
+    /// Fraction of the capacity that is lost when the flow equals the capacity.
+    pub loss_factor: BaseFloat,
+    /// Power lost over the connection in this step.
+    pub losses:      Watt,
+    /// Is the flow over the connection above its capacity?
+    pub overloaded:  bool,
+}
+
+impl Connection {
+    /// Creates a new closed horizontal link between two agents, using the index of the graph.
+    pub fn new(index: BaseUint, from: BaseUint, to: BaseUint, capacity: Watt, loss_factor: BaseFloat) -> Self {
+        Self {
+            kind: AgentKind::Connection,
+            index,
+            step: Steps(0),
+            children: vec![],
+            powerstate: PowerState::new(),
+            from,
+            to,
+            capacity,
+            closed: true,
+            flow: Watt(0),
+            vertical: false,
+            loss_factor,
+            losses: Watt(0),
+            overloaded: false,
+        }
+    }
+
+    /// Creates a new line between a parent and its child, using the index of the graph.
+    pub fn new_line(
+        index: BaseUint,
+        parent: BaseUint,
+        child: BaseUint,
+        capacity: Watt,
+        loss_factor: BaseFloat,
+    ) -> Self {
+        Self {
+            vertical: true,
+            ..Self::new(index, parent, child, capacity, loss_factor)
+        }
+    }
+
+    /// Set the flow over the connection and determine the losses and whether it is overloaded.
+    pub fn load(&mut self, flow: Watt) {
+        self.flow = flow;
+        (self.losses, self.overloaded) = Self::losses_and_overload(self.capacity, self.loss_factor, flow);
+    }
+
+    /// The losses over a connection with the given capacity and loss factor for the flow, and whether the flow is
+    /// above the capacity.
+    pub fn losses_and_overload(capacity: Watt, loss_factor: BaseFloat, flow: Watt) -> (Watt, bool) {
+        let losses = if capacity > Watt(0) {
+            let flow = flow.0 as BaseFloat;
+            Watt((loss_factor * flow * flow / capacity.0 as BaseFloat) as i64)
+        } else {
+            Watt(0)
+        };
+        (losses, flow.0.abs() > capacity.0)
+    }
+
+    /// Determine the flow over the connection given the power mismatch at both ends. Both ends are brought to the same
+    /// mismatch, as far as the capacity allows. Returns the flow from `from` to `to`.
+    pub fn share(&mut self, from_error: Watt, to_error: Watt) -> Watt {
+        let flow = Self::shared_flow(self.closed, self.capacity, from_error, to_error);
+        self.load(flow);
+        self.powerstate.power_used = self.flow;
+        self.powerstate.power_lost = self.losses;
+        self.flow
+    }
+
+    /// The flow over a connection with the given capacity that brings both ends to the same mismatch, see
+    /// [Connection::share].
+    pub fn shared_flow(closed: bool, capacity: Watt, from_error: Watt, to_error: Watt) -> Watt {
+        if closed {
+            Watt(((from_error - to_error).0 / 2).clamp(-capacity.0, capacity.0))
+        } else {
+            Watt(0)
+        }
+    }
+}
+
+/// The losses and congestion over all connections in a single step.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineStatistics {
+    /// Total power lost over the connections.
+    pub losses:     Watt,
+    /// Number of connections with a flow above their capacity.
+    pub overloaded: BaseUint,
 }
 
 impl AgentTrait for Connection {
@@ -44,6 +163,13 @@ impl AgentTrait for Connection {
 
     fn powerstate_mut(&mut self) -> &mut crate::grid::PowerState { &mut self.powerstate }
 
+    /// A line carries the power drawn by its child. The losses are consumed by the line.
+    fn set_power_from_children(&mut self, sum: &PowerState) {
+        self.powerstate.manual_set(sum);
+        self.load(self.powerstate.power_used - self.powerstate.power_generated);
+        self.powerstate.add_losses(self.losses);
+    }
+
     fn update_step(&mut self, step: crate::util::Steps) { self.step = step; }
 
     fn get_json(&self) -> Result<Vec<u8>, crate::util::ModelError> {
@@ -75,7 +201,11 @@ impl Debug for Connection {
                 .field("Kind", &self.kind)
                 .field("Step", &self.step)
                 .field("Index", &self.index)
-                .field("Children", &self.children)
+                .field("From", &self.from)
+                .field("To", &self.to)
+                .field("Flow", &self.flow)
+                .field("Losses", &self.losses)
+                .field("Overloaded", &self.overloaded)
                 .field("PowerState", &self.powerstate)
                 .finish_non_exhaustive()
         } else {
@@ -104,3 +234,30 @@ impl Display for Connection {
             .finish_non_exhaustive()
     }
 }
+
+#[cfg(test)]
+mod connection_tests {
+    use super::*;
+
+    #[test]
+    fn share_limited_by_capacity() {
+        let mut connection = Connection::new(117, 1, 2, Watt(100), 0.0);
+        assert_eq!(connection.share(Watt(60), Watt(-20)), Watt(40));
+        assert_eq!(connection.share(Watt(-500), Watt(500)), Watt(-100));
+
+        connection.closed = false;
+        assert_eq!(connection.share(Watt(60), Watt(-20)), Watt(0));
+    }
+
+    #[test]
+    fn line_losses_and_overload() {
+        let mut line = Connection::new_line(117, 1, 2, Watt(10_000), 0.02);
+        line.load(Watt(5_000));
+        assert_eq!(line.losses, Watt(50));
+        assert!(!line.overloaded);
+
+        line.load(Watt(-20_000));
+        assert_eq!(line.losses, Watt(800));
+        assert!(line.overloaded);
+    }
+}
@@ -0,0 +1,411 @@
+use std::fmt::{Debug, Display};
+
+use apache_avro::AvroSchema;
+use rand::rngs::SmallRng;
+use serde::{Deserialize, Serialize};
+
+#[allow(unused_imports)]
+use crate::agent::Household;
+use crate::agent::{AgentKind, AgentList, AgentTrait};
+use crate::grid::{InfectionState, PowerState};
+use crate::model::ModelParameters;
+use crate::util::{norm_dist, random_percentage, uni_dist, BaseFloat, BaseUint, RngPurpose, RngStreams, Steps, UtilError, Watt, WattHour};
+
+/// The EvCharger agent. A level below [Household] and the charging point of an electric vehicle.
+///
+/// Each day the vehicle arrives and departs around the same time and needs to be charged in between. The times and the
+/// energy of each visit are drawn anew when the vehicle departs, from a stream of the charger for that day. The charger
+/// either charges immediately on arrival or spreads the charging over the time the vehicle is connected. Chargers are
+/// managed through cloud platforms, so an infected charger can be started or stopped by the attacker, while the
+/// platform keeps reporting the schedule it intended.
+#[derive(Clone, Serialize, AvroSchema, Deserialize)]
+pub struct EvCharger {
+    /// Kind of agent.
+    pub kind:             AgentKind,
+    /// Index used in the graph.
+    pub index:            BaseUint,
+    /// Current step of the model.
+    pub step:             Steps,
+    #[serde(skip)]
+    #[avro(skip)]
+    /// List of children this Agent has according to the graph.
+    pub children:         AgentList,
+    /// The current power state of the agent. Changes each step.
+    pub powerstate:       PowerState,
+    /// Current state of infection. Also, the indication of the system if it is
+    /// vulnerable or not.
+    pub infection_state:  InfectionState,
+    /// How the charging is scheduled.
+    pub strategy:         ChargingStrategy,
+    /// Step of the day the vehicle arrives on its next or current visit.
+    pub arrival:          Steps,
+    /// Step of the day the vehicle departs on its next or current visit.
+    pub departure:        Steps,
+    /// Maximum power the vehicle is charged with.
+    pub charging_power:   Watt,
+    /// Energy needed by the vehicle on its next or current visit.
+    pub energy_demand:    WattHour,
+    /// Energy still needed before the vehicle departs.
+    pub remaining_demand: WattHour,
+}
+
+/// The strategies to schedule the charging of a vehicle.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, AvroSchema)]
+pub enum ChargingStrategy {
+    /// Charge with full power as soon as the vehicle arrives.
+    Immediate,
+    /// Spread the charging evenly over the time the vehicle is connected.
+    Smart,
+}
+
+/// The commands an attacker can give to infected chargers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EvCommand {
+    /// Charge connected vehicles with full power.
+    StartCharging,
+    /// Stop charging.
+    StopCharging,
+}
+
+/// The parameters that determine the [EvCharger] agents, as given by the GridParameters file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EvChargerParam {
+    /// Chance of a household having an [EvCharger].
+    pub adoption:       BaseFloat,
+    /// Step of the day of arrival. First is center, second value is std deviation.
+    pub arrival:        (Steps, Steps),
+    /// Step of the day of departure. First is center, second value is std deviation.
+    pub departure:      (Steps, Steps),
+    /// Energy needed each day. First is center, second value is std deviation.
+    pub energy_demand:  (WattHour, WattHour),
+    /// The charging power levels, one is picked uniformly per charger.
+    pub charging_power: Vec<Watt>,
+    /// Share of the chargers that use [ChargingStrategy::Smart].
+    pub smart_share:    BaseFloat,
+}
+
+impl EvCharger {
+    /// Creates a new charger agent, using the index of the graph and the parameters of the grid.
+    /// The first visit is drawn from the stream of the charger for day zero.
+    pub fn new(index: BaseUint, ev_param: &EvChargerParam, param: &mut ModelParameters) -> Result<Self, UtilError> {
+        let (arrival, departure, energy_demand) = Self::draw_visit(
+            ev_param,
+            &mut param.streams.stream_at(RngPurpose::Device, index, Steps(0)),
+        )?;
+        let level = uni_dist((0, ev_param.charging_power.len() as i64), &mut param.seed);
+        let charging_power = ev_param.charging_power[level as usize];
+        let strategy = if random_percentage(&mut param.seed) < ev_param.smart_share {
+            ChargingStrategy::Smart
+        } else {
+            ChargingStrategy::Immediate
+        };
+        let infection_state = if random_percentage(&mut param.seed) < param.attack.percentage_vuln_devices {
+            InfectionState::Vulnerable
+        } else {
+            InfectionState::NotVulnerable
+        };
+
+        Ok(Self {
+            kind: AgentKind::EvCharger,
+            index,
+            step: Steps(0),
+            children: vec![],
+            powerstate: PowerState::new(),
+            infection_state,
+            strategy,
+            arrival,
+            departure,
+            charging_power,
+            energy_demand,
+            remaining_demand: energy_demand,
+        })
+    }
+
+    /// Draw the next visit when the vehicle departs on this step. The visit is drawn from the stream of the charger for
+    /// the day after the departure, so it does not depend on the draws of earlier days. The vehicle of the next visit
+    /// needs all of its energy demand, whenever it arrives.
+    pub fn plan_visit(&mut self, ev_param: &EvChargerParam, streams: &RngStreams) -> Result<(), UtilError> {
+        let steps_per_day = Steps::steps_per_day().0;
+        if self.step.0.rem_euclid(steps_per_day) != self.departure.0 {
+            return Ok(());
+        }
+        let day = Steps(self.step.0.div_euclid(steps_per_day) + 1);
+        (self.arrival, self.departure, self.energy_demand) =
+            Self::draw_visit(ev_param, &mut streams.stream_at(RngPurpose::Device, self.index, day))?;
+        self.remaining_demand = self.energy_demand;
+        Ok(())
+    }
+
+    /// Draw the arrival, departure and energy demand of a visit.
+    fn draw_visit(ev_param: &EvChargerParam, seed: &mut SmallRng) -> Result<(Steps, Steps, WattHour), UtilError> {
+        let steps_per_day = Steps::steps_per_day().0;
+        let arrival = Steps(
+            (norm_dist(
+                &(ev_param.arrival.0 .0 as BaseFloat),
+                &(ev_param.arrival.1 .0 as BaseFloat),
+                seed,
+            )? as i32)
+                .rem_euclid(steps_per_day),
+        );
+        let departure = Steps(
+            (norm_dist(
+                &(ev_param.departure.0 .0 as BaseFloat),
+                &(ev_param.departure.1 .0 as BaseFloat),
+                seed,
+            )? as i32)
+                .rem_euclid(steps_per_day),
+        );
+        let energy_demand = WattHour(
+            (norm_dist(
+                &(ev_param.energy_demand.0 .0 as BaseFloat),
+                &(ev_param.energy_demand.1 .0 as BaseFloat),
+                seed,
+            )? as i64)
+                .max(0),
+        );
+        Ok((arrival, departure, energy_demand))
+    }
+
+    /// Is the vehicle connected to the charger on this step? Does not include the departure step.
+    pub fn is_connected(&self) -> bool {
+        let time_of_day = self.step.0 % Steps::steps_per_day().0;
+        if self.arrival <= self.departure {
+            time_of_day >= self.arrival.0 && time_of_day < self.departure.0
+        } else {
+            time_of_day >= self.arrival.0 || time_of_day < self.departure.0
+        }
+    }
+
+    /// The power the charger would draw this step according to its strategy.
+    pub fn scheduled_power(&self) -> Watt {
+        if !self.is_connected() {
+            return Watt(0);
+        }
+        let needed = self.remaining_demand.as_step_power();
+        match self.strategy {
+            ChargingStrategy::Immediate => needed.min(self.charging_power),
+            ChargingStrategy::Smart => {
+                let time_of_day = self.step.0 % Steps::steps_per_day().0;
+                let steps_left = i64::from((self.departure.0 - time_of_day).rem_euclid(Steps::steps_per_day().0));
+                Watt((needed.0 + steps_left - 1) / steps_left.max(1)).min(self.charging_power)
+            }
+        }
+    }
+
+    /// Charge the vehicle for this step. Infected chargers follow the command of the attacker, if given. The reported
+    /// power is always the scheduled power.
+    pub fn charge(&mut self, command: Option<EvCommand>) {
+        let scheduled = self.scheduled_power();
+        let power = match command {
+            Some(command) if self.infection_state == InfectionState::Infected => match command {
+                EvCommand::StartCharging if self.is_connected() => {
+                    self.charging_power.min(self.remaining_demand.as_step_power())
+                }
+                EvCommand::StartCharging | EvCommand::StopCharging => Watt(0),
+            },
+            _ => scheduled,
+        };
+        self.remaining_demand = (self.remaining_demand - WattHour::per_step(power)).max(WattHour(0));
+
+        self.powerstate.power_used = power;
+        self.powerstate.power_generated = Watt(0);
+        self.powerstate.power_reported = scheduled;
+        self.powerstate.power_error = scheduled - power;
+    }
+}
+
+impl AgentTrait for EvCharger {
+    fn kind(&self) -> &AgentKind { &self.kind }
+
+    fn step(&self) -> &Steps { &self.step }
+
+    fn index(&self) -> &BaseUint { &self.index }
+
+    fn powerstate(&self) -> &PowerState { &self.powerstate }
+
+    fn children(&self) -> &AgentList { &self.children }
+
+    fn kind_mut(&mut self) -> &mut super::AgentKind { &mut self.kind }
+
+    fn index_mut(&mut self) -> &mut crate::util::BaseUint { &mut self.index }
+
+    fn step_mut(&mut self) -> &mut crate::util::Steps { &mut self.step }
+
+    fn children_mut(&mut self) -> &mut super::AgentList { &mut self.children }
+
+    fn powerstate_mut(&mut self) -> &mut crate::grid::PowerState { &mut self.powerstate }
+
+    fn update_step(&mut self, step: crate::util::Steps) { self.step = step; }
+
+    fn get_json(&self) -> Result<Vec<u8>, crate::util::ModelError> {
+        match serde_json::to_vec(&self) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(crate::util::ModelError::LogStateErrorJson {
+                agent:  self.to_string(),
+                source: e,
+            }),
+        }
+    }
+
+    fn get_avro(&self) -> Result<(apache_avro::Schema, apache_avro::types::Value), crate::util::ModelError> {
+        let schema = Self::get_schema();
+        match apache_avro::to_value(self) {
+            Ok(v) => Ok((schema, v)),
+            Err(e) => Err(crate::util::ModelError::LogStateErrorAvro {
+                agent:  self.to_string(),
+                source: e,
+            }),
+        }
+    }
+}
+
+impl Debug for EvCharger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        if f.sign_plus() {
+            f.debug_struct("EvCharger")
+                .field("Kind", &self.kind)
+                .field("Step", &self.step)
+                .field("Index", &self.index)
+                .field("Strategy", &self.strategy)
+                .field("Connected", &self.is_connected())
+                .field("PowerState", &self.powerstate)
+                .finish_non_exhaustive()
+        } else {
+            f.debug_struct("Agent")
+                .field("Kind", &self.kind)
+                .field("Step", &self.step)
+                .field("Index", &self.index)
+                .finish_non_exhaustive()
+        }
+    }
+}
+
+impl Display for EvCharger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        let powerstate = self.powerstate.power_used.to_string();
+        f.debug_struct("EvCharger")
+            .field("Kind", &self.kind)
+            .field("Index", &self.index)
+            .field("Step", &self.step)
+            .field("PowerState", &powerstate)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod ev_charger_tests {
+    use super::*;
+
+    fn test_ev_charger(strategy: ChargingStrategy) -> EvCharger {
+        EvCharger {
+            kind: AgentKind::EvCharger,
+            index: 117,
+            step: Steps(0),
+            children: vec![],
+            powerstate: PowerState::new(),
+            infection_state: InfectionState::Infected,
+            strategy,
+            arrival: Steps(72),
+            departure: Steps(28),
+            charging_power: Watt(11_000),
+            energy_demand: WattHour(10_000),
+            remaining_demand: WattHour(10_000),
+        }
+    }
+
+    #[test]
+    fn connected_overnight() {
+        let mut ev = test_ev_charger(ChargingStrategy::Immediate);
+        ev.update_step(Steps(50));
+        assert!(!ev.is_connected());
+        ev.update_step(Steps(72));
+        assert!(ev.is_connected());
+        ev.update_step(Steps(96 + 27));
+        assert!(ev.is_connected());
+        ev.update_step(Steps(96 + 28));
+        assert!(!ev.is_connected());
+    }
+
+    #[test]
+    fn immediate_and_smart_charging() {
+        let mut immediate = test_ev_charger(ChargingStrategy::Immediate);
+        immediate.update_step(Steps(72));
+        immediate.charge(None);
+        assert_eq!(immediate.powerstate.power_used, Watt(11_000));
+        assert_eq!(immediate.remaining_demand, WattHour(7_250));
+
+        let mut smart = test_ev_charger(ChargingStrategy::Smart);
+        smart.update_step(Steps(72));
+        smart.charge(None);
+        assert_eq!(smart.powerstate.power_used, Watt(770));
+        assert_eq!(smart.powerstate.power_error, Watt(0));
+    }
+
+    #[test]
+    fn attacked_charging() {
+        let mut ev = test_ev_charger(ChargingStrategy::Smart);
+        ev.update_step(Steps(72));
+        ev.charge(Some(EvCommand::StartCharging));
+        assert_eq!(ev.powerstate.power_used, Watt(11_000));
+        assert_eq!(ev.powerstate.power_reported, Watt(770));
+
+        ev.update_step(Steps(73));
+        ev.charge(Some(EvCommand::StopCharging));
+        assert_eq!(ev.powerstate.power_used, Watt(0));
+        assert!(ev.powerstate.power_error > Watt(0));
+    }
+
+    #[test]
+    fn visits_differ_per_day() {
+        let ev_param = EvChargerParam {
+            adoption:       1.0,
+            arrival:        (Steps(72), Steps(4)),
+            departure:      (Steps(28), Steps(4)),
+            energy_demand:  (WattHour(10_000), WattHour(2_000)),
+            charging_power: vec![Watt(11_000)],
+            smart_share:    0.0,
+        };
+        let visits = |streams: RngStreams| {
+            let mut ev = test_ev_charger(ChargingStrategy::Immediate);
+            let mut visits = vec![];
+            for step in 0..(96 * 4) {
+                ev.update_step(Steps(step));
+                ev.plan_visit(&ev_param, &streams).unwrap();
+                visits.push((ev.arrival, ev.departure, ev.energy_demand));
+            }
+            visits.dedup();
+            visits
+        };
+        let first = visits(RngStreams::new(2010));
+        assert!(first.len() > 2);
+        assert_eq!(visits(RngStreams::new(2010)), first);
+        assert_ne!(visits(RngStreams::new(117)), first);
+    }
+
+    #[test]
+    fn demand_of_early_arrival_charged() {
+        // The vehicle of the next visit arrives before the departure of the last one
+        let ev_param = EvChargerParam {
+            adoption:       1.0,
+            arrival:        (Steps(20), Steps(1)),
+            departure:      (Steps(60), Steps(1)),
+            energy_demand:  (WattHour(10_000), WattHour(1)),
+            charging_power: vec![Watt(11_000)],
+            smart_share:    0.0,
+        };
+        let streams = RngStreams::new(2010);
+        let mut ev = test_ev_charger(ChargingStrategy::Immediate);
+        ev.remaining_demand = WattHour(0);
+        ev.update_step(Steps(28));
+        ev.plan_visit(&ev_param, &streams).unwrap();
+        assert!(ev.arrival < Steps(28));
+        let mut charged = WattHour(0);
+        for step in 28..ev.departure.0 {
+            ev.update_step(Steps(step));
+            ev.charge(None);
+            charged += WattHour::per_step(ev.powerstate.power_used);
+        }
+        assert_eq!(ev.remaining_demand, WattHour(0));
+        assert_eq!(charged, ev.energy_demand);
+    }
+}
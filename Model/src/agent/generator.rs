@@ -0,0 +1,334 @@
+use std::fmt::{Debug, Display};
+
+use apache_avro::AvroSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::{AgentKind, AgentList, AgentTrait};
+#[allow(unused_imports)]
+use crate::agent::{Area, Root};
+use crate::grid::PowerState;
+use crate::util::{BaseFloat, BaseInt, BaseUint, Steps, Watt};
+
+/// The Generator agent. A conventional power plant that is attached to the [Root] or an [Area].
+///
+/// The generators together form the conventional generation fleet. The fleet is scheduled in merit order to follow the
+/// forecasted demand of the grid, and dispatched on top of that to correct the power mismatch measured in the previous
+/// step. The output that differs from the schedule, to balance the grid or because the generator can only change its
+/// output within its ramp rate, is visible as the error of its powerstate. Trips of a generator can be scheduled,
+/// during which it does not produce any power.
+#[derive(Clone, Serialize, AvroSchema, Deserialize)]
+pub struct Generator {
+    /// Kind of agent.
+    pub kind:              AgentKind,
+    /// Index used in the graph.
+    pub index:             BaseUint,
+    /// Current step of the model.
+    pub step:              Steps,
+    #[serde(skip)]
+    #[avro(skip)]
+    /// List of children this Agent has according to the graph.
+    pub children:          AgentList,
+    /// The current power state of the agent. Changes each step.
+    pub powerstate:        PowerState,
+    /// Maximum output of the generator.
+    pub capacity:          Watt,
+    /// Minimum output of the generator when it is dispatched.
+    pub min_stable_output: Watt,
+    /// Maximum change of the output in a single step.
+    pub ramp_rate:         Watt,
+    /// Marginal cost of the generator, used to determine the merit order.
+    pub marginal_cost:     BaseFloat,
+    /// The output the generator is scheduled to deliver for the forecasted demand.
+    pub schedule:          Watt,
+    /// The output the generator is dispatched to deliver, the schedule together with its share of the balancing.
+    pub setpoint:          Watt,
+    /// The output the generator actually delivers.
+    pub output:            Watt,
+    /// Is the generator available? False during a trip.
+    pub online:            bool,
+    /// The scheduled trips of this generator.
+    pub trips:             Vec<GeneratorTrip>,
+}
+
+impl Generator {
+    /// Creates a new generator agent, using the index of the graph and the parameters provided.
+    pub fn new(index: BaseUint, param: &GeneratorParam) -> Self {
+        Self {
+            kind: AgentKind::Generator,
+            index,
+            step: Steps(0),
+            children: vec![],
+            powerstate: PowerState::new(),
+            capacity: param.capacity,
+            min_stable_output: param.min_stable_output,
+            ramp_rate: param.ramp_rate,
+            marginal_cost: param.marginal_cost,
+            schedule: Watt(0),
+            setpoint: Watt(0),
+            output: Watt(0),
+            online: true,
+            trips: param.trips.clone(),
+        }
+    }
+
+    /// Determine the schedule of the generator given the forecasted demand that is not yet covered by generators
+    /// earlier in the merit order. Returns the schedule. See [Generator::dispatch] for the commitment rules.
+    pub fn schedule(&mut self, remaining_forecast: Watt) -> Watt {
+        self.online = !self.trips.iter().any(|t| t.is_active(self.step));
+        self.schedule = self.commit(remaining_forecast);
+        self.schedule
+    }
+
+    /// Determine the setpoint of the generator given the demand that is not yet covered by generators earlier in the
+    /// merit order. Returns the setpoint.
+    ///
+    /// A generator that is not running is not committed for a demand below its minimum stable output. A generator that
+    /// is running stays at its minimum stable output instead, and ramps down to it.
+    pub fn dispatch(&mut self, remaining_demand: Watt) -> Watt {
+        self.online = !self.trips.iter().any(|t| t.is_active(self.step));
+        self.setpoint = self.commit(remaining_demand);
+        self.setpoint
+    }
+
+    /// The output the generator commits to for the remaining demand.
+    fn commit(&self, remaining_demand: Watt) -> Watt {
+        if !self.online || remaining_demand <= Watt(0) {
+            Watt(0)
+        } else if remaining_demand < self.min_stable_output {
+            if self.output > Watt(0) {
+                self.min_stable_output
+            } else {
+                Watt(0)
+            }
+        } else if remaining_demand > self.capacity {
+            self.capacity
+        } else {
+            remaining_demand
+        }
+    }
+
+    /// Move the output towards the setpoint within the ramp rate. The first step of the model is a warm start, where
+    /// the output is set to the setpoint directly.
+    pub fn ramp(&mut self) {
+        self.output = if !self.online {
+            Watt(0)
+        } else if self.step == Steps(0) {
+            self.setpoint
+        } else {
+            let delta = (self.setpoint - self.output)
+                .0
+                .clamp(-self.ramp_rate.0, self.ramp_rate.0);
+            self.output + Watt(delta)
+        };
+        self.powerstate.power_generated = self.output;
+        self.powerstate.power_used = Watt(0);
+        self.powerstate.power_reported = Watt(0);
+        self.powerstate.power_error = self.output - self.schedule;
+    }
+}
+
+/// The parameters of a single generator, as given by the GridParameters file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeneratorParam {
+    /// Where is the generator attached? 0 is the [Root], any other value is the number of the [Area] starting at 1.
+    pub location:          BaseInt,
+    /// Maximum output of the generator.
+    pub capacity:          Watt,
+    /// Minimum output of the generator when it is dispatched.
+    pub min_stable_output: Watt,
+    /// Maximum change of the output in a single step.
+    pub ramp_rate:         Watt,
+    /// Marginal cost of the generator, used to determine the merit order.
+    pub marginal_cost:     BaseFloat,
+    /// The scheduled trips of this generator.
+    pub trips:             Vec<GeneratorTrip>,
+}
+
+/// A scheduled trip of a [Generator]. During the trip the generator is offline.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, AvroSchema)]
+pub struct GeneratorTrip {
+    /// When does the trip begin? Inclusive.
+    pub begin: Steps,
+    /// When does the trip end? Not inclusive.
+    pub end:   Steps,
+}
+
+impl GeneratorTrip {
+    /// Is the generator tripped on this step? Does not include the end step.
+    pub fn is_active(&self, step: Steps) -> bool { step >= self.begin && step < self.end }
+}
+
+/// The result of dispatching the generation fleet in a single step.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FleetDispatch {
+    /// The forecasted demand the fleet is scheduled to follow.
+    pub forecast:  Watt,
+    /// The output on top of the schedule the fleet is asked to deliver, to correct the power mismatch measured in the
+    /// previous step.
+    pub balancing: Watt,
+    /// The total output the fleet is asked to deliver.
+    pub setpoint:  Watt,
+    /// The total output the fleet actually delivers.
+    pub output:    Watt,
+}
+
+impl AgentTrait for Generator {
+    fn kind(&self) -> &AgentKind { &self.kind }
+
+    fn step(&self) -> &Steps { &self.step }
+
+    fn index(&self) -> &BaseUint { &self.index }
+
+    fn powerstate(&self) -> &PowerState { &self.powerstate }
+
+    fn children(&self) -> &AgentList { &self.children }
+
+    fn kind_mut(&mut self) -> &mut super::AgentKind { &mut self.kind }
+
+    fn index_mut(&mut self) -> &mut crate::util::BaseUint { &mut self.index }
+
+    fn step_mut(&mut self) -> &mut crate::util::Steps { &mut self.step }
+
+    fn children_mut(&mut self) -> &mut super::AgentList { &mut self.children }
+
+    fn powerstate_mut(&mut self) -> &mut crate::grid::PowerState { &mut self.powerstate }
+
+    fn update_step(&mut self, step: crate::util::Steps) { self.step = step; }
+
+    fn get_json(&self) -> Result<Vec<u8>, crate::util::ModelError> {
+        match serde_json::to_vec(&self) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(crate::util::ModelError::LogStateErrorJson {
+                agent:  self.to_string(),
+                source: e,
+            }),
+        }
+    }
+
+    fn get_avro(&self) -> Result<(apache_avro::Schema, apache_avro::types::Value), crate::util::ModelError> {
+        let schema = Self::get_schema();
+        match apache_avro::to_value(self) {
+            Ok(v) => Ok((schema, v)),
+            Err(e) => Err(crate::util::ModelError::LogStateErrorAvro {
+                agent:  self.to_string(),
+                source: e,
+            }),
+        }
+    }
+}
+
+impl Debug for Generator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        if f.sign_plus() {
+            f.debug_struct("Generator")
+                .field("Kind", &self.kind)
+                .field("Step", &self.step)
+                .field("Index", &self.index)
+                .field("Online", &self.online)
+                .field("Schedule", &self.schedule)
+                .field("Setpoint", &self.setpoint)
+                .field("Output", &self.output)
+                .field("PowerState", &self.powerstate)
+                .finish_non_exhaustive()
+        } else {
+            f.debug_struct("Agent")
+                .field("Kind", &self.kind)
+                .field("Step", &self.step)
+                .field("Index", &self.index)
+                .finish_non_exhaustive()
+        }
+    }
+}
+
+impl Display for Generator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        let output = self.output.to_string();
+        f.debug_struct("Generator")
+            .field("Kind", &self.kind)
+            .field("Index", &self.index)
+            .field("Step", &self.step)
+            .field("Online", &self.online)
+            .field("Output", &output)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod generator_tests {
+    use super::*;
+
+    fn test_generator() -> Generator {
+        Generator::new(
+            117,
+            &GeneratorParam {
+                location:          0,
+                capacity:          Watt(1_000),
+                min_stable_output: Watt(200),
+                ramp_rate:         Watt(100),
+                marginal_cost:     10.0,
+                trips:             vec![],
+            },
+        )
+    }
+
+    #[test]
+    fn dispatch_within_limits() {
+        let mut generator = test_generator();
+        assert_eq!(generator.dispatch(Watt(-10)), Watt(0));
+        assert_eq!(generator.dispatch(Watt(50)), Watt(0));
+        assert_eq!(generator.dispatch(Watt(500)), Watt(500));
+        assert_eq!(generator.dispatch(Watt(5_000)), Watt(1_000));
+
+        // Running, so it stays at the minimum stable output
+        generator.ramp();
+        assert_eq!(generator.dispatch(Watt(50)), Watt(200));
+    }
+
+    #[test]
+    fn small_remainder_not_committed() {
+        let mut first = test_generator();
+        let mut second = test_generator();
+        let mut remaining = Watt(1_100);
+        remaining -= first.dispatch(remaining);
+        remaining -= second.dispatch(remaining);
+        assert_eq!(first.setpoint, Watt(1_000));
+        assert_eq!(second.setpoint, Watt(0));
+        assert_eq!(remaining, Watt(100));
+    }
+
+    #[test]
+    fn ramp_limited_output() {
+        let mut generator = test_generator();
+        generator.dispatch(Watt(500));
+        generator.ramp();
+        assert_eq!(generator.output, Watt(500));
+
+        generator.update_step(Steps(1));
+        generator.schedule(Watt(1_000));
+        generator.dispatch(Watt(1_000));
+        generator.ramp();
+        assert_eq!(generator.output, Watt(600));
+        assert_eq!(generator.powerstate.power_error, Watt(-400));
+    }
+
+    #[test]
+    fn tripped_generator() {
+        let mut generator = test_generator();
+        generator.trips.push(GeneratorTrip {
+            begin: Steps(1),
+            end:   Steps(2),
+        });
+        generator.dispatch(Watt(500));
+        generator.ramp();
+
+        generator.update_step(Steps(1));
+        assert_eq!(generator.dispatch(Watt(500)), Watt(0));
+        generator.ramp();
+        assert_eq!(generator.output, Watt(0));
+
+        generator.update_step(Steps(2));
+        generator.dispatch(Watt(500));
+        generator.ramp();
+        assert_eq!(generator.output, Watt(100));
+    }
+}
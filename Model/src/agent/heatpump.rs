@@ -0,0 +1,300 @@
+use std::fmt::{Debug, Display};
+
+use apache_avro::AvroSchema;
+use serde::{Deserialize, Serialize};
+
+#[allow(unused_imports)]
+use crate::agent::Household;
+use crate::agent::{AgentKind, AgentList, AgentTrait};
+use crate::grid::{InfectionState, PowerState};
+use crate::model::ModelParameters;
+use crate::util::{norm_dist, random_percentage, BaseFloat, BaseUint, Steps, UtilError, Watt};
+
+/// The HeatPump agent. A level below [Household] and the heating of the house.
+///
+/// The house is modelled as a single thermal resistance and capacitance (RC). Heat is lost to the outdoor temperature
+/// through the resistance and the heat pump adds heat when it is running. A thermostat with a deadband around the
+/// setpoint switches the heat pump on and off. An infected heat pump can be given a different setpoint or be forced on
+/// or off by the attacker, while it keeps reporting the draw of the clean controller.
+#[derive(Clone, Serialize, AvroSchema, Deserialize)]
+pub struct HeatPump {
+    /// Kind of agent.
+    pub kind:                     AgentKind,
+    /// Index used in the graph.
+    pub index:                    BaseUint,
+    /// Current step of the model.
+    pub step:                     Steps,
+    #[serde(skip)]
+    #[avro(skip)]
+    /// List of children this Agent has according to the graph.
+    pub children:                 AgentList,
+    /// The current power state of the agent. Changes each step.
+    pub powerstate:               PowerState,
+    /// Current state of infection. Also, the indication of the system if it is
+    /// vulnerable or not.
+    pub infection_state:          InfectionState,
+    /// Electrical power the heat pump draws when running.
+    pub electrical_power:         Watt,
+    /// Coefficient of performance, heat delivered per unit of electrical power.
+    pub cop:                      BaseFloat,
+    /// Thermostat setpoint in degrees Celsius.
+    pub setpoint:                 BaseFloat,
+    /// Width of the thermostat deadband around the setpoint in degrees Celsius.
+    pub deadband:                 BaseFloat,
+    /// Thermal resistance between the house and the outdoors in K/W.
+    pub resistance:               BaseFloat,
+    /// Thermal capacitance of the house in J/K.
+    pub capacitance:              BaseFloat,
+    /// Indoor temperature in degrees Celsius.
+    pub indoor_temperature:       BaseFloat,
+    /// Is the heat pump running?
+    pub heating:                  bool,
+    /// Indoor temperature if the heat pump had never been attacked. Used to determine the reported power.
+    pub clean_indoor_temperature: BaseFloat,
+    /// Would the heat pump be running if it had never been attacked?
+    pub clean_heating:            bool,
+}
+
+/// The commands an attacker can give to infected heat pumps.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum HeatPumpCommand {
+    /// Use a different thermostat setpoint in degrees Celsius.
+    Setpoint(BaseFloat),
+    /// Keep the heat pump running.
+    ForceOn,
+    /// Keep the heat pump off.
+    ForceOff,
+}
+
+/// The parameters that determine the [HeatPump] agents, as given by the GridParameters file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HeatPumpParam {
+    /// Chance of a household having a [HeatPump].
+    pub adoption:         BaseFloat,
+    /// Electrical power the heat pump draws when running.
+    pub electrical_power: Watt,
+    /// Coefficient of performance, heat delivered per unit of electrical power.
+    pub cop:              BaseFloat,
+    /// Width of the thermostat deadband around the setpoint in degrees Celsius.
+    pub deadband:         BaseFloat,
+    /// Thermostat setpoint. First is center, second value is std deviation.
+    pub setpoint:         (BaseFloat, BaseFloat),
+    /// Thermal resistance of the house. First is center, second value is std deviation.
+    pub resistance:       (BaseFloat, BaseFloat),
+    /// Thermal capacitance of the house. First is center, second value is std deviation.
+    pub capacitance:      (BaseFloat, BaseFloat),
+}
+
+impl HeatPump {
+    /// Creates a new heat pump agent, using the index of the graph and the parameters of the grid. The house starts at
+    /// the setpoint.
+    pub fn new(index: BaseUint, hp_param: &HeatPumpParam, param: &mut ModelParameters) -> Result<Self, UtilError> {
+        let setpoint = norm_dist(&hp_param.setpoint.0, &hp_param.setpoint.1, &mut param.seed)?;
+        let resistance = norm_dist(&hp_param.resistance.0, &hp_param.resistance.1, &mut param.seed)?.abs();
+        let capacitance = norm_dist(&hp_param.capacitance.0, &hp_param.capacitance.1, &mut param.seed)?.abs();
+        let infection_state = if random_percentage(&mut param.seed) < param.attack.percentage_vuln_devices {
+            InfectionState::Vulnerable
+        } else {
+            InfectionState::NotVulnerable
+        };
+
+        Ok(Self {
+            kind: AgentKind::HeatPump,
+            index,
+            step: Steps(0),
+            children: vec![],
+            powerstate: PowerState::new(),
+            infection_state,
+            electrical_power: hp_param.electrical_power,
+            cop: hp_param.cop,
+            setpoint,
+            deadband: hp_param.deadband,
+            resistance,
+            capacitance,
+            indoor_temperature: setpoint,
+            heating: false,
+            clean_indoor_temperature: setpoint,
+            clean_heating: false,
+        })
+    }
+
+    /// Thermostat with a deadband. Switches on below and off above the deadband, otherwise keeps its state.
+    fn thermostat(&self, temperature: BaseFloat, heating: bool, setpoint: BaseFloat) -> bool {
+        if temperature < setpoint - self.deadband / 2.0 {
+            true
+        } else if temperature > setpoint + self.deadband / 2.0 {
+            false
+        } else {
+            heating
+        }
+    }
+
+    /// Indoor temperature after a single step of the RC model.
+    fn next_temperature(&self, temperature: BaseFloat, heating: bool, outdoor: BaseFloat) -> BaseFloat {
+        let seconds = (Steps::minutes_per_step().0 * 60) as BaseFloat;
+        let heat = if heating {
+            self.electrical_power.0 as BaseFloat * self.cop
+        } else {
+            0.0
+        };
+        let loss = (temperature - outdoor) / self.resistance;
+        (seconds / self.capacitance).mul_add(heat - loss, temperature)
+    }
+
+    /// Run the heat pump for this step given the outdoor temperature. Infected heat pumps follow the command of the
+    /// attacker, if given. The reported power is always that of the clean controller.
+    pub fn run(&mut self, outdoor: BaseFloat, command: Option<HeatPumpCommand>) {
+        self.clean_heating = self.thermostat(self.clean_indoor_temperature, self.clean_heating, self.setpoint);
+        self.heating = match command {
+            Some(command) if self.infection_state == InfectionState::Infected => match command {
+                HeatPumpCommand::Setpoint(setpoint) => self.thermostat(self.indoor_temperature, self.heating, setpoint),
+                HeatPumpCommand::ForceOn => true,
+                HeatPumpCommand::ForceOff => false,
+            },
+            _ => self.thermostat(self.indoor_temperature, self.heating, self.setpoint),
+        };
+        self.clean_indoor_temperature =
+            self.next_temperature(self.clean_indoor_temperature, self.clean_heating, outdoor);
+        self.indoor_temperature = self.next_temperature(self.indoor_temperature, self.heating, outdoor);
+
+        let used = if self.heating { self.electrical_power } else { Watt(0) };
+        let reported = if self.clean_heating {
+            self.electrical_power
+        } else {
+            Watt(0)
+        };
+        self.powerstate.power_used = used;
+        self.powerstate.power_generated = Watt(0);
+        self.powerstate.power_reported = reported;
+        self.powerstate.power_error = reported - used;
+    }
+}
+
+impl AgentTrait for HeatPump {
+    fn kind(&self) -> &AgentKind { &self.kind }
+
+    fn step(&self) -> &Steps { &self.step }
+
+    fn index(&self) -> &BaseUint { &self.index }
+
+    fn powerstate(&self) -> &PowerState { &self.powerstate }
+
+    fn children(&self) -> &AgentList { &self.children }
+
+    fn kind_mut(&mut self) -> &mut super::AgentKind { &mut self.kind }
+
+    fn index_mut(&mut self) -> &mut crate::util::BaseUint { &mut self.index }
+
+    fn step_mut(&mut self) -> &mut crate::util::Steps { &mut self.step }
+
+    fn children_mut(&mut self) -> &mut super::AgentList { &mut self.children }
+
+    fn powerstate_mut(&mut self) -> &mut crate::grid::PowerState { &mut self.powerstate }
+
+    fn update_step(&mut self, step: crate::util::Steps) { self.step = step; }
+
+    fn get_json(&self) -> Result<Vec<u8>, crate::util::ModelError> {
+        match serde_json::to_vec(&self) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(crate::util::ModelError::LogStateErrorJson {
+                agent:  self.to_string(),
+                source: e,
+            }),
+        }
+    }
+
+    fn get_avro(&self) -> Result<(apache_avro::Schema, apache_avro::types::Value), crate::util::ModelError> {
+        let schema = Self::get_schema();
+        match apache_avro::to_value(self) {
+            Ok(v) => Ok((schema, v)),
+            Err(e) => Err(crate::util::ModelError::LogStateErrorAvro {
+                agent:  self.to_string(),
+                source: e,
+            }),
+        }
+    }
+}
+
+impl Debug for HeatPump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        if f.sign_plus() {
+            f.debug_struct("HeatPump")
+                .field("Kind", &self.kind)
+                .field("Step", &self.step)
+                .field("Index", &self.index)
+                .field("Indoor", &self.indoor_temperature)
+                .field("Heating", &self.heating)
+                .field("PowerState", &self.powerstate)
+                .finish_non_exhaustive()
+        } else {
+            f.debug_struct("Agent")
+                .field("Kind", &self.kind)
+                .field("Step", &self.step)
+                .field("Index", &self.index)
+                .finish_non_exhaustive()
+        }
+    }
+}
+
+impl Display for HeatPump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        let powerstate = self.powerstate.power_used.to_string();
+        f.debug_struct("HeatPump")
+            .field("Kind", &self.kind)
+            .field("Index", &self.index)
+            .field("Step", &self.step)
+            .field("PowerState", &powerstate)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod heat_pump_tests {
+    use super::*;
+
+    fn test_heat_pump() -> HeatPump {
+        HeatPump {
+            kind:                     AgentKind::HeatPump,
+            index:                    117,
+            step:                     Steps(0),
+            children:                 vec![],
+            powerstate:               PowerState::new(),
+            infection_state:          InfectionState::Infected,
+            electrical_power:         Watt(2_000),
+            cop:                      3.0,
+            setpoint:                 20.0,
+            deadband:                 1.0,
+            resistance:               0.005,
+            capacitance:              20_000_000.0,
+            indoor_temperature:       20.0,
+            heating:                  false,
+            clean_indoor_temperature: 20.0,
+            clean_heating:            false,
+        }
+    }
+
+    #[test]
+    fn thermostat_cycles() {
+        let mut hp = test_heat_pump();
+        let mut switched_on = false;
+        for _ in 0..50 {
+            hp.run(0.0, None);
+            switched_on |= hp.heating;
+            assert!(hp.indoor_temperature > 19.0 && hp.indoor_temperature < 21.0);
+        }
+        assert!(switched_on);
+        assert_eq!(hp.powerstate.power_error, Watt(0));
+    }
+
+    #[test]
+    fn attacked_heat_pump() {
+        let mut hp = test_heat_pump();
+        hp.run(0.0, Some(HeatPumpCommand::ForceOn));
+        assert_eq!(hp.powerstate.power_used, Watt(2_000));
+        assert_eq!(hp.powerstate.power_reported, Watt(0));
+        assert!(hp.indoor_temperature > hp.clean_indoor_temperature);
+
+        hp.run(0.0, Some(HeatPumpCommand::Setpoint(15.0)));
+        assert_eq!(hp.powerstate.power_used, Watt(0));
+    }
+}
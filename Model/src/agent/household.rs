@@ -3,16 +3,17 @@ use std::fmt::{Debug, Display};
 use apache_avro::AvroSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::agent::{AgentKind, AgentList, AgentTrait, BatteryRef};
 #[allow(unused_imports)]
-use crate::agent::Netstation;
-use crate::agent::{AgentKind, AgentList, AgentTrait};
-use crate::grid::{PowerGeneration, PowerState};
-use crate::util::{BaseUint, Steps, Watt};
+use crate::agent::{Battery, EvCharger, HeatPump, Netstation};
+use crate::grid::{PowerGeneration, PowerState, SupplyState};
+use crate::util::{BaseUint, Steps, UtilError, Watt};
 /// The Household agent. A level below [Netstation] and lowest on the grid.
 ///
 /// An Household is a representation of a single house that is connected to the powergrid. It can have a PV system to
-/// generate its own power. These [PowerGeneration] devices can be vulnerable to a cyber attack and infect the grid as
-/// a result of an attack.
+/// generate its own power and a [Battery] to store it. These devices can be vulnerable to a cyber attack and infect the
+/// grid as a result of an attack. The children of a household are its [Battery], [EvCharger] and [HeatPump] agents, if
+/// it has any.
 #[derive(Clone, Serialize, AvroSchema, Deserialize)]
 pub struct Household {
     /// Kind of agent.
@@ -30,6 +31,12 @@ pub struct Household {
     /// A [Household] is the only agent that generates power using a DER / PV.
     /// This field contains that power generation unit.
     pub power_generation: PowerGeneration,
+    #[serde(skip)]
+    #[avro(skip)]
+    /// The home battery of the household, if it has one. The battery is also one of the children.
+    pub battery:          Option<BatteryRef>,
+    /// Is the household energised and is its PV installation connected?
+    pub supply:           SupplyState,
 }
 
 impl Household {
@@ -48,16 +55,33 @@ impl Household {
             children: vec![],
             powerstate: PowerState::new(),
             power_generation,
+            battery: None,
+            supply: SupplyState::default(),
         }
     }
 
     /// Calculates the power state of the agent, without it being impacted by a cyber attack.
-    pub fn clean_power_gen(&mut self) {
-        let (generated, used, reported) = self.power_generation.calc_power(&self.step);
+    pub fn clean_power_gen(&mut self) -> Result<(), UtilError> {
+        let (generated, used, reported) = self.power_generation.calc_power(&self.step)?;
         self.powerstate.power_used = used;
         self.powerstate.power_generated = generated;
         self.powerstate.power_reported = reported;
         self.powerstate.power_error = Watt(0);
+        Ok(())
+    }
+
+    /// Removes the generation of the PV installation if it is not connected. If the operator expects it to be
+    /// disconnected, the generation is no longer part of the reported power either.
+    pub fn disconnect_pv(&mut self) {
+        let generated = self.powerstate.power_generated;
+        if !self.supply.pv_clean_connected {
+            self.powerstate.power_reported += generated;
+        }
+        if !self.supply.pv_connected {
+            self.powerstate.power_generated = Watt(0);
+        }
+        self.powerstate.power_error =
+            self.powerstate.power_reported - (self.powerstate.power_used - self.powerstate.power_generated);
     }
 }
 
@@ -116,6 +140,7 @@ impl Debug for Household {
             f.debug_struct("Household")
                 .field("Kind", &self.kind)
                 .field("PV", &self.power_generation.generation_param.is_empty())
+                .field("Battery", &self.battery.is_some())
                 .field("Step", &self.step)
                 .field("Index", &self.index)
                 .field("Children", &self.children)
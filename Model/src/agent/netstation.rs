@@ -3,10 +3,10 @@ use std::fmt::{Debug, Display};
 use apache_avro::AvroSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::agent::{AgentKind, AgentList, AgentTrait};
+use crate::agent::{AgentKind, AgentList, AgentTrait, HouseholdList};
 #[allow(unused_imports)]
 use crate::agent::{Area, Household};
-use crate::grid::{Boundaries, BoundaryAgentTrait, Grid, GridState, GridWarning, InfectionState, InfectionStatistics, PowerState, VoltState};
+use crate::grid::{Boundaries, BoundaryAgentTrait, Grid, GridState, GridWarning, PowerState, VoltState};
 use crate::util::{mVolt, BaseUint, Steps};
 
 /// The Netstation agent. A level below [Area] and one above [Household].
@@ -32,6 +32,12 @@ pub struct Netstation {
     pub volt_boundary: Boundaries<mVolt>,
     /// The current voltage and history of voltage within the agent.    
     pub volt_state:    VoltState,
+    #[serde(skip)]
+    #[avro(skip)]
+    /// The households connected to the netstation.
+    pub households:    HouseholdList,
+    /// Is the netstation energised?
+    pub energised:     bool,
 }
 
 impl Netstation {
@@ -51,17 +57,23 @@ impl Netstation {
             powerstate: PowerState::new(),
             volt_boundary: Boundaries::<mVolt>::from(grid_param),
             volt_state: VoltState::new(grid_param),
+            households: vec![],
+            energised: true,
         }
     }
 
-    /// Get the infection statistics for the children of the netstation
-    pub fn get_infection_state_children(&self) -> InfectionStatistics {
-        let states: Vec<InfectionState> = self
-            .children()
+    /// The netstation and its households lose their supply.
+    pub fn de_energise(&mut self) {
+        self.energised = false;
+        self.households.iter().for_each(|h| h.write_arc().supply.de_energise());
+    }
+
+    /// The netstation and its households are energised again. The PV installations may reconnect after the delay.
+    pub fn energise(&mut self, step: Steps, pv_reconnect_delay: Steps) {
+        self.energised = true;
+        self.households
             .iter()
-            .filter_map(|c| c.read_arc_recursive().power_gen().map(|pg| pg.infection_state))
-            .collect();
-        InfectionStatistics::new(&states)
+            .for_each(|h| h.write_arc().supply.energise(step, pv_reconnect_delay));
     }
 }
 
@@ -71,7 +83,6 @@ impl BoundaryAgentTrait<mVolt> for Netstation {
             gw.agent_index = Some(self.index);
             gw.agent_powerstate = Some(self.powerstate.clone());
             gw.volt_state = Some(self.volt_state.clone());
-            gw.infectionstatistics = Some(self.get_infection_state_children());
             Some(gw)
         } else {
             None
@@ -100,6 +111,14 @@ impl AgentTrait for Netstation {
 
     fn powerstate_mut(&mut self) -> &mut crate::grid::PowerState { &mut self.powerstate }
 
+    /// A netstation that is not energised has no power.
+    fn set_power_from_children(&mut self, sum: &PowerState) {
+        self.powerstate.manual_set(sum);
+        if !self.energised {
+            self.powerstate.de_energise();
+        }
+    }
+
     fn update_step(&mut self, step: crate::util::Steps) { self.step = step; }
 
     fn get_json(&self) -> Result<Vec<u8>, crate::util::ModelError> {
@@ -57,8 +57,17 @@ impl Root {
 }
 
 impl BoundaryAgentTrait<mHz> for Root {
+    /// Checks both the level of the frequency and its rate of change. A critical warning is preferred.
     fn boundary_check(&mut self) -> Option<GridWarning> {
-        if let Some(mut gw) = self.freq_boundary.update(self.freq_state.now) {
+        let level = self.freq_boundary.update(self.freq_state.now);
+        let rate = self
+            .freq_boundary
+            .check_rate(self.freq_state.now, &self.freq_state.history);
+        let warning = match (level, rate) {
+            (Some(level), Some(rate)) if rate.critical && !level.critical => Some(rate),
+            (level, rate) => level.or(rate),
+        };
+        if let Some(mut gw) = warning {
             gw.agent_index = Some(self.index);
             gw.agent_powerstate = Some(self.powerstate.clone());
             gw.freq_state = Some(self.freq_state.clone());
@@ -3,21 +3,17 @@
 //! Both structures referenced in this module are created by the AttackParameters file given to the executable via the
 //! ModelParameters file.
 
-use std::sync::Arc;
-
-use parking_lot::RwLock;
-use rand::{rngs::SmallRng};
 // #[cfg(feature = "multi_thread")]
-use rayon::prelude::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 
-use crate::{agent::{AgentTrait, Household}, util::default_smallrng};
+use crate::agent::{AgentTrait, Battery, BatteryCommand, EvCharger, EvCommand, HeatPump, HeatPumpCommand, Household};
 use crate::grid::InfectionState;
 #[allow(unused_imports)]
 use crate::grid::PowerGeneration;
 // #[cfg(feature = "single_thread")]
 // use crate::norayon::prelude::*;
-use crate::util::{random_percentage, BaseFloat, Steps};
+use crate::util::{random_percentage, BaseFloat, BaseUint, RngPurpose, RngStreams, Steps};
 
 /// Information on the attack and patching behaviour during the model.
 ///
@@ -25,70 +21,98 @@ use crate::util::{random_percentage, BaseFloat, Steps};
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Attack {
     /// Percentage of [PowerGeneration] units that are vulnerable to an [Attack]
-    pub percentage_vuln_devices: f32,
+    pub percentage_vuln_devices:  f32,
     /// Percentage of infections per step.
-    pub infection_rate_per_step: f32,
+    pub infection_rate_per_step:  f32,
     /// Percentage of patched devices per step.
-    pub patch_rate_per_step:     f32,
+    pub patch_rate_per_step:      f32,
     /// Starting step of the infection.
-    pub infection_start:         Steps,
+    pub infection_start:          Steps,
     /// Last step of the infection.
-    pub infection_stop:          Steps,
+    pub infection_stop:           Steps,
     /// Starting step of patching.
-    pub patch_start:             Steps,
+    pub patch_start:              Steps,
     /// Last step of patching.
-    pub patch_stop:              Steps,
+    pub patch_stop:               Steps,
     /// A list of behaviours that are exhibited by infected units.
-    pub attack_behaviour:        Vec<AttackBehaviour>,
-    #[serde(skip)]
-    #[serde(default = "default_smallrng")]
-    /// A seed to power the rng generators that are used to determine if an agent is impacted.
-    pub seed:                    Arc<RwLock<SmallRng>>,
+    pub attack_behaviour:         Vec<AttackBehaviour>,
+    /// A list of commands that are given to infected batteries.
+    pub battery_behaviour:        Vec<BatteryAttack>,
+    /// A list of commands that are given to infected EV chargers.
+    pub ev_behaviour:             Vec<EvAttack>,
+    /// A list of commands that are given to infected heat pumps.
+    pub heat_pump_behaviour:      Vec<HeatPumpAttack>,
+    /// A list of periods in which infected PV inverters ignore the reconnection rules.
+    pub reconnect_behaviour:      Vec<ReconnectAttack>,
+    /// The streams of random numbers that are used to determine if an agent is patched or infected. Each agent draws
+    /// from its own stream at each step.
+    pub streams:                  RngStreams,
     /// Out of the list of behaviours, defined in [AttackBehaviour], what is the current active one?
     /// If one is active at all.
-    pub current_attack:          Option<AttackBehaviour>,
+    pub current_attack:           Option<AttackBehaviour>,
+    /// Out of the list of battery commands, defined in [BatteryAttack], what is the current active one?
+    /// If one is active at all.
+    pub current_battery_attack:   Option<BatteryAttack>,
+    /// Out of the list of EV charger commands, defined in [EvAttack], what is the current active one?
+    /// If one is active at all.
+    pub current_ev_attack:        Option<EvAttack>,
+    /// Out of the list of heat pump commands, defined in [HeatPumpAttack], what is the current active one?
+    /// If one is active at all.
+    pub current_heat_pump_attack: Option<HeatPumpAttack>,
+    /// Out of the list of reconnection periods, defined in [ReconnectAttack], what is the current active one?
+    /// If one is active at all.
+    pub current_reconnect_attack: Option<ReconnectAttack>,
 }
 
-
 impl Attack {
-    /// Tries to patch vulnerable or infected [`PowerGeneration`] units. Checks
-    /// for [`InfectionState`] within.
+    /// Tries to patch or infect the [`PowerGeneration`] unit of the household, if an attack is active. Checks for
+    /// [`InfectionState`] within.
+    #[inline]
+    pub fn try_to_patch_and_infect(&self, household: &mut Household, step: Steps) {
+        if self.current_attack.is_some() {
+            let (will_patch, will_infect) = self.patch_and_infect_draws(household.index, step);
+            let state = household.power_generation.infection_state;
+            household.power_generation.infection_state = state.progress(will_infect, will_patch);
+        }
+    }
+
+    /// Tries to patch or infect the home battery, if a battery attack is active. Checks for [`InfectionState`] within.
+    #[inline]
+    pub fn try_to_patch_and_infect_battery(&self, battery: &mut Battery, step: Steps) {
+        if self.current_battery_attack.is_some() {
+            let (will_patch, will_infect) = self.patch_and_infect_draws(battery.index, step);
+            battery.infection_state = battery.infection_state.progress(will_infect, will_patch);
+        }
+    }
+
+    /// Tries to patch or infect the EV charger, if an EV attack is active. Checks for [`InfectionState`] within.
+    #[inline]
+    pub fn try_to_patch_and_infect_ev_charger(&self, charger: &mut EvCharger, step: Steps) {
+        if self.current_ev_attack.is_some() {
+            let (will_patch, will_infect) = self.patch_and_infect_draws(charger.index, step);
+            charger.infection_state = charger.infection_state.progress(will_infect, will_patch);
+        }
+    }
+
+    /// Tries to patch or infect the heat pump, if a heat pump attack is active. Checks for [`InfectionState`] within.
     #[inline]
-    pub fn try_to_patch_and_infect(&mut self, hh: &Vec<Arc<RwLock<Household>>>) {
-        if self.current_attack.is_none() {
-            return;
+    pub fn try_to_patch_and_infect_heat_pump(&self, heat_pump: &mut HeatPump, step: Steps) {
+        if self.current_heat_pump_attack.is_some() {
+            let (will_patch, will_infect) = self.patch_and_infect_draws(heat_pump.index, step);
+            heat_pump.infection_state = heat_pump.infection_state.progress(will_infect, will_patch);
         }
-        let will_patch_will_infect: Vec<(bool, bool)> = (0..hh.len())
-            .into_iter()
-            .map(|_| {
-                let seed = &mut self.seed.write_arc();
-                (
-                    random_percentage(seed) < self.patch_rate_per_step,
-                    random_percentage(seed) < self.infection_rate_per_step,
-                )
-            })
-            .collect();
-
-        hh.par_iter()
-            .zip(will_patch_will_infect)
-            .for_each(|(h, (will_patch, will_infect))| {
-                let mut house = h.write_arc();
-                match (house.power_generation.infection_state, will_infect, will_patch) {
-                    (InfectionState::NotVulnerable, ..) => (),
-                    (InfectionState::Patched, ..) => (),
-                    (InfectionState::Infected, _, true) => {
-                        house.power_generation.infection_state = InfectionState::Patched;
-                    }
-                    (InfectionState::Vulnerable, _, true) => {
-                        house.power_generation.infection_state = InfectionState::Patched;
-                    }
-                    (InfectionState::Infected, _, false) => (),
-                    (InfectionState::Vulnerable, true, _) => {
-                        house.power_generation.infection_state = InfectionState::Infected;
-                    }
-                    (InfectionState::Vulnerable, false, false) => (),
-                }
-            })
+    }
+
+    /// Draw whether the device with the given index will be patched and whether it will be infected at the given step.
+    /// The draws only depend on the index and step, not on the order in which the devices are handled.
+    #[inline]
+    fn patch_and_infect_draws(&self, index: BaseUint, step: Steps) -> (bool, bool) {
+        (
+            random_percentage(&mut self.streams.stream_at(RngPurpose::Patching, index, step))
+                < self.patch_rate_per_step,
+            random_percentage(&mut self.streams.stream_at(RngPurpose::Infection, index, step))
+                < self.infection_rate_per_step,
+        )
     }
 
     /// Check if in the list of behaviours an attack is present and if so, set it in the field of the struct. This field
@@ -101,6 +125,26 @@ impl Attack {
             .par_iter()
             .find_first(|ab| ab.is_active(step))
             .copied();
+        self.current_battery_attack = self
+            .battery_behaviour
+            .par_iter()
+            .find_first(|ba| ba.is_active(step))
+            .copied();
+        self.current_ev_attack = self
+            .ev_behaviour
+            .par_iter()
+            .find_first(|ea| ea.is_active(step))
+            .copied();
+        self.current_heat_pump_attack = self
+            .heat_pump_behaviour
+            .par_iter()
+            .find_first(|ha| ha.is_active(step))
+            .copied();
+        self.current_reconnect_attack = self
+            .reconnect_behaviour
+            .par_iter()
+            .find_first(|ra| ra.is_active(step))
+            .copied();
     }
 
     #[inline]
@@ -109,17 +153,54 @@ impl Attack {
     /// is used by the infected agents to modify their behaviour.
     pub fn check_current_attack(&mut self, step: Steps) {
         self.current_attack = self.attack_behaviour.par_iter().find(|ab| ab.is_active(step)).copied();
+        self.current_battery_attack = self.battery_behaviour.par_iter().find(|ba| ba.is_active(step)).copied();
+        self.current_ev_attack = self.ev_behaviour.par_iter().find(|ea| ea.is_active(step)).copied();
+        self.current_heat_pump_attack = self
+            .heat_pump_behaviour
+            .par_iter()
+            .find(|ha| ha.is_active(step))
+            .copied();
+        self.current_reconnect_attack = self
+            .reconnect_behaviour
+            .par_iter()
+            .find(|ra| ra.is_active(step))
+            .copied();
     }
 
-    /// Modify the infected households using the attack behaviour.
+    /// Modify the power of the household using the attack behaviour, if it is infected.
     #[inline]
-    pub fn modify_infected_devices(&self, hh: &Vec<Arc<RwLock<Household>>>) {
+    pub fn modify_infected_device(&self, household: &mut Household) {
         if let Some(modifier) = &self.current_attack {
-            hh.par_iter()
-                .filter(|h| h.read_arc_recursive().power_generation().infection_state == InfectionState::Infected)
-                .for_each(|h| h.write_arc().powerstate_mut().attack(*modifier));
+            if household.power_generation().infection_state == InfectionState::Infected {
+                household.powerstate_mut().attack(*modifier);
+            }
         }
     }
+
+    /// The command infected batteries follow, if a battery attack is active. All other batteries use
+    /// self-consumption.
+    #[inline]
+    pub fn battery_command(&self) -> Option<BatteryCommand> { self.current_battery_attack.map(|ba| ba.command) }
+
+    /// The command infected EV chargers follow, if an EV attack is active. All other chargers charge as planned.
+    #[inline]
+    pub fn ev_command(&self) -> Option<EvCommand> { self.current_ev_attack.map(|ea| ea.command) }
+
+    /// Reconnect the PV installation of the household if the reconnection rules allow it. An infected inverter
+    /// reconnects directly if a reconnection attack is active.
+    #[inline]
+    pub fn reconnect_pv(&self, household: &mut Household, step: Steps, frequency_allows: bool) {
+        if !household.supply.pv_clean_connected {
+            let bypass = self.current_reconnect_attack.is_some()
+                && household.power_generation.infection_state == InfectionState::Infected;
+            household.supply.reconnect_pv(step, frequency_allows, bypass);
+        }
+    }
+
+    /// The command infected heat pumps follow, if a heat pump attack is active. All other heat pumps follow the
+    /// outdoor temperature.
+    #[inline]
+    pub fn heat_pump_command(&self) -> Option<HeatPumpCommand> { self.current_heat_pump_attack.map(|ha| ha.command) }
 }
 
 /// A step in the behaviour of the attacker. Is generated from the same attack parameters as the attack.
@@ -139,3 +220,66 @@ impl AttackBehaviour {
     /// Is this substep active on this step? Does not include the end step.
     pub fn is_active(&self, step: Steps) -> bool { step >= self.begin && step <= self.end }
 }
+
+/// A command of the attacker to all infected batteries. Is generated from the same attack parameters as the attack.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatteryAttack {
+    /// When does the command begin? Inclusive.
+    pub begin:   Steps,
+    /// When does the command end? Not inclusive.
+    pub end:     Steps,
+    /// The command given to the infected batteries.
+    pub command: BatteryCommand,
+}
+
+impl BatteryAttack {
+    /// Is this command active on this step? Does not include the end step.
+    pub fn is_active(&self, step: Steps) -> bool { step >= self.begin && step < self.end }
+}
+
+/// A command of the attacker to all infected EV chargers. Is generated from the same attack parameters as the attack.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvAttack {
+    /// When does the command begin? Inclusive.
+    pub begin:   Steps,
+    /// When does the command end? Not inclusive.
+    pub end:     Steps,
+    /// The command given to the infected EV chargers.
+    pub command: EvCommand,
+}
+
+impl EvAttack {
+    /// Is this command active on this step? Does not include the end step.
+    pub fn is_active(&self, step: Steps) -> bool { step >= self.begin && step < self.end }
+}
+
+/// A command of the attacker to all infected heat pumps. Is generated from the same attack parameters as the attack.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HeatPumpAttack {
+    /// When does the command begin? Inclusive.
+    pub begin:   Steps,
+    /// When does the command end? Not inclusive.
+    pub end:     Steps,
+    /// The command given to the infected heat pumps.
+    pub command: HeatPumpCommand,
+}
+
+impl HeatPumpAttack {
+    /// Is this command active on this step? Does not include the end step.
+    pub fn is_active(&self, step: Steps) -> bool { step >= self.begin && step < self.end }
+}
+
+/// A period in which infected PV inverters ignore the reconnection rules after a blackout. Is generated from the same
+/// attack parameters as the attack.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReconnectAttack {
+    /// When does the period begin? Inclusive.
+    pub begin: Steps,
+    /// When does the period end? Not inclusive.
+    pub end:   Steps,
+}
+
+impl ReconnectAttack {
+    /// Is this period active on this step? Does not include the end step.
+    pub fn is_active(&self, step: Steps) -> bool { step >= self.begin && step < self.end }
+}
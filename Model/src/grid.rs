@@ -13,9 +13,15 @@
 //! - Boundary values.    
 //!   For both frequency and voltage, the allowed limits are given.   
 //!   These are extracted from EU regulations.  
+//!   Areas are monitored on the share of their netstations outside the normal voltage band, if limits are given.  
 //! - Number of agents per layer.    
-//!   How many agents are needed per layer? Currently, no connections are made on a horizontal level.  
-//!   So this is the number of children per node.
+//!   How many agents are needed per layer? This is the number of children per node.
+//! - Lines.    
+//!   Optionally, each netstation and each area is fed by a line with a capacity. Power flowing over a [Connection]
+//!   is partly lost, and a flow above the capacity overloads the line.
+//! - Horizontal links.    
+//!   Optionally, netstations within an area and the areas themselves are linked in a ring by [Connection] agents.  
+//!   The density is the chance that two neighbours in the ring are linked.
 //! - PV adoption.    
 //!   The number of households that have a PV installation at the home.  
 //!   This may or may not be infectable.  
@@ -29,30 +35,98 @@
 //! To let each house be unique the standardized profile is adjusted with noise functions and a linear modifier. This
 //! gives dynamic in the grid.
 //!
+//! Optionally, the consumption follows the standard profiles of MFFBAS directly, see [StandardProfiles]. Each household
+//! is assigned a category of profiles and consumes its average power usage over the year.
+//!
+//! Optionally, the consumption is built bottom-up from the appliances of each household, see [ApplianceLoad].
+//! Appliances are switched on by chance when someone is at home, which gives a spiky load and diversity among the
+//! households.
+//!
+//! Optionally, households replay measured time series of smart meters instead, see [MeterData]. A household samples a
+//! meter, which determines whether it has a PV installation.
+//!
+//! Optionally, the noise is a stochastic process instead of a sum of sine functions, see [NoiseProcess]. It is not
+//! periodic, so no two days are the same, and the noise of households under the same netstation can be correlated.
+//!
+//! # Energy storage
+//! Optionally, households have a home [Battery] as a child in the graph, next to their PV installation. It is used for
+//! self-consumption by default, but can be infected as well. Infected batteries charge or discharge with full power at
+//! the command of the attacker, which doubles the controllable swing of a household compared to PV alone.
+//!
+//! # Electric vehicles
+//! Optionally, households have an [EvCharger] as a child in the graph. The vehicle arrives and departs each day and is
+//! charged immediately or with a smart schedule in between. The chargers are managed through cloud platforms, so an
+//! attacker can start or stop all infected chargers at once.
+//!
+//! # Heat pumps
+//! Optionally, households have a [HeatPump] as a child in the graph. Its consumption follows a thermal model of the
+//! house, driven by the [OutdoorTemperature] and the setpoint of the thermostat. An attacker can change the setpoint of
+//! infected heat pumps or run them in sync.
+//!
+//! # Power sharing
+//! Over a closed [Connection] both ends share their power mismatch, up to the capacity of the line. Sharing is done
+//! while the power states are combined, between netstations before the areas are summed and between areas before the
+//! root is summed, so the areas and the root include it. The losses over a link are consumed by the receiving end. As
+//! netstations are only linked within an area, sharing between netstations only changes the mismatch of an area by
+//! its losses, and the same holds for the root.
+//!
 //! # Power mismatch
 //! When there is too much or too little power being generated with regard to the power being consumed this has an
 //! impact on the voltage and frequency of the grid. This is monitored by the [Root] and [Netstation] agents.
+//!
+//! # Conventional generation
+//! Optionally, a fleet of conventional power plants is part of the grid, see [Generator]. The fleet is scheduled in
+//! merit order to follow the forecasted demand, and balances the power mismatch measured in the previous step on top
+//! of that, e.g. the mismatch caused by an attack. It can only change its output within the ramp rates of the plants.
+//!
+//! # Synchronous area
+//! Optionally, the grid is part of a larger synchronous area. The rest of the continental grid is then modelled as an
+//! aggregated external system connected through tie-lines, see [SynchronousArea]. It absorbs part of the power
+//! mismatch in response to the frequency deviation, limited by the capacity of the tie-lines.
+//!
+//! # Blackout and restoration
+//! Optionally, a critical frequency error causes a blackout when the model does not stop on it. All netstations are
+//! de-energised and restored after a black start, see [RestorationParam]. PV installations disconnect and reconnect
+//! according to the rules of their inverters. The time until all netstations are energised again is the recovery
+//! time.
 
+mod appliance;
 mod boundary;
 mod gridstate;
 mod gridwarning;
+mod noise;
 mod powergeneration;
+mod profile;
+mod replay;
 mod reservepower;
+mod restoration;
 mod states;
+mod synchronousarea;
+mod topology;
+mod weather;
 
 use std::fmt::Debug;
 
+pub use appliance::*;
 pub use boundary::*;
 pub use gridstate::*;
 pub use gridwarning::*;
+pub use noise::*;
 pub use powergeneration::*;
+pub use profile::*;
+pub use replay::*;
 pub use reservepower::*;
+pub use restoration::*;
 use serde::{Serialize, Deserialize};
 pub use states::*;
+pub use synchronousarea::*;
+pub use topology::*;
+pub use weather::*;
 
 #[allow(unused_imports)]
-use crate::agent::{Netstation, Root};
-use crate::util::{mHz, mVolt, BaseFloat, BaseInt, Watt};
+use crate::agent::{Battery, Connection, EvCharger, Generator, HeatPump, Netstation, Root};
+use crate::agent::{EvChargerParam, GeneratorParam, HeatPumpParam};
+use crate::util::{mHz, mPercent, mVolt, BaseFloat, BaseInt, Watt, WattHour};
 
 /// Struct that defines the grid parameters
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -63,6 +137,9 @@ pub struct Grid {
     pub energy_storage:                           Watt,
     /// Max energy generation in W per time tick
     pub max_gen_inc_tick:                         Watt,
+    /// The imported topology of the grid. The areas, netstations and households are drawn at random if none is given.
+    /// See [Topology].
+    pub topology:                                 Option<Topology>,
     /// Bounds for the number of netstation per
     /// area. Intended to be used with a uniform
     /// distribution
@@ -74,8 +151,47 @@ pub struct Grid {
     /// Percentage of PV adoption by households
     /// i.e. chance of a household having a PV.
     pub pv_adoption:                              BaseFloat,
+    /// Percentage of battery adoption by households
+    /// i.e. chance of a household having a [Battery].
+    pub battery_adoption:                         BaseFloat,
+    /// Storage capacity of a home [Battery].
+    pub battery_capacity:                         WattHour,
+    /// Maximum charge and discharge power of a home [Battery].
+    pub battery_power:                            (Watt, Watt),
+    /// Capacity of the line that feeds each netstation. No lines are added if zero.
+    pub ns_line_capacity:                         Watt,
+    /// Capacity of the line that feeds each area. No lines are added if zero.
+    pub area_line_capacity:                       Watt,
+    /// Fraction of the capacity that is lost over a [Connection] when the flow equals the capacity.
+    pub line_loss_factor:                         BaseFloat,
+    /// Chance that two neighbouring netstations in an area are linked by a [Connection].
+    pub ns_link_density:                          BaseFloat,
+    /// Capacity of a [Connection] between netstations.
+    pub ns_link_capacity:                         Watt,
+    /// Chance that two neighbouring areas are linked by a [Connection].
+    pub area_link_density:                        BaseFloat,
+    /// Capacity of a [Connection] between areas.
+    pub area_link_capacity:                       Watt,
+    /// The parameters of the [EvCharger] agents. No household has an EV charger if none are given.
+    pub ev_charger:                               Option<EvChargerParam>,
+    /// The parameters of the [HeatPump] agents. No household has a heat pump if none are given.
+    pub heat_pump:                                Option<HeatPumpParam>,
+    /// The outdoor temperature that drives the [HeatPump] agents.
+    pub outdoor_temperature:                      OutdoorTemperature,
+    /// The restoration after a blackout. Without it, a critical frequency error does not cause a blackout.
+    pub restoration:                              Option<RestorationParam>,
     /// Amount of noise functions per [PowerGeneration] unit.
     pub num_noise_functions:                      BaseInt,
+    /// The standard profiles that drive the consumption of the [PowerGeneration] units. The consumption is a sum of
+    /// sine functions if none are given.
+    pub profiles:                                 Option<StandardProfiles>,
+    /// The appliances that build the consumption of the [PowerGeneration] units bottom-up. The consumption follows the
+    /// profiles if none are given.
+    pub appliances:                               Option<ApplianceParam>,
+    /// The measured time series that are replayed by the [PowerGeneration] units, instead of the profiles and noise.
+    pub replay:                                   Option<MeterData>,
+    /// The stochastic noise of the [PowerGeneration] units. The noise is a sum of sine functions if none is given.
+    pub noise:                                    Option<NoiseParam>,
     /// The normal distribution parameters that determine [PowerGeneration] units.
     /// First is center, second value is std deviation.
     pub household_power_consumption_distribution: (Watt, Watt),
@@ -86,8 +202,14 @@ pub struct Grid {
     pub percentage_generation_of_usage:           f32,
     /// Bulk consumption, i.e. factories
     pub bulk_consumption:                         Watt,
+    /// The conventional generation fleet. Each entry becomes a [Generator] agent.
+    pub generators:                               Vec<GeneratorParam>,
     /// Impact of voltage due to power mismatch. Linear assumption.
     pub volt_modifier:                            BaseFloat,
+    /// The synchronous area the grid is connected to with tie-lines. The grid is isolated if none is given.
+    pub synchronous_area:                         Option<SynchronousArea>,
     pub(crate) volt_boundary:                     Boundaries<mVolt>,
     pub(crate) freq_boundary:                     Boundaries<mHz>,
+    /// Boundaries on the share of netstations in an area that are outside the normal voltage band.
+    pub(crate) area_boundary:                     Boundaries<mPercent>,
 }
@@ -0,0 +1,242 @@
+use apache_avro::AvroSchema;
+use rand::rngs::SmallRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+#[allow(unused_imports)]
+use crate::agent::Household;
+use crate::util::{norm_dist, random_percentage, BaseFloat, BaseInt, RngStreams, Steps, UtilError, Watt};
+
+/// Is anyone at home, and awake?
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, AvroSchema)]
+pub enum Occupancy {
+    /// Someone is at home and awake. Appliances can be switched on.
+    Home,
+    /// Nobody is at home.
+    Away,
+    /// Everybody is asleep.
+    Asleep,
+}
+
+/// An appliance of a household, e.g. a washing machine or a cooker. Given by the GridParameters file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, AvroSchema)]
+pub struct Appliance {
+    /// Power of the appliance while it runs.
+    pub power:       Watt,
+    /// Number of steps the appliance runs once it is switched on.
+    pub duration:    Steps,
+    /// Expected number of times a day the appliance is switched on, when someone is at home the whole window.
+    pub activations: BaseFloat,
+    /// First step of the day the appliance may be switched on.
+    pub begin:       Steps,
+    /// Last step of the day the appliance may be switched on.
+    pub end:         Steps,
+    /// Share of the households that own the appliance.
+    pub adoption:    BaseFloat,
+    /// Number of steps the appliance still runs.
+    pub remaining:   Steps,
+}
+
+impl Appliance {
+    /// Chance the appliance is switched on at the given step of the day, when someone is at home.
+    fn chance(&self, time_of_day: Steps) -> BaseFloat {
+        if time_of_day < self.begin || time_of_day > self.end {
+            return 0.0;
+        }
+        self.activations / (self.end.0 - self.begin.0 + 1) as BaseFloat
+    }
+}
+
+/// The parameters of the bottom-up load model of the households, as given by the GridParameters file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ApplianceParam {
+    /// The appliances a household may own.
+    pub appliances:    Vec<Appliance>,
+    /// Power that is always used, e.g. by the fridge and devices on standby.
+    pub base_load:     Watt,
+    /// The step of the day the household wakes up. First is center, second value is std deviation.
+    pub wake_up:       (Steps, Steps),
+    /// The step of the day the household goes to bed. First is center, second value is std deviation.
+    pub bed_time:      (Steps, Steps),
+    /// Chance per step that a household that is at home leaves.
+    pub away_chance:   BaseFloat,
+    /// Chance per step that a household that is away returns.
+    pub return_chance: BaseFloat,
+}
+
+/// Bottom-up load of a [Household], built from its appliances and its occupancy.
+///
+/// The household is asleep from its bed time until it wakes up. While awake it may leave and return home. Only when
+/// someone is at home, appliances are switched on with a chance that depends on the time of day. A running appliance
+/// uses its full power until it is done, which gives the load its spikes. Like the stochastic noise, the draws of a
+/// step come from the stream of that step, so the load of a step is the same each time it is asked for. The state of
+/// the last step is part of a checkpoint.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, AvroSchema)]
+pub struct ApplianceLoad {
+    /// The appliances the household owns.
+    pub appliances:    Vec<Appliance>,
+    /// Power that is always used.
+    pub base_load:     Watt,
+    /// The step of the day the household wakes up.
+    pub wake_up:       Steps,
+    /// The step of the day the household goes to bed.
+    pub bed_time:      Steps,
+    /// Chance per step that the household leaves.
+    pub away_chance:   BaseFloat,
+    /// Chance per step that the household returns.
+    pub return_chance: BaseFloat,
+    /// Current occupancy of the household.
+    pub occupancy:     Occupancy,
+    /// Seed of the stream of the household.
+    pub seed:          i64,
+    /// The last step the load was determined for, if any.
+    pub last_step:     Option<Steps>,
+    /// The power used at the last step.
+    pub last_power:    Watt,
+}
+
+impl ApplianceLoad {
+    /// Creates the load of a new household. The appliances it owns and its daily rhythm are drawn from the parameters.
+    pub fn new(param: &ApplianceParam, seed: &mut SmallRng) -> Result<Self, UtilError> {
+        let mut appliances = vec![];
+        for appliance in &param.appliances {
+            if appliance.adoption > random_percentage(seed) {
+                appliances.push(appliance.clone());
+            }
+        }
+        let mut time_of_day = |(center, std): (Steps, Steps)| -> Result<Steps, UtilError> {
+            let step = if std > Steps(0) {
+                norm_dist(&(center.0 as BaseFloat), &(std.0 as BaseFloat), seed)?
+            } else {
+                center.0 as BaseFloat
+            };
+            Ok(Steps((step as BaseInt).clamp(0, Steps::steps_per_day().0 - 1)))
+        };
+        let wake_up = time_of_day(param.wake_up)?;
+        let bed_time = time_of_day(param.bed_time)?.max(wake_up);
+        Ok(Self {
+            appliances,
+            base_load: param.base_load,
+            wake_up,
+            bed_time,
+            away_chance: param.away_chance,
+            return_chance: param.return_chance,
+            occupancy: Occupancy::Asleep,
+            seed: seed.gen(),
+            last_step: None,
+            last_power: Watt(0),
+        })
+    }
+
+    /// The power used at the given step.
+    pub fn power(&mut self, step: &Steps) -> Watt {
+        let step = Steps(step.0.max(0));
+        if self.last_step.is_some_and(|last| last > step) {
+            self.reset();
+        }
+        loop {
+            let next = match self.last_step {
+                Some(last) if last == step => return self.last_power,
+                Some(last) => Steps(last.0 + 1),
+                None => Steps(0),
+            };
+            self.last_power = self.next(next);
+            self.last_step = Some(next);
+        }
+    }
+
+    /// Start again from the first step.
+    fn reset(&mut self) {
+        self.occupancy = Occupancy::Asleep;
+        self.appliances.iter_mut().for_each(|a| a.remaining = Steps(0));
+        self.last_step = None;
+    }
+
+    /// Update the occupancy and appliances for the given step and give the power used.
+    fn next(&mut self, step: Steps) -> Watt {
+        let stream = &mut RngStreams::seeded_at(self.seed, step);
+        let time_of_day = Steps(step.0 % Steps::steps_per_day().0);
+        let awake = time_of_day >= self.wake_up && time_of_day < self.bed_time;
+        self.occupancy = match self.occupancy {
+            _ if !awake => Occupancy::Asleep,
+            Occupancy::Asleep => Occupancy::Home,
+            Occupancy::Home if random_percentage(stream) < self.away_chance => Occupancy::Away,
+            Occupancy::Away if random_percentage(stream) < self.return_chance => Occupancy::Home,
+            occupancy => occupancy,
+        };
+
+        let mut power = self.base_load;
+        for appliance in &mut self.appliances {
+            if appliance.remaining == Steps(0)
+                && self.occupancy == Occupancy::Home
+                && random_percentage(stream) < appliance.chance(time_of_day)
+            {
+                appliance.remaining = appliance.duration;
+            }
+            if appliance.remaining > Steps(0) {
+                appliance.remaining -= Steps(1);
+                power += appliance.power;
+            }
+        }
+        power
+    }
+}
+
+#[cfg(test)]
+mod appliance_tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn test_param() -> ApplianceParam {
+        ApplianceParam {
+            appliances:    vec![Appliance {
+                power:       Watt(2_000),
+                duration:    Steps(4),
+                activations: 2.0,
+                begin:       Steps(28),
+                end:         Steps(88),
+                adoption:    1.0,
+                remaining:   Steps(0),
+            }],
+            base_load:     Watt(100),
+            wake_up:       (Steps(28), Steps(0)),
+            bed_time:      (Steps(92), Steps(0)),
+            away_chance:   0.01,
+            return_chance: 0.1,
+        }
+    }
+
+    #[test]
+    fn load_follows_occupancy() {
+        let mut load = ApplianceLoad::new(&test_param(), &mut SmallRng::seed_from_u64(117)).unwrap();
+        assert_eq!((load.wake_up, load.bed_time), (Steps(28), Steps(92)));
+        let night: Vec<Watt> = (0..28).map(|s| load.power(&Steps(s))).collect();
+        assert!(night.iter().all(|p| *p == Watt(100)));
+        assert_eq!(load.occupancy, Occupancy::Asleep);
+
+        let day: Vec<Watt> = (0..96 * 7).map(|s| load.power(&Steps(s))).collect();
+        assert!(day.contains(&Watt(2_100)));
+        assert!(day.iter().all(|p| *p == Watt(100) || *p == Watt(2_100)));
+    }
+
+    #[test]
+    fn load_is_repeatable() {
+        let mut load = ApplianceLoad::new(&test_param(), &mut SmallRng::seed_from_u64(117)).unwrap();
+        let first: Vec<Watt> = (0..96 * 3).map(|s| load.power(&Steps(s))).collect();
+        assert_eq!(load.power(&Steps(50)), first[50]);
+        assert_eq!(load.power(&Steps(200)), first[200]);
+    }
+
+    #[test]
+    fn restored_load_continues() {
+        let mut load = ApplianceLoad::new(&test_param(), &mut SmallRng::seed_from_u64(117)).unwrap();
+        let first: Vec<Watt> = (0..96 * 3).map(|s| load.power(&Steps(s))).collect();
+        let mut load = ApplianceLoad::new(&test_param(), &mut SmallRng::seed_from_u64(117)).unwrap();
+        load.power(&Steps(150));
+        let mut restored: ApplianceLoad = serde_json::from_str(&serde_json::to_string(&load).unwrap()).unwrap();
+        assert_eq!(restored.last_step, Some(Steps(150)));
+        let rest: Vec<Watt> = (151..96 * 3).map(|s| restored.power(&Steps(s))).collect();
+        assert_eq!(rest, first[151..]);
+    }
+}
@@ -3,6 +3,7 @@ mod normal;
 
 use std::cmp::Ordering;
 use std::fmt::Display;
+use std::ops::{Add, Neg, Sub};
 use std::str::FromStr;
 
 use apache_avro::schema::derive::AvroSchemaComponent;
@@ -13,7 +14,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::grid::gridstate::GridBoundaryState;
 use crate::grid::{Grid, GridWarning};
-use crate::util::{mHz, mVolt, BaseInt, Steps, StructTraitBound};
+use crate::util::{mHz, mPercent, mVolt, BaseInt, Steps, StructTraitBound};
 
 /// A helper trait to enable checking the boundaries
 pub trait BoundaryAgentTrait<T: BoundaryUnitTrait> {
@@ -23,7 +24,17 @@ pub trait BoundaryAgentTrait<T: BoundaryUnitTrait> {
 
 /// Helper trait, to implement on the units used in the boundary checking.
 pub trait BoundaryUnitTrait:
-    StructTraitBound + From<BaseInt> + AvroSchemaComponent + FromStr + Display + Default + PartialOrd + Sized
+    StructTraitBound
+    + From<BaseInt>
+    + AvroSchemaComponent
+    + FromStr
+    + Display
+    + Default
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Neg<Output = Self>
+    + Sized
 {
 }
 
@@ -40,22 +51,88 @@ pub struct Boundaries<T: BoundaryUnitTrait> {
     pub lowerbands: Vec<BoundaryBand<T>>,
     /// HashMap of boundary value and max permittable steps.
     pub upperbands: Vec<BoundaryBand<T>>,
+    /// Optional limit on the rate of change, e.g. RoCoF for the frequency.
+    pub rate_limit: Option<RateLimit<T>>,
+    /// Current state of the rate of change.
+    pub rate_state: GridBoundaryState,
+}
+
+/// Limit on the rate of change of a value, e.g. the Rate of Change of Frequency (RoCoF). Given by the GridParameters
+/// file.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Serialize, AvroSchema, Default)]
+pub struct RateLimit<T: BoundaryUnitTrait> {
+    /// Maximum change of the value over the window.
+    pub max_change:       T,
+    /// Number of steps over which the change is determined. Given in minutes by the GridParameters file.
+    pub window:           Steps,
+    /// How many steps may the change be above the maximum?
+    pub max_time_allowed: Steps,
+    /// How many steps has the change already been above the maximum?
+    pub time_passed:      Steps,
 }
 
 impl<T: BoundaryUnitTrait> Boundaries<T> {
     /// Update the boundary and check all the relevant restrictions to see if the grid is outside its parameters.
+    ///
+    /// Once outside its normal band, the value needs to be within the hysteresis of the normal band to return to
+    /// normal. Until then the time past the bands is kept.
     pub fn update(&mut self, current: T) -> Option<GridWarning> {
+        self.lowerbands.iter_mut().for_each(|b| b.tick(current, Ordering::Less));
+        self.upperbands
+            .iter_mut()
+            .for_each(|b| b.tick(current, Ordering::Greater));
         let update_cmp = self.normalband.compare(current);
         match update_cmp {
             Ordering::Less => self.check_lowerbands(current),
             Ordering::Equal => {
-                self.check_normalband();
-                None
+                if self.state == GridBoundaryState::Normal || self.normalband.returned(current) {
+                    self.check_normalband();
+                    None
+                } else {
+                    self.change_state(self.state)
+                }
             }
             Ordering::Greater => self.check_upperbands(current),
         }
     }
 
+    /// Check the rate of change of the value against the rate limit, if any. The history holds the previous values,
+    /// the latest last.
+    pub fn check_rate(&mut self, current: T, history: &[T]) -> Option<GridWarning> {
+        let limit = self.rate_limit.as_mut()?;
+        let window = limit.window.0 as usize;
+        if window == 0 || history.len() < window {
+            return None;
+        }
+        let change = current - history[history.len() - window];
+        let state = if change > limit.max_change {
+            GridBoundaryState::High
+        } else if change < -limit.max_change {
+            GridBoundaryState::Low
+        } else {
+            GridBoundaryState::Normal
+        };
+        if state == GridBoundaryState::Normal {
+            limit.time_passed = Steps(0);
+        } else {
+            limit.time_passed += Steps(1);
+        }
+        let state = match state {
+            GridBoundaryState::High if limit.time_passed > limit.max_time_allowed => GridBoundaryState::TooHigh,
+            GridBoundaryState::Low if limit.time_passed > limit.max_time_allowed => GridBoundaryState::TooLow,
+            state => state,
+        };
+        self.rate_state = state;
+        Self::warning(state)
+    }
+
+    /// Is the value or its rate of change past its limits for too long?
+    pub fn is_critical(&self) -> bool {
+        [self.state, self.rate_state]
+            .iter()
+            .any(|s| matches!(s, GridBoundaryState::TooLow | GridBoundaryState::TooHigh))
+    }
+
     fn check_normalband(&mut self) {
         if self.state == GridBoundaryState::Normal {
         } else {
@@ -96,6 +173,10 @@ impl<T: BoundaryUnitTrait> Boundaries<T> {
         if self.state != state {
             self.state = state;
         }
+        Self::warning(state)
+    }
+
+    fn warning(state: GridBoundaryState) -> Option<GridWarning> {
         match state {
             GridBoundaryState::TooLow | GridBoundaryState::TooHigh => Some(GridWarning {
                 state,
@@ -104,6 +185,7 @@ impl<T: BoundaryUnitTrait> Boundaries<T> {
                 agent_powerstate: None,
                 freq_state: None,
                 volt_state: None,
+                area_state: None,
                 infectionstatistics: None,
             }),
             GridBoundaryState::Low | GridBoundaryState::High => Some(GridWarning {
@@ -113,20 +195,38 @@ impl<T: BoundaryUnitTrait> Boundaries<T> {
                 agent_powerstate: None,
                 freq_state: None,
                 volt_state: None,
+                area_state: None,
                 infectionstatistics: None,
             }),
             GridBoundaryState::Normal => None,
         }
     }
 
+    /// Reset the boundary to the normal state.
+    pub fn reset(&mut self) {
+        self.state = GridBoundaryState::Normal;
+        self.rate_state = GridBoundaryState::Normal;
+        if let Some(limit) = self.rate_limit.as_mut() {
+            limit.time_passed = Steps(0);
+        }
+        self.reset_bands();
+        self.lowerbands.iter_mut().for_each(|b| b.passed.clear());
+        self.upperbands.iter_mut().for_each(|b| b.passed.clear());
+    }
+
+    /// Reset the time past the bands. Bands with a window keep their time, as it slides out of the window by itself.
     fn reset_bands(&mut self) {
-        self.lowerbands.iter_mut().for_each(|b| b.time_passed = Steps(0));
-        self.upperbands.iter_mut().for_each(|b| b.time_passed = Steps(0));
+        self.lowerbands
+            .iter_mut()
+            .chain(self.upperbands.iter_mut())
+            .filter(|b| !b.has_window())
+            .for_each(|b| b.time_passed = Steps(0));
     }
 }
 
 impl BoundaryUnitTrait for mHz {}
 impl BoundaryUnitTrait for mVolt {}
+impl BoundaryUnitTrait for mPercent {}
 
 impl From<&Grid> for Boundaries<mHz> {
     fn from(value: &Grid) -> Self { value.freq_boundary.clone() }
@@ -135,3 +235,84 @@ impl From<&Grid> for Boundaries<mHz> {
 impl From<&Grid> for Boundaries<mVolt> {
     fn from(value: &Grid) -> Self { value.volt_boundary.clone() }
 }
+
+impl From<&Grid> for Boundaries<mPercent> {
+    fn from(value: &Grid) -> Self { value.area_boundary.clone() }
+}
+
+#[cfg(test)]
+mod boundary_tests {
+    use super::*;
+
+    fn test_boundaries() -> Boundaries<mHz> {
+        Boundaries {
+            state:      GridBoundaryState::Normal,
+            normalband: NormalBand {
+                lower:      mHz(49_000),
+                higher:     mHz(51_000),
+                hysteresis: mHz(100),
+            },
+            lowerbands: vec![],
+            upperbands: vec![BoundaryBand {
+                border:           mHz(51_000),
+                max_time_allowed: Steps(2),
+                time_passed:      Steps(0),
+                window:           Steps(0),
+                passed:           vec![],
+            }],
+            rate_limit: None,
+            rate_state: GridBoundaryState::Normal,
+        }
+    }
+
+    #[test]
+    fn hysteresis_keeps_time_passed() {
+        let mut boundaries = test_boundaries();
+        assert!(boundaries.update(mHz(51_100)).is_some());
+        assert!(boundaries.update(mHz(50_950)).is_some());
+        assert_eq!(boundaries.state, GridBoundaryState::High);
+        assert!(boundaries.update(mHz(51_100)).is_some());
+        assert_eq!(boundaries.upperbands[0].time_passed, Steps(2));
+        assert!(boundaries.update(mHz(50_800)).is_none());
+        assert_eq!(boundaries.state, GridBoundaryState::Normal);
+        assert_eq!(boundaries.upperbands[0].time_passed, Steps(0));
+    }
+
+    #[test]
+    fn window_accumulates_time_passed() {
+        let mut boundaries = test_boundaries();
+        boundaries.normalband.hysteresis = mHz(0);
+        boundaries.upperbands[0].window = Steps(5);
+        for value in [51_100, 50_000, 51_100, 50_000] {
+            boundaries.update(mHz(value));
+        }
+        assert_eq!(boundaries.upperbands[0].time_passed, Steps(2));
+        let warning = boundaries.update(mHz(51_100)).unwrap();
+        assert!(warning.critical);
+        boundaries.update(mHz(50_000));
+        boundaries.update(mHz(50_000));
+        assert_eq!(boundaries.upperbands[0].time_passed, Steps(2));
+        assert_eq!(boundaries.state, GridBoundaryState::Normal);
+    }
+
+    #[test]
+    fn rate_of_change_limit() {
+        let mut boundaries = test_boundaries();
+        boundaries.rate_limit = Some(RateLimit {
+            max_change:       mHz(500),
+            window:           Steps(2),
+            max_time_allowed: Steps(1),
+            time_passed:      Steps(0),
+        });
+        let history = [mHz(50_000), mHz(49_800)];
+        assert!(boundaries.check_rate(mHz(49_400), &history[..1]).is_none());
+        assert!(boundaries.check_rate(mHz(49_600), &history).is_none());
+
+        let warning = boundaries.check_rate(mHz(49_400), &history).unwrap();
+        assert_eq!(warning.state, GridBoundaryState::Low);
+        assert!(!boundaries.is_critical());
+        let warning = boundaries.check_rate(mHz(49_400), &history).unwrap();
+        assert_eq!(warning.state, GridBoundaryState::TooLow);
+        assert!(boundaries.is_critical());
+    }
+}
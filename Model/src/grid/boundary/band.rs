@@ -7,10 +7,13 @@ use crate::grid::gridstate::GridBoundaryState;
 #[allow(unused_imports)]
 use crate::grid::Boundaries;
 use crate::grid::BoundaryUnitTrait;
-use crate::util::Steps;
+use crate::util::{BaseInt, Steps};
 
 /// Restriction on the grid. How long may the grid pass the border in the number of steps? Generated from the
 /// GridParameters file.
+///
+/// Without a window, the time past the border accumulates until the value returns to normal. With a window, only the
+/// steps past the border within the last window count, also when the value returned to normal in between.
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, AvroSchema)]
 pub struct BoundaryBand<T: BoundaryUnitTrait> {
     /// The border value, that the value must pass to trigger this restriction. Direction is given by the related
@@ -20,6 +23,10 @@ pub struct BoundaryBand<T: BoundaryUnitTrait> {
     pub max_time_allowed: Steps,
     /// How many steps have already been had past the border?
     pub time_passed:      Steps,
+    /// Length of the sliding window over which the time past the border is accumulated. No window if zero.
+    pub window:           Steps,
+    /// Was the value past the border, for each step within the window?
+    pub passed:           Vec<bool>,
 }
 
 impl<T: BoundaryUnitTrait> BoundaryBand<T> {
@@ -27,7 +34,7 @@ impl<T: BoundaryUnitTrait> BoundaryBand<T> {
     #[inline]
     pub fn check(&mut self, current: T, direction_regards_normal: Ordering) -> GridBoundaryState {
         let cmp = current.cmp(&self.border);
-        if cmp == direction_regards_normal || cmp == Ordering::Equal {
+        if !self.has_window() && (cmp == direction_regards_normal || cmp == Ordering::Equal) {
             self.time_passed += Steps(1);
         }
         if self.time_passed > self.max_time_allowed {
@@ -42,4 +49,22 @@ impl<T: BoundaryUnitTrait> BoundaryBand<T> {
             GridBoundaryState::High
         }
     }
+
+    /// Does the band accumulate its time over a sliding window?
+    #[inline]
+    pub fn has_window(&self) -> bool { self.window > Steps(0) }
+
+    /// Slide the window of the band by a step, is done every step. Only for bands with a window.
+    pub fn tick(&mut self, current: T, direction_regards_normal: Ordering) {
+        if !self.has_window() {
+            return;
+        }
+        let cmp = current.cmp(&self.border);
+        self.passed
+            .push(cmp == direction_regards_normal || cmp == Ordering::Equal);
+        if self.passed.len() > self.window.0 as usize {
+            self.passed.remove(0);
+        }
+        self.time_passed = Steps(self.passed.iter().filter(|p| **p).count() as BaseInt);
+    }
 }
@@ -9,9 +9,11 @@ use crate::grid::BoundaryUnitTrait;
 #[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Serialize, AvroSchema, Default)]
 pub struct NormalBand<T: BoundaryUnitTrait> {
     /// Lower limit of this GridParameter.
-    pub lower:  T,
+    pub lower:      T,
     /// Higher limit of this GridParameter.
-    pub higher: T,
+    pub higher:     T,
+    /// How far the value needs to be within the limits to return to normal.
+    pub hysteresis: T,
 }
 
 impl<T: BoundaryUnitTrait> NormalBand<T> {
@@ -25,4 +27,9 @@ impl<T: BoundaryUnitTrait> NormalBand<T> {
         }
         Ordering::Equal
     }
+
+    /// Is the given value far enough within the limits to return to normal?
+    pub fn returned(&self, value: T) -> bool {
+        value >= self.lower + self.hysteresis && value <= self.higher - self.hysteresis
+    }
 }
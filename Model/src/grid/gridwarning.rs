@@ -3,7 +3,7 @@ use serde::{Serialize, Deserialize};
 use crate::grid::gridstate::GridBoundaryState;
 #[allow(unused_imports)]
 use crate::grid::BoundaryBand;
-use crate::grid::{FreqState, InfectionStatistics, PowerState, VoltState};
+use crate::grid::{AreaState, FreqState, InfectionStatistics, PowerState, VoltState};
 use crate::util::BaseUint;
 
 /// The warning returned if the grid is outside normal parameters.
@@ -21,6 +21,8 @@ pub struct GridWarning {
     pub freq_state:          Option<FreqState>,
     /// The voltage state of the agent.
     pub volt_state:          Option<VoltState>,
+    /// The aggregated state of the area.
+    pub area_state:          Option<AreaState>,
     /// InfectionStatistics
     pub infectionstatistics: Option<InfectionStatistics>,
 }
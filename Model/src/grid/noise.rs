@@ -0,0 +1,163 @@
+use apache_avro::AvroSchema;
+use serde::{Deserialize, Serialize};
+
+#[allow(unused_imports)]
+use crate::agent::{Household, Netstation};
+#[allow(unused_imports)]
+use crate::grid::PowerGeneration;
+use crate::util::{norm_dist, BaseFloat, RngStreams, Steps, UtilError};
+
+/// The stochastic process that is used as noise on the power of a [PowerGeneration] unit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, AvroSchema)]
+pub enum NoiseModel {
+    /// First order autoregressive process. The correlation is the coefficient between two consecutive steps.
+    Ar1,
+    /// Ornstein-Uhlenbeck process. The correlation is the rate at which the noise reverts to zero per step.
+    OrnsteinUhlenbeck,
+}
+
+/// The parameters of the stochastic noise, as given by the GridParameters file. Without them, the noise is a sum of
+/// sine functions.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NoiseParam {
+    /// The stochastic process used.
+    pub model:                 NoiseModel,
+    /// Correlation in time, its meaning depends on the model.
+    pub correlation:           BaseFloat,
+    /// Correlation between the noise of [Household] agents under the same [Netstation].
+    pub neighbour_correlation: BaseFloat,
+}
+
+impl NoiseParam {
+    /// The coefficient between the noise of two consecutive steps. An Ornstein-Uhlenbeck process sampled each step is
+    /// an AR(1) process.
+    pub fn coefficient(&self) -> BaseFloat {
+        match self.model {
+            NoiseModel::Ar1 => self.correlation,
+            NoiseModel::OrnsteinUhlenbeck => (-self.correlation).exp(),
+        }
+    }
+}
+
+/// Noise of a [PowerGeneration] unit that follows an AR(1) process with a standard deviation of one.
+///
+/// Each step the process is driven by a shock that is partly drawn from the stream of the household itself and partly
+/// from the stream of its neighbours. Households under the same [Netstation] share the seed of the neighbour stream,
+/// which correlates their noise. The shock of a step is drawn from the streams of that step, so the noise of a step is
+/// the same each time it is asked for. The last value is kept and is part of a checkpoint, an earlier step is found by
+/// replaying the process from the first step.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, AvroSchema)]
+pub struct NoiseProcess {
+    /// Coefficient between the noise of two consecutive steps.
+    pub coefficient:           BaseFloat,
+    /// Correlation between the noise of neighbours.
+    pub neighbour_correlation: BaseFloat,
+    /// Seed of the stream of the household itself.
+    pub seed:                  i64,
+    /// Seed of the stream shared with the neighbours.
+    pub neighbour_seed:        i64,
+    /// The last step the noise was determined for, if any.
+    pub last_step:             Option<Steps>,
+    /// The noise at the last step.
+    pub last_value:            BaseFloat,
+}
+
+impl NoiseProcess {
+    /// Creates a new noise process from its own seed and the seed shared with the neighbours.
+    pub fn new(param: &NoiseParam, seed: i64, neighbour_seed: i64) -> Self {
+        Self {
+            coefficient: param.coefficient(),
+            neighbour_correlation: param.neighbour_correlation.clamp(0.0, 1.0),
+            seed,
+            neighbour_seed,
+            last_step: None,
+            last_value: 0.0,
+        }
+    }
+
+    /// The noise at the given step.
+    pub fn value(&mut self, step: &Steps) -> Result<BaseFloat, UtilError> {
+        let step = Steps(step.0.max(0));
+        if self.last_step.is_some_and(|last| last > step) {
+            self.last_step = None;
+        }
+        loop {
+            match self.last_step {
+                Some(last) if last == step => return Ok(self.last_value),
+                Some(last) => {
+                    let next = Steps(last.0 + 1);
+                    let shock = self.shock(next)?;
+                    self.last_value = self.coefficient.mul_add(
+                        self.last_value,
+                        self.coefficient.mul_add(-self.coefficient, 1.0).max(0.0).sqrt() * shock,
+                    );
+                    self.last_step = Some(next);
+                }
+                None => {
+                    self.last_value = self.shock(Steps(0))?;
+                    self.last_step = Some(Steps(0));
+                }
+            }
+        }
+    }
+
+    /// The shock of the given step, drawn from the streams of that step.
+    fn shock(&self, step: Steps) -> Result<BaseFloat, UtilError> {
+        let own = norm_dist(&0.0, &1.0, &mut RngStreams::seeded_at(self.seed, step))?;
+        let neighbour = norm_dist(&0.0, &1.0, &mut RngStreams::seeded_at(self.neighbour_seed, step))?;
+        Ok(self
+            .neighbour_correlation
+            .sqrt()
+            .mul_add(neighbour, (1.0 - self.neighbour_correlation).sqrt() * own))
+    }
+}
+
+#[cfg(test)]
+mod noise_tests {
+    use super::*;
+
+    const PARAM: NoiseParam = NoiseParam {
+        model:                 NoiseModel::Ar1,
+        correlation:           0.9,
+        neighbour_correlation: 1.0,
+    };
+
+    #[test]
+    fn noise_is_repeatable() {
+        let mut process = NoiseProcess::new(&PARAM, 117, 2010);
+        let first: Vec<BaseFloat> = (0..96).map(|s| process.value(&Steps(s)).unwrap()).collect();
+        assert_eq!(process.value(&Steps(12)).unwrap(), first[12]);
+
+        let mut restarted = NoiseProcess::new(&PARAM, 117, 2010);
+        assert_eq!(restarted.value(&Steps(95)).unwrap(), first[95]);
+        assert_ne!(first[0], first[1]);
+    }
+
+    #[test]
+    fn restored_noise_continues() {
+        let mut process = NoiseProcess::new(&PARAM, 117, 2010);
+        process.value(&Steps(50)).unwrap();
+        let mut restored: NoiseProcess = serde_json::from_str(&serde_json::to_string(&process).unwrap()).unwrap();
+        assert_eq!(restored.last_step, Some(Steps(50)));
+        assert_eq!(restored.value(&Steps(51)).unwrap(), process.value(&Steps(51)).unwrap());
+    }
+
+    #[test]
+    fn neighbours_are_correlated() {
+        let mut household = NoiseProcess::new(&PARAM, 117, 2010);
+        let mut neighbour = NoiseProcess::new(&PARAM, 118, 2010);
+        let mut other = NoiseProcess::new(&PARAM, 118, 2011);
+        assert_eq!(
+            household.value(&Steps(20)).unwrap(),
+            neighbour.value(&Steps(20)).unwrap()
+        );
+        assert_ne!(household.value(&Steps(20)).unwrap(), other.value(&Steps(20)).unwrap());
+
+        let ou = NoiseParam {
+            model: NoiseModel::OrnsteinUhlenbeck,
+            correlation: 0.1,
+            ..PARAM
+        };
+        assert!((ou.coefficient() - 0.904_837).abs() < 1e-5);
+    }
+}
@@ -1,11 +1,11 @@
-use std::collections::HashMap;
 use std::f32::consts::PI;
 
 use apache_avro::AvroSchema;
 use derive_more::{Mul, MulAssign};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
-use crate::grid::InfectionState;
+use crate::grid::{ApplianceLoad, ConsumptionProfile, InfectionState, NoiseProcess, ReplayProfile};
 use crate::model::ModelParameters;
 use crate::util::{norm_dist, random_percentage, BaseFloat, BaseInt, Steps, UtilError, Watt};
 
@@ -19,10 +19,20 @@ pub struct PowerGeneration {
     pub generation_param:               Vec<SineParam>,
     /// The usage parameters that create the power consumed per step.
     pub consumption_param:              Vec<SineParam>,
+    /// The standard profile that creates the power consumed per step, used instead of the usage parameters if given.
+    pub consumption_profile:            Option<ConsumptionProfile>,
     /// The noise parameters given to this [PowerGeneration] unit.
     pub generation_noise_param:         Vec<SineParam>,
     /// The noise parameters given to this [PowerGeneration] unit.
     pub consumption_noise_param:        Vec<SineParam>,
+    /// The stochastic noise on the generation, used instead of the noise parameters if given.
+    pub generation_noise_process:       Option<NoiseProcess>,
+    /// The stochastic noise on the consumption, used instead of the noise parameters if given.
+    pub consumption_noise_process:      Option<NoiseProcess>,
+    /// The appliances that make up the consumption, used instead of the usage parameters and noise if given.
+    pub appliance_load:                 Option<ApplianceLoad>,
+    /// The measured series that are replayed, used instead of all parameters if given.
+    pub replay:                         Option<ReplayProfile>,
     /// The index of the household associated with the power generation unit.
     pub index:                          Option<BaseInt>,
     /// Average power consumption
@@ -33,25 +43,65 @@ pub struct PowerGeneration {
     pub percentage_generation_of_usage: f32,
     #[serde(skip)]
     #[avro(skip)]
-    calc_power_cache:                   HashMap<Steps, (Watt, Watt, Watt)>,
+    daily_profile:                      DailyProfile,
+}
+
+/// The relative generation and consumption of a [PowerGeneration] unit for each step of the day, as far as they repeat
+/// each day. Multiplied by the average power usage of the household this gives the power. Its size does not grow with
+/// the number of steps that are simulated.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct DailyProfile {
+    generation:  Vec<BaseFloat>,
+    consumption: Vec<BaseFloat>,
+}
+
+impl DailyProfile {
+    #[inline]
+    fn is_empty(&self) -> bool { self.consumption.is_empty() }
+
+    /// The relative generation and consumption at the given step.
+    #[inline]
+    fn at(&self, step: &Steps) -> (BaseFloat, BaseFloat) {
+        let index = (Steps::time_of_day(step).0 / Steps::minutes_per_step().0) as usize;
+        (self.generation[index], self.consumption[index])
+    }
 }
 
 impl PowerGeneration {
     /// Create a new PV power generation unit. Assumes that a house on average
-    /// is self-sufficient.
-    pub fn new_pv(index: Option<BaseInt>, param: &mut ModelParameters) -> Result<Self, UtilError> {
-        let mut pg = Self::new(index, param, true)?;
+    /// is self-sufficient. The neighbour seed is shared by the households under the same netstation, to correlate their
+    /// stochastic noise.
+    pub fn new_pv(
+        index: Option<BaseInt>,
+        neighbour_seed: Option<i64>,
+        param: &mut ModelParameters,
+    ) -> Result<Self, UtilError> {
+        let mut pg = Self::new(index, neighbour_seed, param, true)?;
         pg.generation_param = gen_generation_param(param)?;
         Ok(pg)
     }
 
+    /// Does the unit have a PV installation, either simulated or in the replayed meter?
+    pub fn has_pv(&self) -> bool {
+        !self.generation_param.is_empty() || self.replay.as_ref().is_some_and(ReplayProfile::has_generation)
+    }
+
     /// Creates a power generation unit for a house that only consumes energy.
-    pub fn new_no_pv(index: Option<BaseInt>, param: &mut ModelParameters) -> Result<Self, UtilError> {
-        let pg = Self::new(index, param, false)?;
+    pub fn new_no_pv(
+        index: Option<BaseInt>,
+        neighbour_seed: Option<i64>,
+        param: &mut ModelParameters,
+    ) -> Result<Self, UtilError> {
+        let pg = Self::new(index, neighbour_seed, param, false)?;
         Ok(pg)
     }
 
-    fn new(index: Option<BaseInt>, param: &mut ModelParameters, infectable: bool) -> Result<Self, UtilError> {
+    fn new(
+        index: Option<BaseInt>,
+        neighbour_seed: Option<i64>,
+        param: &mut ModelParameters,
+        infectable: bool,
+    ) -> Result<Self, UtilError> {
         let average_power_usage = Watt(
             norm_dist(
                 &(param.grid.household_power_consumption_distribution.0 .0 as f32),
@@ -60,9 +110,31 @@ impl PowerGeneration {
             )
             .expect("Couldnt get average power") as i64,
         );
-        let generation_noise_param = gen_noise_param(param)?;
-        let consumption_noise_param = gen_noise_param(param)?;
-        let consumption_param = gen_consumption_param(param)?;
+        let (generation_noise_param, consumption_noise_param, generation_noise_process, consumption_noise_process) =
+            match param.grid.noise {
+                Some(noise) => {
+                    let neighbour_seed = neighbour_seed.unwrap_or_else(|| param.seed.gen());
+                    (
+                        vec![],
+                        vec![],
+                        Some(NoiseProcess::new(&noise, param.seed.gen(), neighbour_seed)),
+                        Some(NoiseProcess::new(
+                            &noise,
+                            param.seed.gen(),
+                            neighbour_seed.wrapping_add(1),
+                        )),
+                    )
+                }
+                None => (gen_noise_param(param)?, gen_noise_param(param)?, None, None),
+            };
+        let (consumption_param, consumption_profile) = match param.grid.profiles.as_ref() {
+            Some(profiles) => (vec![], Some(profiles.pick(&mut param.seed))),
+            None => (gen_consumption_param(param)?, None),
+        };
+        let appliance_load = match param.grid.appliances.as_ref() {
+            Some(appliances) => Some(ApplianceLoad::new(appliances, &mut param.seed)?),
+            None => None,
+        };
         let infection_state = if infectable && random_percentage(&mut param.seed) < param.attack.percentage_vuln_devices
         {
             InfectionState::Vulnerable
@@ -76,62 +148,96 @@ impl PowerGeneration {
             average_power_usage,
             generation_param: vec![],
             consumption_param,
+            consumption_profile,
             generation_noise_param,
             consumption_noise_param,
+            generation_noise_process,
+            consumption_noise_process,
+            appliance_load,
+            replay: None,
             noise_percentage: param.grid.percentage_noise_on_power,
             percentage_generation_of_usage: param.grid.percentage_generation_of_usage,
-            calc_power_cache: HashMap::new(),
+            daily_profile: DailyProfile::default(),
         })
     }
 
     /// Calculates the power of the unit. Both the noise and power consumption
-    /// parts.
+    /// parts. The parts that repeat each day are taken from the daily profile, the parts that depend on the calendar
+    /// are calculated for the step.
     #[inline]
-    pub fn calc_power(&mut self, step: &Steps) -> (Watt, Watt, Watt) {
-        match self.calc_power_cache.contains_key(step) {
-            true => self.calc_power_cache.get(step).unwrap().to_owned(),
-            false => {
-                let new_entry = self.gen_calc_power(step);
-                self.calc_power_cache.insert(step.to_owned(), new_entry);
-                new_entry
-            }
+    pub fn calc_power(&mut self, step: &Steps) -> Result<(Watt, Watt, Watt), UtilError> {
+        if let Some(replay) = self.replay.as_ref() {
+            let (power_generated, power_used) = replay.power(step);
+            return Ok((power_generated, power_used, power_used - power_generated));
         }
-    }
+        if self.daily_profile.is_empty() {
+            self.daily_profile = self.gen_daily_profile();
+        }
+        let (daily_generation, daily_consumption) = self.daily_profile.at(step);
 
-    fn gen_calc_power(&self, step: &Steps) -> (Watt, Watt, Watt) {
         let power_generated = if self.generation_param.is_empty() {
             Watt(0)
         } else {
-            Watt(
-                (self
-                    .generation_noise(step)
-                    .mul_add(self.noise_percentage, self.generation(step))
-                    * self.average_power_usage.0 as f32) as i64,
-            )
+            let generation = match self.generation_noise_process.as_mut() {
+                Some(p) => p.value(step)?.mul_add(self.noise_percentage, daily_generation),
+                None => daily_generation,
+            };
+            Watt((generation * self.average_power_usage.0 as f32) as i64)
         };
-        let power_used = Watt(
-            (self
-                .consumption_noise(step)
-                .mul_add(self.noise_percentage, self.consumption(step))
-                * self.average_power_usage.0 as f32) as i64,
-        );
-        (power_generated, power_used, power_used - power_generated)
+        if let Some(appliance_load) = self.appliance_load.as_mut() {
+            let power_used = appliance_load.power(step);
+            return Ok((power_generated, power_used, power_used - power_generated));
+        }
+        let consumption = self
+            .consumption_profile
+            .as_ref()
+            .map_or(daily_consumption, |p| daily_consumption + p.relative_consumption(step));
+        let consumption = match self.consumption_noise_process.as_mut() {
+            Some(p) => p.value(step)?.mul_add(self.noise_percentage, consumption),
+            None => consumption,
+        };
+        let power_used = Watt((consumption * self.average_power_usage.0 as f32) as i64);
+        Ok((power_generated, power_used, power_used - power_generated))
+    }
+
+    /// Generates the parts of the generation and consumption that repeat each day. These are the sine functions, while
+    /// the standard profiles and the stochastic noise depend on the calendar.
+    fn gen_daily_profile(&self) -> DailyProfile {
+        let mut daily_profile = DailyProfile::default();
+        for step in (0..Steps::steps_per_day().0).map(Steps) {
+            let generation = self.generation(&step);
+            let consumption = if self.consumption_profile.is_some() {
+                0.0
+            } else {
+                self.consumption(&step)
+            };
+            daily_profile.generation.push(match self.generation_noise_process {
+                Some(_) => generation,
+                None => Self::calc_sin(&step, &self.generation_noise_param).mul_add(self.noise_percentage, generation),
+            });
+            daily_profile.consumption.push(match self.consumption_noise_process {
+                Some(_) => consumption,
+                None => {
+                    Self::calc_sin(&step, &self.consumption_noise_param).mul_add(self.noise_percentage, consumption)
+                }
+            });
+        }
+        daily_profile
     }
 
     #[inline]
-    fn consumption(&self, step: &Steps) -> BaseFloat { Self::calc_sin(step, &self.consumption_param) }
+    fn consumption(&self, step: &Steps) -> BaseFloat {
+        self.consumption_profile.as_ref().map_or_else(
+            || Self::calc_sin(step, &self.consumption_param),
+            |p| p.relative_consumption(step),
+        )
+    }
 
     #[inline]
     fn generation(&self, step: &Steps) -> BaseFloat {
         Self::calc_sin(step, &self.generation_param) * self.percentage_generation_of_usage
     }
 
-    #[inline]
-    fn consumption_noise(&self, step: &Steps) -> BaseFloat { Self::calc_sin(step, &self.consumption_noise_param) }
-
-    #[inline]
-    fn generation_noise(&self, step: &Steps) -> BaseFloat { Self::calc_sin(step, &self.generation_noise_param) }
-
     #[inline]
     fn calc_sin(step: &Steps, sinparams: &[SineParam]) -> f32 {
         sinparams
@@ -243,11 +349,11 @@ mod power_generation_test {
         let mut param = ModelParameters::test();
 
         let powergens_pv: Vec<PowerGeneration> = (0..100)
-            .map(|_| PowerGeneration::new_pv(None, &mut param).unwrap())
+            .map(|_| PowerGeneration::new_pv(None, None, &mut param).unwrap())
             .collect();
 
         let powergens_non_pv: Vec<PowerGeneration> = (0..100)
-            .map(|_| PowerGeneration::new_no_pv(None, &mut param).unwrap())
+            .map(|_| PowerGeneration::new_no_pv(None, None, &mut param).unwrap())
             .collect();
 
         let filename = "../data_analysis/model_output/No_PV_validation_consumption.csv";
@@ -332,6 +438,20 @@ mod power_generation_test {
         file.flush().unwrap();
     }
 
+    #[test]
+    fn daily_profile_repeats() {
+        let mut param = ModelParameters::test();
+        let mut pg = PowerGeneration::new_pv(None, None, &mut param).unwrap();
+        let day = Steps::steps_per_day().0;
+        let first_day: Vec<(Watt, Watt, Watt)> = (0..day).map(|s| pg.calc_power(&Steps(s)).unwrap()).collect();
+        let later_day: Vec<(Watt, Watt, Watt)> = (0..day)
+            .map(|s| pg.calc_power(&Steps(100 * day + s)).unwrap())
+            .collect();
+        assert_eq!(first_day, later_day);
+        assert_eq!(pg.daily_profile.consumption.len(), day as usize);
+        assert!(first_day.iter().any(|(generated, ..)| *generated != Watt(0)));
+    }
+
     #[test]
     fn sine_func_test() {
         let sp = vec![SineParam {
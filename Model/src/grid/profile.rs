@@ -0,0 +1,207 @@
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::Arc;
+
+use apache_avro::AvroSchema;
+use rand::rngs::SmallRng;
+use serde::{Deserialize, Serialize};
+
+#[allow(unused_imports)]
+use crate::agent::Household;
+#[allow(unused_imports)]
+use crate::grid::PowerGeneration;
+use crate::util::{gen_csv_table, random_percentage, read_file, BaseFloat, ConfigError, Steps};
+
+/// Minutes between two values of a standard profile.
+const MINUTES_PER_VALUE: usize = 15;
+
+/// The standard profiles of household consumption published by [MFFBAS](https://www.mffbas.nl/documenten/). The same
+/// file is used by the validation of the model.
+///
+/// The file holds a value for each quarter of an hour of the year, in a column per profile, starting at 1 January.
+/// A category, e.g. AZI or AMI_A, combines all columns that contain its name, except those of the large connections
+/// (E3 and E4). The categories are assigned to the [Household] agents by their share.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct StandardProfiles {
+    /// The file the profiles are loaded from.
+    pub file:       String,
+    /// The categories and the share of households they are assigned to.
+    pub categories: Vec<(String, BaseFloat)>,
+    /// The consumption of each category relative to its average, for each quarter of an hour.
+    #[serde(skip)]
+    pub values:     Vec<Arc<Vec<BaseFloat>>>,
+}
+
+impl StandardProfiles {
+    /// Loads the profiles of the given categories from the CSV file of MFFBAS.
+    pub fn from_csv_file(
+        filepath: impl AsRef<Path>,
+        categories: Vec<(String, BaseFloat)>,
+    ) -> Result<Self, ConfigError> {
+        Ok(Self {
+            file: filepath.as_ref().display().to_string(),
+            ..Self::from_csv_string(&read_file(&filepath)?, categories)?
+        })
+    }
+
+    fn from_csv_string(csv_string: &str, categories: Vec<(String, BaseFloat)>) -> Result<Self, ConfigError> {
+        let (header, rows) = gen_csv_table(csv_string)?;
+
+        let mut values = vec![];
+        for (category, _) in &categories {
+            let columns: Vec<usize> = header
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| c.contains(category.as_str()) && !c.contains("E3") && !c.contains("E4"))
+                .map(|(i, _)| i)
+                .collect();
+            if columns.is_empty() {
+                return Err(ConfigError::NotPermittedOption(format!(
+                    "{category} is not a category of the profile file"
+                )));
+            }
+            let mut profile = vec![];
+            for row in &rows {
+                let mut value = 0.0;
+                for column in &columns {
+                    value += match row.get(*column).map(String::as_str) {
+                        None | Some("") => 0.0,
+                        Some(v) => v
+                            .parse::<BaseFloat>()
+                            .map_err(|e| ConfigError::ParsingError(format!("{v} in the profile file: {e}")))?,
+                    };
+                }
+                profile.push(value);
+            }
+            let average = profile.iter().sum::<BaseFloat>() / profile.len() as BaseFloat;
+            if average <= 0.0 {
+                return Err(ConfigError::ParsingError(format!(
+                    "{category} has no consumption in the profile file"
+                )));
+            }
+            values.push(Arc::new(profile.into_iter().map(|v| v / average).collect()));
+        }
+        Ok(Self {
+            file: String::new(),
+            categories,
+            values,
+        })
+    }
+
+    /// Picks the profile of a household, according to the shares of the categories.
+    pub fn pick(&self, seed: &mut SmallRng) -> ConsumptionProfile {
+        let total: BaseFloat = self.categories.iter().map(|(_, share)| share).sum();
+        let mut pick = random_percentage(seed) * total;
+        let index = self
+            .categories
+            .iter()
+            .position(|(_, share)| {
+                pick -= share;
+                pick < 0.0
+            })
+            .unwrap_or(self.categories.len() - 1);
+        ConsumptionProfile {
+            category: self.categories[index].0.clone(),
+            values:   self.values[index].clone(),
+        }
+    }
+
+    /// The profile of the given category, if it is one of the categories.
+    pub fn get(&self, category: &str) -> Option<ConsumptionProfile> {
+        let index = self.categories.iter().position(|(c, _)| c == category)?;
+        Some(ConsumptionProfile {
+            category: category.to_string(),
+            values:   self.values[index].clone(),
+        })
+    }
+}
+
+impl Debug for StandardProfiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StandardProfiles")
+            .field("File", &self.file)
+            .field("Categories", &self.categories)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The standard profile that drives the consumption of a [PowerGeneration] unit.
+#[derive(Clone, PartialEq, Serialize, Deserialize, AvroSchema)]
+pub struct ConsumptionProfile {
+    /// The category of the profile.
+    pub category: String,
+    #[serde(skip)]
+    #[avro(skip)]
+    values:       Arc<Vec<BaseFloat>>,
+}
+
+impl ConsumptionProfile {
+    /// The consumption at the given step relative to the average consumption. Step 0 is the first quarter of an hour of
+    /// the year, the profile repeats each year.
+    pub fn relative_consumption(&self, step: &Steps) -> BaseFloat {
+        if self.values.is_empty() {
+            return 0.0;
+        }
+        let minutes = step.0.max(0) as usize * Steps::minutes_per_step().0 as usize;
+        self.values[(minutes / MINUTES_PER_VALUE) % self.values.len()]
+    }
+}
+
+impl Debug for ConsumptionProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConsumptionProfile")
+            .field("Category", &self.category)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod profile_tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    const CSV: &str = "Tijd;E1A_AZI_A;E1A_AMI_A;E1A_AMI_I;E1B_AZI_A;E3A_AZI_A
+01-01-2023 00:15;0,1;0.2;0.0;0.3;9.0
+01-01-2023 00:30;0,3;0.2;0.0;0.1;9.0
+01-01-2023 00:45;0,2;0.2;0.4;0.2;1.0
+";
+
+    fn assert_close(left: &[BaseFloat], right: &[BaseFloat]) {
+        assert_eq!(left.len(), right.len());
+        assert!(
+            left.iter().zip(right).all(|(l, r)| (l - r).abs() < 1e-5),
+            "{left:?} != {right:?}"
+        );
+    }
+
+    #[test]
+    fn profiles_from_csv() {
+        let categories = vec![("AZI".to_string(), 0.5), ("AMI_A".to_string(), 0.5)];
+        let profiles = StandardProfiles::from_csv_string(CSV, categories).unwrap();
+        assert_close(&profiles.values[0], &[1.0, 1.0, 1.0]);
+        assert_close(&profiles.values[1], &[1.0, 1.0, 1.0]);
+
+        let profiles = StandardProfiles::from_csv_string(CSV, vec![("AMI".to_string(), 1.0)]).unwrap();
+        let profile = profiles.pick(&mut SmallRng::seed_from_u64(117));
+        assert_eq!(profile.category, "AMI");
+        assert_close(
+            &[
+                profile.relative_consumption(&Steps(2)),
+                profile.relative_consumption(&Steps(3)),
+            ],
+            &[1.8, 0.6],
+        );
+
+        assert!(StandardProfiles::from_csv_string(CSV, vec![("XYZ".to_string(), 1.0)]).is_err());
+        assert!(!serde_json::to_string(&profiles).unwrap().contains("values"));
+    }
+
+    #[test]
+    fn categories_picked_by_share() {
+        let categories = vec![("AZI".to_string(), 0.0), ("AMI_A".to_string(), 1.0)];
+        let profiles = StandardProfiles::from_csv_string(CSV, categories).unwrap();
+        let mut seed = SmallRng::seed_from_u64(117);
+        assert!((0..20).all(|_| profiles.pick(&mut seed).category == "AMI_A"));
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::Arc;
+
+use apache_avro::AvroSchema;
+use rand::rngs::SmallRng;
+use serde::{Deserialize, Serialize};
+
+#[allow(unused_imports)]
+use crate::agent::Household;
+use crate::util::{gen_csv_table, norm_dist, read_file, uni_dist, BaseFloat, ConfigError, Steps, UtilError, Watt};
+
+/// The measured power of a meter for each step.
+type MeterSeries = Arc<Vec<Watt>>;
+
+/// Measured time series of smart meters that are replayed by the [Household] agents, e.g. handed over by the DSO for a
+/// case study of a neighbourhood.
+///
+/// Each file is a CSV file with the time in the first column and a column per anonymised meter, with the average power
+/// in W for each step. The generation file is optional and matches its meters to the consumption by the name of the
+/// column. A household samples a meter, shifts it in time by at most the given number of steps and scales it.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeterData {
+    /// The file the consumption is loaded from.
+    pub consumption_file: String,
+    /// The file the generation is loaded from, if any.
+    pub generation_file:  Option<String>,
+    /// The names of the meters.
+    pub meters:           Vec<String>,
+    /// The measured consumption of each meter.
+    #[serde(skip)]
+    pub consumption:      Vec<MeterSeries>,
+    /// The measured generation of each meter, if any.
+    #[serde(skip)]
+    pub generation:       Vec<Option<MeterSeries>>,
+    /// Maximum number of steps a series is shifted in time, in both directions.
+    pub shift:            Steps,
+    /// The normal distribution of the scale of a series. First is center, second value is std deviation.
+    pub scale:            (BaseFloat, BaseFloat),
+}
+
+impl MeterData {
+    /// Loads the measured consumption, and optionally generation, from CSV files.
+    pub fn from_csv_files(
+        consumption_file: impl AsRef<Path>,
+        generation_file: Option<impl AsRef<Path>>,
+        shift: Steps,
+        scale: (BaseFloat, BaseFloat),
+    ) -> Result<Self, ConfigError> {
+        let generation = match &generation_file {
+            Some(file) => Some(read_file(file)?),
+            None => None,
+        };
+        Ok(Self {
+            consumption_file: consumption_file.as_ref().display().to_string(),
+            generation_file: generation_file.map(|f| f.as_ref().display().to_string()),
+            ..Self::from_csv_strings(&read_file(&consumption_file)?, generation.as_deref(), shift, scale)?
+        })
+    }
+
+    fn from_csv_strings(
+        consumption: &str,
+        generation: Option<&str>,
+        shift: Steps,
+        scale: (BaseFloat, BaseFloat),
+    ) -> Result<Self, ConfigError> {
+        let (meters, consumption) = Self::series_from_csv(consumption)?;
+        if meters.is_empty() {
+            return Err(ConfigError::NumElementsTooLow(
+                "Number of elements is too low. Expected at least 1 meter".to_string(),
+            ));
+        }
+        let (generation_meters, generation_series) = match generation {
+            Some(generation) => Self::series_from_csv(generation)?,
+            None => (vec![], vec![]),
+        };
+        let generation = meters
+            .iter()
+            .map(|m| {
+                generation_meters
+                    .iter()
+                    .position(|g| g == m)
+                    .map(|i| generation_series[i].clone())
+            })
+            .collect();
+        Ok(Self {
+            consumption_file: String::new(),
+            generation_file: None,
+            meters,
+            consumption,
+            generation,
+            shift,
+            scale,
+        })
+    }
+
+    /// The names of the meters and their series. The first column holds the time and is skipped.
+    fn series_from_csv(csv_string: &str) -> Result<(Vec<String>, Vec<MeterSeries>), ConfigError> {
+        let (header, rows) = gen_csv_table(csv_string)?;
+        let mut series = vec![];
+        for column in 1..header.len() {
+            let mut values = vec![];
+            for row in &rows {
+                values.push(match row.get(column).map(String::as_str) {
+                    None | Some("") => Watt(0),
+                    Some(v) => Watt(
+                        v.parse::<f64>()
+                            .map_err(|e| ConfigError::ParsingError(format!("{v} in the meter file: {e}")))?
+                            as i64,
+                    ),
+                });
+            }
+            series.push(Arc::new(values));
+        }
+        Ok((header.into_iter().skip(1).collect(), series))
+    }
+
+    /// Samples a meter for a household, with a random shift and scale.
+    pub fn pick(&self, seed: &mut SmallRng) -> Result<ReplayProfile, UtilError> {
+        let index = uni_dist((0, self.meters.len() as i64), seed) as usize;
+        let shift = if self.shift > Steps(0) {
+            Steps(uni_dist((-self.shift.0, self.shift.0 + 1), seed) as i32)
+        } else {
+            Steps(0)
+        };
+        let scale = if self.scale.1 > 0.0 {
+            norm_dist(&self.scale.0, &self.scale.1, seed)?.max(0.0)
+        } else {
+            self.scale.0
+        };
+        Ok(ReplayProfile {
+            meter: self.meters[index].clone(),
+            shift,
+            scale,
+            consumption: self.consumption[index].clone(),
+            generation: self.generation[index].clone(),
+        })
+    }
+
+    /// The series of the given meter with the given shift and scale, if it is one of the meters.
+    pub fn get(&self, meter: &str, shift: Steps, scale: BaseFloat) -> Option<ReplayProfile> {
+        let index = self.meters.iter().position(|m| m == meter)?;
+        Some(ReplayProfile {
+            meter: meter.to_string(),
+            shift,
+            scale,
+            consumption: self.consumption[index].clone(),
+            generation: self.generation[index].clone(),
+        })
+    }
+}
+
+impl Debug for MeterData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MeterData")
+            .field("ConsumptionFile", &self.consumption_file)
+            .field("GenerationFile", &self.generation_file)
+            .field("Meters", &self.meters.len())
+            .field("Shift", &self.shift)
+            .field("Scale", &self.scale)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The measured series of a single meter that is replayed by a [Household].
+#[derive(Clone, PartialEq, Serialize, Deserialize, AvroSchema)]
+pub struct ReplayProfile {
+    /// The name of the meter.
+    pub meter:   String,
+    /// Number of steps the series is shifted in time.
+    pub shift:   Steps,
+    /// Scale of the series.
+    pub scale:   BaseFloat,
+    #[serde(skip)]
+    #[avro(skip)]
+    consumption: MeterSeries,
+    #[serde(skip)]
+    #[avro(skip)]
+    generation:  Option<MeterSeries>,
+}
+
+impl ReplayProfile {
+    /// Does the meter measure a generation as well?
+    pub fn has_generation(&self) -> bool { self.generation.is_some() }
+
+    /// The generated and used power at the given step. The series repeat after their last value.
+    pub fn power(&self, step: &Steps) -> (Watt, Watt) {
+        let value = |series: &[Watt]| {
+            if series.is_empty() {
+                Watt(0)
+            } else {
+                let index = (step.0 + self.shift.0).rem_euclid(series.len() as i32) as usize;
+                Watt((series[index].0 as f64 * f64::from(self.scale)) as i64)
+            }
+        };
+        (
+            self.generation.as_ref().map_or(Watt(0), |g| value(g)),
+            value(&self.consumption),
+        )
+    }
+}
+
+impl Debug for ReplayProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReplayProfile")
+            .field("Meter", &self.meter)
+            .field("Shift", &self.shift)
+            .field("Scale", &self.scale)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod replay_tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    const CONSUMPTION: &str = "time,meter_a,meter_b
+2023-01-01 00:15,100,400
+2023-01-01 00:30,200,500
+2023-01-01 00:45,300,600
+";
+    const GENERATION: &str = "time;meter_b
+2023-01-01 00:15;10,5
+2023-01-01 00:30;20
+2023-01-01 00:45;30
+";
+
+    #[test]
+    fn meters_from_csv() {
+        let data = MeterData::from_csv_strings(CONSUMPTION, Some(GENERATION), Steps(0), (1.0, 0.0)).unwrap();
+        assert_eq!(data.meters, vec!["meter_a", "meter_b"]);
+        assert_eq!(*data.consumption[1], vec![Watt(400), Watt(500), Watt(600)]);
+        assert!(data.generation[0].is_none());
+        assert_eq!(data.generation[1].as_deref(), Some(&vec![Watt(10), Watt(20), Watt(30)]));
+
+        let serialized = serde_json::to_string(&data).unwrap();
+        assert!(serialized.contains("meter_b"));
+        assert!(!serialized.contains("400"));
+    }
+
+    #[test]
+    fn replay_shifted_and_scaled() {
+        let data = MeterData::from_csv_strings(CONSUMPTION, Some(GENERATION), Steps(1), (2.0, 0.0)).unwrap();
+        let mut seed = SmallRng::seed_from_u64(117);
+        for _ in 0..10 {
+            let replay = data.pick(&mut seed).unwrap();
+            assert!((-1..=1).contains(&replay.shift.0));
+            assert_eq!(replay.scale, 2.0);
+            let (generated, used) = replay.power(&Steps(4 - replay.shift.0));
+            if replay.meter == "meter_b" {
+                assert!(replay.has_generation());
+                assert_eq!((generated, used), (Watt(40), Watt(1_000)));
+            } else {
+                assert_eq!((generated, used), (Watt(0), Watt(400)));
+            }
+        }
+    }
+}
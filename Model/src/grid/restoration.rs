@@ -0,0 +1,160 @@
+use apache_avro::AvroSchema;
+use serde::{Deserialize, Serialize};
+
+#[allow(unused_imports)]
+use crate::agent::{Area, Household, Netstation, Root};
+use crate::grid::NOMINAL_FREQUENCY;
+use crate::util::{mHz, mPercent, Steps, Watt};
+
+/// The parameters of the restoration after a blackout, as given by the GridParameters file.
+///
+/// After the black start time, the [Area] agents are restored one after the other in the given sequence. Within an
+/// area the [Netstation] agents are energised in order, as long as the load that is picked up in a single step stays
+/// within the load pickup limit. At least one netstation is energised each step.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestorationParam {
+    /// Steps between the blackout and the start of the restoration.
+    pub black_start_time:   Steps,
+    /// The areas that are restored first, numbered from 1. The other areas follow in their own order.
+    pub sequence:           Vec<usize>,
+    /// Maximum load that is picked up in a single step. The load of a netstation counts in absolute value, as a net
+    /// generation is a sudden change in power as well.
+    pub load_pickup_limit:  Watt,
+    /// Steps a PV inverter waits after its netstation is energised before it reconnects.
+    pub pv_reconnect_delay: Steps,
+    /// A PV inverter only reconnects if the frequency deviates less than this from the nominal frequency.
+    pub pv_reconnect_band:  mHz,
+}
+
+impl RestorationParam {
+    /// The order in which the areas are restored, as index of the list of areas.
+    pub fn order(&self, n_areas: usize) -> Vec<usize> {
+        let mut order: Vec<usize> = self
+            .sequence
+            .iter()
+            .filter(|a| (1..=n_areas).contains(*a))
+            .map(|a| a - 1)
+            .collect();
+        for area in 0..n_areas {
+            if !order.contains(&area) {
+                order.push(area);
+            }
+        }
+        order
+    }
+
+    /// May a PV inverter reconnect at this frequency?
+    pub fn frequency_allows_reconnect(&self, frequency: mHz) -> bool {
+        (frequency.0 - NOMINAL_FREQUENCY.0).abs() < self.pv_reconnect_band.0
+    }
+}
+
+/// A blackout of the grid and the progress of its restoration.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Blackout {
+    /// The step the blackout started.
+    pub start:         Steps,
+    /// Share of the netstations that are energised again.
+    pub restored:      mPercent,
+    /// Steps between the blackout and the moment all netstations are energised again.
+    pub recovery_time: Option<Steps>,
+}
+
+impl Blackout {
+    /// Creates a new blackout that starts at the given step.
+    pub const fn new(start: Steps) -> Self {
+        Self {
+            start,
+            restored: mPercent(0),
+            recovery_time: None,
+        }
+    }
+}
+
+/// The supply of a [Household]. Is it energised and is its PV installation connected?
+///
+/// After its netstation is energised, the PV inverter waits for the reconnection delay and a frequency close to
+/// nominal. Infected inverters can be made to reconnect directly, while the operator still expects them to follow the
+/// rules.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, AvroSchema)]
+pub struct SupplyState {
+    /// Is the household energised?
+    pub energised:          bool,
+    /// Is the PV installation connected?
+    pub pv_connected:       bool,
+    /// Would the PV installation be connected if it followed the reconnection rules?
+    pub pv_clean_connected: bool,
+    /// The first step the PV installation may reconnect.
+    pub pv_reconnect_at:    Steps,
+}
+
+impl Default for SupplyState {
+    fn default() -> Self {
+        Self {
+            energised:          true,
+            pv_connected:       true,
+            pv_clean_connected: true,
+            pv_reconnect_at:    Steps(0),
+        }
+    }
+}
+
+impl SupplyState {
+    /// The supply is lost. The PV installation disconnects.
+    pub fn de_energise(&mut self) {
+        self.energised = false;
+        self.pv_connected = false;
+        self.pv_clean_connected = false;
+    }
+
+    /// The supply is restored. The PV installation may reconnect after the delay, or later if it is curtailed.
+    pub fn energise(&mut self, step: Steps, delay: Steps) {
+        self.energised = true;
+        self.pv_reconnect_at = self.pv_reconnect_at.max(step + delay);
+    }
+
+    /// Reconnect the PV installation if the rules allow it. With a bypass, the installation reconnects directly.
+    pub fn reconnect_pv(&mut self, step: Steps, frequency_allows: bool, bypass: bool) {
+        if !self.energised {
+            return;
+        }
+        let allowed = step >= self.pv_reconnect_at && frequency_allows;
+        self.pv_clean_connected |= allowed;
+        self.pv_connected |= allowed || bypass;
+    }
+}
+
+#[cfg(test)]
+mod restoration_tests {
+    use super::*;
+
+    #[test]
+    fn restoration_order() {
+        let param = RestorationParam {
+            black_start_time:   Steps(4),
+            sequence:           vec![3, 1, 7],
+            load_pickup_limit:  Watt(1_000),
+            pv_reconnect_delay: Steps(2),
+            pv_reconnect_band:  mHz(100),
+        };
+        assert_eq!(param.order(4), vec![2, 0, 1, 3]);
+        assert!(param.frequency_allows_reconnect(mHz(49_950)));
+        assert!(!param.frequency_allows_reconnect(mHz(49_800)));
+    }
+
+    #[test]
+    fn pv_reconnects_after_delay() {
+        let mut supply = SupplyState::default();
+        supply.de_energise();
+        supply.reconnect_pv(Steps(10), true, true);
+        assert!(!supply.pv_connected);
+
+        supply.energise(Steps(10), Steps(2));
+        supply.reconnect_pv(Steps(10), true, false);
+        assert!(!supply.pv_connected);
+        supply.reconnect_pv(Steps(11), true, true);
+        assert!(supply.pv_connected && !supply.pv_clean_connected);
+        supply.reconnect_pv(Steps(12), true, false);
+        assert!(supply.pv_clean_connected);
+    }
+}
@@ -1,11 +1,13 @@
 use crate::grid::{BoundaryUnitTrait, Grid};
 use crate::util::Watt;
 
+mod area;
 mod frequency;
 mod infectionstate;
 mod power;
 mod volt;
 
+pub use area::*;
 pub use frequency::*;
 pub use infectionstate::*;
 pub use power::*;
@@ -0,0 +1,22 @@
+use apache_avro::AvroSchema;
+use serde::{Deserialize, Serialize};
+
+#[allow(unused_imports)]
+use crate::agent::{Area, Netstation};
+use crate::util::{mPercent, Watt};
+
+/// Aggregated health of an [Area]. Determined from the voltage states of its [Netstation] agents and the power state
+/// of the area itself.
+#[derive(Serialize, Clone, Copy, AvroSchema, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct AreaState {
+    /// Share of netstations that are not in the normal voltage band.
+    pub unhealthy: mPercent,
+    /// Share of netstations with a voltage above the normal band.
+    pub high:      mPercent,
+    /// Share of netstations with a voltage below the normal band.
+    pub low:       mPercent,
+    /// Share of the PV installations, batteries, EV chargers and heat pumps below the area that are infected.
+    pub infected:  mPercent,
+    /// Power mismatch of the area.
+    pub imbalance: Watt,
+}
@@ -6,6 +6,9 @@ use serde::{Deserialize, Serialize};
 use crate::grid::{Grid, GridState};
 use crate::util::{mHz, BaseInt, Watt};
 
+/// The nominal frequency of the grid.
+pub const NOMINAL_FREQUENCY: mHz = mHz(50_000);
+
 /// Frequency state of an agent. With frequency, this is only the root node.
 #[derive(Serialize, Clone, Debug, AvroSchema, Deserialize, Default)]
 pub struct FreqState {
@@ -13,7 +16,7 @@ pub struct FreqState {
     pub now:         mHz,
     /// History of the values.
     pub history:     Vec<mHz>,
-    /// How much history is kept?
+    /// How much history is kept? At least the window of the rate limit of the frequency, if any.
     pub history_len: BaseInt,
 }
 
@@ -23,11 +26,11 @@ impl GridState<mHz> for FreqState {
             + (f64::from(self.now.0) * power_error.0 as f64 / (power_total.0 + bulk_consumption.0) as f64) as BaseInt)
     }
 
-    fn new(_: &Grid) -> Self {
+    fn new(grid: &Grid) -> Self {
         Self {
-            now:         mHz(50_000),
+            now:         NOMINAL_FREQUENCY,
             history:     vec![],
-            history_len: 10,
+            history_len: grid.freq_boundary.rate_limit.map_or(10, |r| r.window.0.max(10)),
         }
     }
 
@@ -52,6 +55,8 @@ impl Display for FreqState {
 #[cfg(test)]
 mod test_freq_state {
     use super::*;
+    use crate::grid::RateLimit;
+    use crate::util::Steps;
 
     fn test_create_freq_state() -> FreqState {
         FreqState {
@@ -81,4 +86,22 @@ mod test_freq_state {
         let new = fs.power_mismatch(&100.into(), &Watt(-10), &1000.into());
         assert_eq!(new, mHz(49546));
     }
+
+    #[test]
+    fn history_covers_rate_window() {
+        let mut grid = Grid::_test();
+        grid.freq_boundary.rate_limit = Some(RateLimit {
+            max_change:       mHz(500),
+            window:           Steps(20),
+            max_time_allowed: Steps(0),
+            time_passed:      Steps(0),
+        });
+        let mut fs = FreqState::new(&grid);
+        for _ in 0..30 {
+            fs.update(NOMINAL_FREQUENCY);
+        }
+        assert_eq!(fs.history.len(), 20);
+        let warning = grid.freq_boundary.check_rate(mHz(49_000), &fs.history).unwrap();
+        assert!(warning.critical);
+    }
 }
@@ -6,12 +6,14 @@ use derive_more::{Add, Sum};
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 
+#[allow(unused_imports)]
+use crate::agent::Battery;
 use crate::grid::PowerGeneration;
 #[cfg(feature = "single_thread")]
 use crate::norayon::prelude::*;
 use crate::util::{BaseFloat, BaseInt};
 
-/// All possible states that a PV system can have regarding being infected.
+/// All possible states that a PV system or [Battery] can have regarding being infected.
 #[derive(Clone, Copy, Debug, Serialize, AvroSchema, Deserialize, PartialEq, Eq, PartialOrd, Hash)]
 pub enum InfectionState {
     /// Can not be infected.
@@ -25,6 +27,20 @@ pub enum InfectionState {
     Patched,
 }
 
+impl InfectionState {
+    /// The next state given the outcome of the infection and patch draws. Patching takes precedence over infecting.
+    pub const fn progress(self, will_infect: bool, will_patch: bool) -> Self {
+        match (self, will_infect, will_patch) {
+            (Self::NotVulnerable, ..) => Self::NotVulnerable,
+            (Self::Patched, ..) => Self::Patched,
+            (Self::Infected | Self::Vulnerable, _, true) => Self::Patched,
+            (Self::Infected, _, false) => Self::Infected,
+            (Self::Vulnerable, true, false) => Self::Infected,
+            (Self::Vulnerable, false, false) => Self::Vulnerable,
+        }
+    }
+}
+
 /// A helper struct to determine the state of the grid.
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Sum, Add)]
 pub struct InfectionCount {
@@ -39,10 +55,15 @@ pub struct InfectionCount {
     pub patched:        BaseInt,
 }
 
+impl InfectionCount {
+    /// The number of devices counted.
+    pub const fn total(&self) -> BaseInt { self.not_vulnerable + self.vulnerable + self.infected + self.patched }
+}
+
 /// A helper struct to determine the statistics on the infection in the grid.
 #[derive(Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct InfectionStatistics {
-    /// Total amount of [PowerGeneration] devices
+    /// Total amount of [PowerGeneration] and other devices counted
     pub(crate) total:               BaseInt,
     pub(crate) num_not_vulnerable:  BaseInt,
     pub(crate) num_vulnerable:      BaseInt,
@@ -60,20 +81,30 @@ impl InfectionStatistics {
     /// Only input Households
     pub(crate) fn new(states: &[InfectionState]) -> Self {
         // Info
-        let (num_not_vulnerable, num_vulnerable, num_infected, num_patched) = count_states(states);
+        let (not_vulnerable, vulnerable, infected, patched) = count_states(states);
+        Self::from(InfectionCount {
+            not_vulnerable,
+            vulnerable,
+            infected,
+            patched,
+        })
+    }
+}
 
-        let total = num_not_vulnerable + num_infected + num_vulnerable + num_patched;
-        let perc_not_vulnerable = (num_not_vulnerable * 100) as f32 / total as f32;
-        let perc_infected = (num_infected * 100) as f32 / total as f32;
-        let perc_vulnerable = (num_vulnerable * 100) as f32 / total as f32;
-        let perc_patched = (num_patched * 100) as f32 / total as f32;
+impl From<InfectionCount> for InfectionStatistics {
+    fn from(count: InfectionCount) -> Self {
+        let total = count.total();
+        let perc_not_vulnerable = (count.not_vulnerable * 100) as f32 / total as f32;
+        let perc_infected = (count.infected * 100) as f32 / total as f32;
+        let perc_vulnerable = (count.vulnerable * 100) as f32 / total as f32;
+        let perc_patched = (count.patched * 100) as f32 / total as f32;
 
         Self {
             total,
-            num_not_vulnerable,
-            num_vulnerable,
-            num_infected,
-            num_patched,
+            num_not_vulnerable: count.not_vulnerable,
+            num_vulnerable: count.vulnerable,
+            num_infected: count.infected,
+            num_patched: count.patched,
             perc_not_vulnerable,
             perc_vulnerable,
             perc_infected,
@@ -81,6 +112,7 @@ impl InfectionStatistics {
         }
     }
 }
+
 #[cfg(not(feature = "single_thread"))]
 fn count_states(states: &[InfectionState]) -> (i32, i32, i32, i32) {
     let (num_not_vulnerable, num_vulnerable, num_infected, num_patched) = states
@@ -25,6 +25,8 @@ pub struct PowerState {
     /// Current error between power used and power
     /// reported.
     pub power_error:                    Watt,
+    /// Power lost in the lines below the agent.
+    pub power_lost:                     Watt,
     pub(crate) history_power_used:      Vec<Watt>,
     pub(crate) history_power_generated: Vec<Watt>,
     pub(crate) history_power_reported:  Vec<Watt>,
@@ -41,6 +43,7 @@ impl PowerState {
             power_generated:         Watt(0),
             power_reported:          Watt(0),
             power_error:             Watt(0),
+            power_lost:              Watt(0),
             history_power_used:      vec![],
             history_power_reported:  vec![],
             history_power_error:     vec![],
@@ -68,6 +71,33 @@ impl PowerState {
         self.power_reported += rhs.power_reported;
         self.power_error += rhs.power_error;
         self.power_generated += rhs.power_generated;
+        self.power_lost += rhs.power_lost;
+    }
+
+    /// Set the power to that of another powerstate, without its history.
+    pub(crate) fn manual_set(&mut self, rhs: &Self) {
+        self.power_used = rhs.power_used;
+        self.power_reported = rhs.power_reported;
+        self.power_error = rhs.power_error;
+        self.power_generated = rhs.power_generated;
+        self.power_lost = rhs.power_lost;
+    }
+
+    /// Add the losses of a line to the agent. The losses are consumed by the agent, so they add to the power used and
+    /// lower the power error.
+    pub fn add_losses(&mut self, losses: Watt) {
+        self.power_used += losses;
+        self.power_lost += losses;
+        self.power_error -= losses;
+    }
+
+    /// Clear the power of the agent, as it is not energised.
+    pub fn de_energise(&mut self) {
+        self.power_used = Watt(0);
+        self.power_generated = Watt(0);
+        self.power_reported = Watt(0);
+        self.power_error = Watt(0);
+        self.power_lost = Watt(0);
     }
 
     /// Attack the PowerState using the [AttackBehaviour] given.
@@ -96,6 +126,7 @@ impl Add for PowerState {
             power_reported:          self.power_reported + rhs.power_reported,
             power_error:             self.power_error + rhs.power_error,
             power_generated:         self.power_generated + rhs.power_generated,
+            power_lost:              self.power_lost + rhs.power_lost,
             history_power_used:      vec![],
             history_power_reported:  vec![],
             history_power_error:     vec![],
@@ -111,6 +142,7 @@ impl AddAssign for PowerState {
         self.power_reported += rhs.power_reported;
         self.power_error += rhs.power_error;
         self.power_generated += rhs.power_generated;
+        self.power_lost += rhs.power_lost;
     }
 }
 
@@ -196,6 +228,7 @@ mod states_tests {
             power_used:              Watt(40),
             power_reported:          Watt(40),
             power_error:             Watt(40),
+            power_lost:              Watt(0),
             power_generated:         Watt(40),
             history_power_generated: vec![],
             history_power_used:      vec![],
@@ -207,6 +240,7 @@ mod states_tests {
             power_used:              Watt(40),
             power_reported:          Watt(40),
             power_error:             Watt(40),
+            power_lost:              Watt(0),
             power_generated:         Watt(40),
             history_power_generated: vec![],
             history_power_used:      vec![],
@@ -218,6 +252,7 @@ mod states_tests {
             power_used:              Watt(80),
             power_reported:          Watt(80),
             power_error:             Watt(80),
+            power_lost:              Watt(0),
             power_generated:         Watt(80),
             history_power_generated: vec![],
             history_power_used:      vec![],
@@ -243,8 +278,8 @@ mod states_tests {
     fn check_powerstate_calc() {
         let mut param = ModelParameters::test();
         let mut agent = Netstation::new(117, &param.grid);
-        let mut child1 = Household::new(117, PowerGeneration::new_no_pv(Some(117), &mut param).unwrap());
-        let mut child2 = Household::new(117, PowerGeneration::new_pv(Some(117), &mut param).unwrap());
+        let mut child1 = Household::new(117, PowerGeneration::new_no_pv(Some(117), None, &mut param).unwrap());
+        let mut child2 = Household::new(117, PowerGeneration::new_pv(Some(117), None, &mut param).unwrap());
 
         let (ps1, ps2, ps3) = powerstate_test();
         *child1.powerstate_mut() = ps1;
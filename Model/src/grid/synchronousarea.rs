@@ -0,0 +1,104 @@
+use std::fmt::Debug;
+
+use serde::{Deserialize, Serialize};
+use thousands::Separable;
+
+#[allow(unused_imports)]
+use crate::agent::Root;
+use crate::grid::NOMINAL_FREQUENCY;
+use crate::util::{mHz, Watt};
+
+/// The synchronous area the grid is a part of. The rest of the continental grid is modelled as a single aggregated
+/// external system that is connected to the [Root] by tie-lines.
+///
+/// The external system responds to the frequency deviation of the grid using its power frequency characteristic. The
+/// resulting exchange over the tie-lines is limited by their capacity and never exceeds the current power mismatch.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SynchronousArea {
+    /// How much power can be imported over the tie-lines?
+    pub import_capacity:                Watt,
+    /// How much power can be exported over the tie-lines?
+    pub export_capacity:                Watt,
+    /// Response of the external system in Watt per mHz of frequency deviation.
+    pub power_frequency_characteristic: Watt,
+    /// Current exchange over the tie-lines. Positive indicates an export.
+    pub current_exchange:               Watt,
+}
+
+impl SynchronousArea {
+    /// Determine the exchange over the tie-lines given the frequency of the grid and the power mismatch.
+    /// Returns the amount of Watt that is exported, negative values are imports.
+    pub fn exchange(&mut self, frequency: mHz, power_error: Watt) -> Watt {
+        let deviation = i64::from(frequency.0 - NOMINAL_FREQUENCY.0);
+        let response = (self.power_frequency_characteristic * deviation).0;
+        let absorbed = if power_error.0 > 0 {
+            response.clamp(0, power_error.0)
+        } else {
+            response.clamp(power_error.0, 0)
+        };
+        self.current_exchange = Watt(absorbed.clamp(-self.import_capacity.0, self.export_capacity.0));
+        self.current_exchange
+    }
+}
+
+impl Debug for SynchronousArea {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SynchronousArea")
+            .field(
+                "import_capacity",
+                &format!("Watt({})", self.import_capacity.0.separate_with_underscores()),
+            )
+            .field(
+                "export_capacity",
+                &format!("Watt({})", self.export_capacity.0.separate_with_underscores()),
+            )
+            .field(
+                "power_frequency_characteristic",
+                &format!(
+                    "Watt({})",
+                    self.power_frequency_characteristic.0.separate_with_underscores()
+                ),
+            )
+            .field(
+                "current_exchange",
+                &format!("Watt({})", self.current_exchange.0.separate_with_underscores()),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test_synchronous_area {
+    use super::*;
+
+    fn test_create_synchronous_area() -> SynchronousArea {
+        SynchronousArea {
+            import_capacity: Watt(100),
+            export_capacity: Watt(50),
+            power_frequency_characteristic: Watt(10),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_exchange_nominal_frequency() {
+        let mut sa = test_create_synchronous_area();
+        assert_eq!(sa.exchange(NOMINAL_FREQUENCY, Watt(40)), Watt(0));
+    }
+
+    #[test]
+    fn test_exchange_limited_by_power_error() {
+        let mut sa = test_create_synchronous_area();
+        assert_eq!(sa.exchange(mHz(50_002), Watt(40)), Watt(20));
+        assert_eq!(sa.exchange(mHz(50_005), Watt(40)), Watt(40));
+        assert_eq!(sa.exchange(mHz(50_005), Watt(-40)), Watt(0));
+    }
+
+    #[test]
+    fn test_exchange_limited_by_capacity() {
+        let mut sa = test_create_synchronous_area();
+        assert_eq!(sa.exchange(mHz(50_100), Watt(1_000)), Watt(50));
+        assert_eq!(sa.exchange(mHz(49_900), Watt(-1_000)), Watt(-100));
+        assert_eq!(sa.current_exchange, Watt(-100));
+    }
+}
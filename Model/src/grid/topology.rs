@@ -0,0 +1,253 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[allow(unused_imports)]
+use crate::agent::{Area, Household, Netstation};
+use crate::util::{gen_csv_table, read_file, BaseInt, ConfigError, Watt};
+
+/// A topology of the grid that is imported instead of drawn at random, e.g. from an anonymised inventory of
+/// netstations handed over by the DSO.
+///
+/// The topology lists the [Area] agents and the [Netstation] agents within each of them, in the order they are
+/// created. The attributes of a netstation are optional. Without a number of households, the number is drawn as for a
+/// random topology. Without a rating, the netstation is fed as configured for the grid.
+///
+/// Three formats are supported, recognised by the extension of the file:
+/// - `.json`, a graph of areas, each with a name and a list of netstations.
+/// - `.csv`, a row per netstation with the columns `area` and `netstation`, and optionally `households`, `rating`,
+///   `longitude` and `latitude`.
+/// - `.geojson`, a feature collection with a point per netstation and the same columns as properties.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Topology {
+    /// The areas of the grid.
+    pub areas: Vec<AreaTopology>,
+}
+
+/// An [Area] of an imported [Topology].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AreaTopology {
+    /// The name of the area.
+    pub name:        String,
+    /// The netstations of the area.
+    pub netstations: Vec<NetstationTopology>,
+}
+
+/// A [Netstation] of an imported [Topology].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetstationTopology {
+    /// The name of the netstation.
+    pub name:        String,
+    /// Number of [Household] agents connected to the netstation.
+    #[serde(default, alias = "connections")]
+    pub households:  Option<BaseInt>,
+    /// Rating of the transformer, used as capacity of the line that feeds the netstation.
+    #[serde(default)]
+    pub rating:      Option<Watt>,
+    /// Location of the netstation as (longitude, latitude).
+    #[serde(default)]
+    pub coordinates: Option<(f64, f64)>,
+}
+
+impl Topology {
+    /// Loads the topology from a JSON, CSV or GeoJSON file.
+    pub fn from_file(filepath: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let extension = filepath
+            .as_ref()
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        let content = read_file(&filepath)?;
+        match extension.as_str() {
+            "json" => Self::from_json_str(&content),
+            "csv" => Self::from_csv_str(&content),
+            "geojson" => Self::from_geojson_str(&content),
+            _ => Err(ConfigError::NotPermittedOption(format!(
+                "{} is not a JSON, CSV or GeoJSON file",
+                filepath.as_ref().display()
+            ))),
+        }
+    }
+
+    /// Number of netstations over all areas.
+    pub fn num_netstations(&self) -> usize { self.areas.iter().map(|a| a.netstations.len()).sum() }
+
+    fn from_json_str(s: &str) -> Result<Self, ConfigError> {
+        let topology: Self =
+            serde_json::from_str(s).map_err(|e| ConfigError::ParsingError(format!("the topology file: {e}")))?;
+        topology.validated()
+    }
+
+    fn from_csv_str(s: &str) -> Result<Self, ConfigError> {
+        let (header, rows) = gen_csv_table(s)?;
+        let column = |name: &str| header.iter().position(|h| h.eq_ignore_ascii_case(name));
+        let (Some(area), Some(netstation)) = (column("area"), column("netstation")) else {
+            return Err(ConfigError::ParsingError(
+                "the topology file needs the columns area and netstation".to_string(),
+            ));
+        };
+        let (households, rating) = (column("households"), column("rating"));
+        let (longitude, latitude) = (column("longitude"), column("latitude"));
+
+        let mut netstations = vec![];
+        for row in &rows {
+            let value = |column: Option<usize>| {
+                column
+                    .and_then(|c| row.get(c))
+                    .map(String::as_str)
+                    .filter(|v| !v.is_empty())
+            };
+            let coordinates = match (parse(value(longitude))?, parse(value(latitude))?) {
+                (Some(longitude), Some(latitude)) => Some((longitude, latitude)),
+                _ => None,
+            };
+            netstations.push((
+                value(Some(area)).unwrap_or_default().to_string(),
+                NetstationTopology {
+                    name: value(Some(netstation)).unwrap_or_default().to_string(),
+                    households: parse::<f64>(value(households))?.map(|h| h as BaseInt),
+                    rating: parse::<f64>(value(rating))?.map(|r| Watt(r as i64)),
+                    coordinates,
+                },
+            ));
+        }
+        Self::from_netstations(netstations).validated()
+    }
+
+    fn from_geojson_str(s: &str) -> Result<Self, ConfigError> {
+        let collection: Value =
+            serde_json::from_str(s).map_err(|e| ConfigError::ParsingError(format!("the topology file: {e}")))?;
+        let Some(features) = collection.get("features").and_then(Value::as_array) else {
+            return Err(ConfigError::ParsingError(
+                "the topology file is not a GeoJSON feature collection".to_string(),
+            ));
+        };
+
+        let mut netstations = vec![];
+        for feature in features {
+            let empty = Map::new();
+            let properties = feature.get("properties").and_then(Value::as_object).unwrap_or(&empty);
+            let text = |key: &str| match properties.get(key) {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Null) | None => String::new(),
+                Some(v) => v.to_string(),
+            };
+            let number = |key: &str| properties.get(key).and_then(Value::as_f64);
+            let geometry = feature.get("geometry").filter(|g| !g.is_null());
+            let coordinates = match geometry {
+                Some(g) if g.get("type").and_then(Value::as_str) == Some("Point") => {
+                    match g.get("coordinates").and_then(Value::as_array).map(Vec::as_slice) {
+                        Some([longitude, latitude, ..]) => longitude.as_f64().zip(latitude.as_f64()),
+                        _ => None,
+                    }
+                }
+                Some(_) => {
+                    return Err(ConfigError::NotPermittedOption(format!(
+                        "the geometry of netstation {} is not a point",
+                        text("netstation")
+                    )))
+                }
+                None => None,
+            };
+            netstations.push((
+                text("area"),
+                NetstationTopology {
+                    name: text("netstation"),
+                    households: number("households").map(|h| h as BaseInt),
+                    rating: number("rating").map(|r| Watt(r as i64)),
+                    coordinates,
+                },
+            ));
+        }
+        Self::from_netstations(netstations).validated()
+    }
+
+    /// Groups the netstations by the name of their area, in the order the areas first appear.
+    fn from_netstations(netstations: Vec<(String, NetstationTopology)>) -> Self {
+        let mut areas: Vec<AreaTopology> = vec![];
+        for (area, netstation) in netstations {
+            match areas.iter_mut().find(|a| a.name == area) {
+                Some(a) => a.netstations.push(netstation),
+                None => areas.push(AreaTopology {
+                    name:        area,
+                    netstations: vec![netstation],
+                }),
+            }
+        }
+        Self { areas }
+    }
+
+    fn validated(self) -> Result<Self, ConfigError> {
+        if self.num_netstations() == 0 {
+            return Err(ConfigError::NumElementsTooLow(
+                "Number of elements is too low. Expected at least 1 netstation in the topology".to_string(),
+            ));
+        }
+        if let Some(n) = self
+            .areas
+            .iter()
+            .flat_map(|a| &a.netstations)
+            .find(|n| n.households.is_some_and(|h| h < 0))
+        {
+            return Err(ConfigError::NotPermittedOption(format!(
+                "netstation {} has a negative number of households",
+                n.name
+            )));
+        }
+        Ok(self)
+    }
+}
+
+/// Parses the value of a column, if given.
+fn parse<T: std::str::FromStr>(value: Option<&str>) -> Result<Option<T>, ConfigError>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .map(|v| {
+            v.parse::<T>()
+                .map_err(|e| ConfigError::ParsingError(format!("{v} in the topology file: {e}")))
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod topology_tests {
+    use super::*;
+
+    #[test]
+    fn topology_from_formats() {
+        let json = r#"{"areas": [
+            {"name": "north", "netstations": [
+                {"name": "ns-1", "households": 3, "rating": 400000, "coordinates": [4.37, 52.01]},
+                {"name": "ns-2"}
+            ]},
+            {"name": "south", "netstations": [{"name": "ns-3", "connections": 2}]}
+        ]}"#;
+        let csv = "area;netstation;households;rating;longitude;latitude
+north;ns-1;3;400000;4,37;52,01
+north;ns-2;;;;
+south;ns-3;2;;;
+";
+        let geojson = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "geometry": {"type": "Point", "coordinates": [4.37, 52.01]},
+             "properties": {"area": "north", "netstation": "ns-1", "households": 3, "rating": 400000}},
+            {"type": "Feature", "geometry": null, "properties": {"area": "north", "netstation": "ns-2"}},
+            {"type": "Feature", "geometry": null, "properties": {"area": "south", "netstation": "ns-3", "households": 2}}
+        ]}"#;
+
+        let topology = Topology::from_json_str(json).unwrap();
+        assert_eq!(topology.areas.len(), 2);
+        assert_eq!(topology.num_netstations(), 3);
+        assert_eq!(topology.areas[0].netstations[0].rating, Some(Watt(400_000)));
+        assert_eq!(topology.areas[0].netstations[1].households, None);
+        assert_eq!(topology.areas[1].netstations[0].households, Some(2));
+        assert_eq!(Topology::from_csv_str(csv).unwrap(), topology);
+        assert_eq!(Topology::from_geojson_str(geojson).unwrap(), topology);
+
+        assert!(Topology::from_csv_str("area,netstation\n").is_err());
+        assert!(Topology::from_csv_str("area,households\nnorth,3\n").is_err());
+    }
+}
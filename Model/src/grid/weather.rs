@@ -0,0 +1,63 @@
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use crate::util::{BaseFloat, BaseInt, Steps};
+
+/// The outdoor temperature the grid is exposed to. Step 0 of the model is the start of the year.
+///
+/// The temperature follows a seasonal sine with its minimum on the coldest day of the year, together with a daily
+/// sine that is the coldest in the early morning and the warmest in the afternoon.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OutdoorTemperature {
+    /// Mean temperature over the year in degrees Celsius.
+    pub mean:               BaseFloat,
+    /// Amplitude of the seasonal variation in degrees Celsius.
+    pub seasonal_amplitude: BaseFloat,
+    /// Amplitude of the daily variation in degrees Celsius.
+    pub daily_amplitude:    BaseFloat,
+    /// The day of the year with the lowest temperature.
+    pub coldest_day:        BaseInt,
+}
+
+impl Default for OutdoorTemperature {
+    fn default() -> Self {
+        Self {
+            mean:               10.5,
+            seasonal_amplitude: 7.0,
+            daily_amplitude:    4.0,
+            coldest_day:        15,
+        }
+    }
+}
+
+impl OutdoorTemperature {
+    /// Fraction of the afternoon the temperature of the day peaks, 15:00.
+    const WARMEST_TIME_OF_DAY: BaseFloat = 0.625;
+
+    /// Outdoor temperature in degrees Celsius on the given step.
+    pub fn temperature(&self, step: &Steps) -> BaseFloat {
+        let day = (step.0 / Steps::steps_per_day().0 - self.coldest_day).rem_euclid(365);
+        let seasonal = self.seasonal_amplitude * (2.0 * PI * day as BaseFloat / 365.0).cos();
+        let daily =
+            self.daily_amplitude * (2.0 * PI * (Steps::percentage_of_day(step) - Self::WARMEST_TIME_OF_DAY)).cos();
+        self.mean - seasonal + daily
+    }
+}
+
+#[cfg(test)]
+mod weather_tests {
+    use super::*;
+
+    #[test]
+    fn coldest_and_warmest() {
+        let weather = OutdoorTemperature::default();
+        let steps_per_day = Steps::steps_per_day().0;
+        let winter_afternoon = weather.temperature(&Steps(15 * steps_per_day + 60));
+        let winter_night = weather.temperature(&Steps(15 * steps_per_day + 12));
+        let summer_afternoon = weather.temperature(&Steps(197 * steps_per_day + 60));
+        assert!((winter_afternoon - 7.5).abs() < 0.01);
+        assert!(winter_night < winter_afternoon);
+        assert!(summer_afternoon > 21.0);
+    }
+}
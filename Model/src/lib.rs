@@ -54,7 +54,13 @@ pub fn run_from_config(configfile: impl AsRef<Path>) -> Result<(), ModelError> {
 pub fn run_model(param: ModelParameters) -> Result<(), ModelError> {
     let start_time = Instant::now();
     init(&param).expect("Failed to init the logger");
-    let mut model = Model::new(param)?;
+    let mut model = match param.resume_file.clone() {
+        Some(resume_file) => Model::load_checkpoint(param, resume_file)?,
+        None => Model::new(param)?,
+    };
+    if let Some(graph_export_file) = &model.param.graph_export_file {
+        model.export_graph(graph_export_file)?;
+    }
     let res = model.run();
     log::info!("Running took {:?}", start_time.elapsed());
     println!("Running took {:?}", start_time.elapsed());
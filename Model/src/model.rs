@@ -1,41 +1,83 @@
 //! Modelling the grid by bringing other parts together happens in this module.
 
 use std::sync::Arc;
+use std::thread::JoinHandle;
 
 use log::{debug, info};
 use parking_lot::RwLock;
 
-use crate::agent::{AgentKind, AgentList, Area, AreaList, Household, HouseholdList, Netstation, NetstationList, Root};
-use crate::grid::{PowerGeneration, ReservePower};
-use crate::util::{random_percentage, uni_dist, BaseUint, ModelError, Watt};
+use crate::agent::{AgentKind, AgentList, Area, AreaList, Battery, BatteryList, Connection, ConnectionList, EvCharger, EvChargerList, Generator, GeneratorList, HeatPump, HeatPumpList, Household, HouseholdList, Netstation, NetstationList, Root};
+use crate::grid::{Blackout, NetstationTopology, PowerGeneration, ReplayProfile, ReservePower, SynchronousArea};
+use crate::util::{random_percentage, uni_dist, BaseFloat, BaseUint, ModelError, RngPurpose, Steps, Watt};
 
+mod checkpoint;
+mod ensemble;
+mod layout;
 mod modelparameters;
 mod mygraph;
+mod observer;
+mod scenario;
 mod step;
+mod stop;
 
+pub use checkpoint::*;
+pub use ensemble::*;
+pub use layout::*;
 pub use modelparameters::*;
 pub use mygraph::*;
+pub use observer::*;
+pub use scenario::*;
 pub use step::*;
+pub use stop::*;
 
 /// Struct for creating and running a model based on the respective
 /// [`ModelParameters`].
 pub struct Model {
     /// The parameters of the model. See [ModelParameters]
-    pub param:         ModelParameters,
+    pub param:                 ModelParameters,
     /// The graph of the model
-    pub graph:         MyGraph,
+    pub graph:                 MyGraph,
     /// The list of agents in the model.
-    pub agents:        AgentList,
+    pub agents:                AgentList,
+    /// The next step the model takes.
+    pub current_step:          Steps,
+    /// The output of the model that is being written in the background.
+    pub(crate) output_handles: Vec<JoinHandle<Result<(), ModelError>>>,
+    /// The observers that are called during each step. See [Observer].
+    pub(crate) observers:      Vec<Box<dyn Observer + Send + Sync>>,
+    /// The rule that ended the last run early, if any. See [StopRule].
+    pub stopped_by:            Option<StopRule>,
+    /// What the stop rules remember between steps.
+    pub(crate) stop_state:     StopState,
     /// ReservePower
-    pub reserve_power: ReservePower,
+    pub reserve_power:         ReservePower,
+    /// The synchronous area the grid is connected to, if any. See [SynchronousArea].
+    pub synchronous_area:      Option<SynchronousArea>,
+    /// The current blackout of the grid and its restoration, if any. See [Blackout].
+    pub blackout:              Option<Blackout>,
+    /// The power mismatch at the [Root] measured in the last step, before the tie-lines and the reserve. The
+    /// generation fleet is dispatched to correct it.
+    pub imbalance:             Watt,
     /// The [Root] agent of the model.
-    pub root:          Arc<RwLock<Root>>,
+    pub root:                  Arc<RwLock<Root>>,
     /// A list of [Area] agents that are part of the model
-    pub areas:         Vec<Arc<RwLock<Area>>>,
+    pub areas:                 Vec<Arc<RwLock<Area>>>,
     /// A list of [Netstation] agents that are part of the model.
-    pub netstations:   Vec<Arc<RwLock<Netstation>>>,
+    pub netstations:           Vec<Arc<RwLock<Netstation>>>,
     /// A list of [Household] agents that are part of the model.
-    pub households:    Vec<Arc<RwLock<Household>>>,
+    pub households:            Vec<Arc<RwLock<Household>>>,
+    /// A list of [Generator] agents that are part of the model, sorted in merit order.
+    pub generators:            GeneratorList,
+    /// A list of [EvCharger] agents that are part of the model.
+    pub ev_chargers:           EvChargerList,
+    /// A list of [HeatPump] agents that are part of the model.
+    pub heat_pumps:            HeatPumpList,
+    /// A list of [Battery] agents that are part of the model.
+    pub batteries:             BatteryList,
+    /// The tree of the agents from the bottom up, used to aggregate their power. See [GridLayout].
+    pub layout:                GridLayout,
+    /// A list of [Connection] agents that link netstations or areas horizontally.
+    pub connections:           ConnectionList,
 }
 impl Model {
     #[allow(clippy::type_complexity)]
@@ -49,6 +91,11 @@ impl Model {
             AreaList,
             NetstationList,
             HouseholdList,
+            GeneratorList,
+            EvChargerList,
+            HeatPumpList,
+            BatteryList,
+            ConnectionList,
         ),
         ModelError,
     > {
@@ -58,6 +105,13 @@ impl Model {
         let mut areas: Vec<Arc<RwLock<Area>>> = vec![];
         let mut netstations: Vec<Arc<RwLock<Netstation>>> = vec![];
         let mut households: Vec<Arc<RwLock<Household>>> = vec![];
+        let mut generators: GeneratorList = vec![];
+        let mut ev_chargers: EvChargerList = vec![];
+        let mut heat_pumps: HeatPumpList = vec![];
+        let mut batteries: BatteryList = vec![];
+        let mut connections: ConnectionList = vec![];
+        let mut area_indices: Vec<BaseUint> = vec![];
+        let mut netstation_indices: Vec<Vec<BaseUint>> = vec![];
 
         // Root
         let root_index = agents.len() as BaseUint;
@@ -66,69 +120,319 @@ impl Model {
         let root = Arc::new(RwLock::new(root));
         agents.push(root.clone());
 
-        // Areas
-        for _ in 0..param.grid.n_areas {
+        // Areas, from the imported topology if any
+        let topology = param.grid.topology.clone();
+        let n_areas = topology.as_ref().map_or(param.grid.n_areas as usize, |t| t.areas.len());
+        for area_position in 0..n_areas {
+            let area_parent = Self::generate_line(
+                root_index,
+                param.grid.area_line_capacity,
+                param,
+                &mut agents,
+                &mut mygraph,
+                &mut connections,
+            )?;
             let area_index = agents.len() as BaseUint;
-            let area = Area::new(area_index);
+            let area = Area::new(area_index, &param.grid);
             let a = Arc::new(RwLock::new(area));
             agents.push(a.clone());
-            areas.push(a);
+            areas.push(a.clone());
 
             mygraph.add_node(AgentKind::Area, area_index)?;
-            mygraph.add_edge(root_index, area_index)?;
+            mygraph.add_edge(area_parent, area_index)?;
+            area_indices.push(area_index);
+            netstation_indices.push(vec![]);
 
             // Netstations
-            for _ in 0..uni_dist(param.grid.ns_per_a, &mut param.seed) {
+            param.seed = param.streams.stream(RngPurpose::Topology, area_index);
+            let area_netstations: Vec<Option<&NetstationTopology>> = match &topology {
+                Some(topology) => topology.areas[area_position].netstations.iter().map(Some).collect(),
+                None => vec![None; uni_dist(param.grid.ns_per_a, &mut param.seed) as usize],
+            };
+            for netstation_topology in area_netstations {
+                let netstation_parent = Self::generate_line(
+                    area_index,
+                    netstation_topology
+                        .and_then(|n| n.rating)
+                        .unwrap_or(param.grid.ns_line_capacity),
+                    param,
+                    &mut agents,
+                    &mut mygraph,
+                    &mut connections,
+                )?;
                 let netstation_index = agents.len() as BaseUint;
                 let netstation = Netstation::new(netstation_index, &param.grid);
                 let ns = Arc::new(RwLock::new(netstation));
                 agents.push(ns.clone());
-                netstations.push(ns);
+                netstations.push(ns.clone());
 
                 mygraph.add_node(AgentKind::Netstation, netstation_index)?;
-                mygraph.add_edge(area_index, netstation_index)?;
+                a.write().netstations.push(ns.clone());
+                mygraph.add_edge(netstation_parent, netstation_index)?;
+                if let Some(area_netstations) = netstation_indices.last_mut() {
+                    area_netstations.push(netstation_index);
+                }
 
                 // Households
-                for _ in 0..uni_dist(param.grid.hs_per_ns, &mut param.seed) {
+                let noise_seed: Option<i64> = param
+                    .grid
+                    .noise
+                    .map(|_| param.streams.seed(RngPurpose::Noise, netstation_index));
+                param.seed = param.streams.stream(RngPurpose::Topology, netstation_index);
+                let num_households = match netstation_topology.and_then(|n| n.households) {
+                    Some(num_households) => i64::from(num_households),
+                    None => uni_dist(param.grid.hs_per_ns, &mut param.seed),
+                };
+                for _ in 0..num_households {
+                    let household_index = agents.len() as BaseUint;
+                    param.seed = param.streams.stream(RngPurpose::Generation, household_index);
+
                     // PowerGeneration
                     let random_perc = random_percentage(&mut param.seed);
-                    let power_generation = if param.grid.pv_adoption > random_perc {
-                        PowerGeneration::new_pv(None, param)?
+                    let replay = match param.grid.replay.as_ref() {
+                        Some(meter_data) => Some(meter_data.pick(&mut param.seed)?),
+                        None => None,
+                    };
+                    let pv = replay
+                        .as_ref()
+                        .map_or(param.grid.pv_adoption > random_perc, ReplayProfile::has_generation);
+                    let mut power_generation = if pv {
+                        PowerGeneration::new_pv(None, noise_seed, param)?
                     } else {
-                        PowerGeneration::new_no_pv(None, param)?
+                        PowerGeneration::new_no_pv(None, noise_seed, param)?
                     };
+                    power_generation.replay = replay;
+
+                    let has_battery = param.grid.battery_adoption > 0.0
+                        && param.grid.battery_adoption > random_percentage(&mut param.seed);
 
                     // Household
-                    let household_index = agents.len() as BaseUint;
                     let household = Household::new(household_index, power_generation);
                     let h = Arc::new(RwLock::new(household));
                     agents.push(h.clone());
-                    households.push(h);
+                    households.push(h.clone());
+                    ns.write().households.push(h.clone());
 
                     mygraph.add_node(AgentKind::Household, household_index)?;
                     mygraph.add_edge(netstation_index, household_index)?;
+
+                    // Devices, adopted by the draws of the household
+                    let ev_param = param
+                        .grid
+                        .ev_charger
+                        .clone()
+                        .filter(|ev_param| ev_param.adoption > random_percentage(&mut param.seed));
+                    let hp_param = param
+                        .grid
+                        .heat_pump
+                        .clone()
+                        .filter(|hp_param| hp_param.adoption > random_percentage(&mut param.seed));
+
+                    // Battery
+                    if has_battery {
+                        let battery_index = agents.len() as BaseUint;
+                        param.seed = param.streams.stream(RngPurpose::Device, battery_index);
+                        let battery = Arc::new(RwLock::new(Battery::new(battery_index, param)));
+                        agents.push(battery.clone());
+                        batteries.push(battery.clone());
+                        h.write().battery = Some(battery);
+
+                        mygraph.add_node(AgentKind::Battery, battery_index)?;
+                        mygraph.add_edge(household_index, battery_index)?;
+                    }
+
+                    // EV charger
+                    if let Some(ev_param) = ev_param {
+                        let ev_charger_index = agents.len() as BaseUint;
+                        param.seed = param.streams.stream(RngPurpose::Device, ev_charger_index);
+                        let ev_charger = EvCharger::new(ev_charger_index, &ev_param, param)?;
+                        let ev = Arc::new(RwLock::new(ev_charger));
+                        agents.push(ev.clone());
+                        ev_chargers.push(ev);
+
+                        mygraph.add_node(AgentKind::EvCharger, ev_charger_index)?;
+                        mygraph.add_edge(household_index, ev_charger_index)?;
+                    }
+
+                    // Heat pump
+                    if let Some(hp_param) = hp_param {
+                        let heat_pump_index = agents.len() as BaseUint;
+                        param.seed = param.streams.stream(RngPurpose::Device, heat_pump_index);
+                        let heat_pump = HeatPump::new(heat_pump_index, &hp_param, param)?;
+                        let hp = Arc::new(RwLock::new(heat_pump));
+                        agents.push(hp.clone());
+                        heat_pumps.push(hp);
+
+                        mygraph.add_node(AgentKind::HeatPump, heat_pump_index)?;
+                        mygraph.add_edge(household_index, heat_pump_index)?;
+                    }
                 }
             }
         }
-        Ok((mygraph, agents, root, areas, netstations, households))
+
+        // Generators
+        for generator_param in &param.grid.generators {
+            let parent_index = match generator_param.location {
+                0 => root_index,
+                location => match areas.get((location - 1) as usize) {
+                    Some(area) => area.read_arc_recursive().index,
+                    None => {
+                        return Err(ModelError::ParamError {
+                            msg:     format!("Generator attached to area {location} that does not exist"),
+                            context: "generators".to_string(),
+                        })
+                    }
+                },
+            };
+            let generator_index = agents.len() as BaseUint;
+            let generator = Generator::new(generator_index, generator_param);
+            let g = Arc::new(RwLock::new(generator));
+            agents.push(g.clone());
+            generators.push(g);
+
+            mygraph.add_node(AgentKind::Generator, generator_index)?;
+            mygraph.add_edge(parent_index, generator_index)?;
+        }
+
+        // Connections
+        if param.grid.ns_link_density > 0.0 {
+            for area_netstations in &netstation_indices {
+                Self::generate_ring_links(
+                    area_netstations,
+                    (param.grid.ns_link_density, param.grid.ns_link_capacity),
+                    param,
+                    &mut agents,
+                    &mut mygraph,
+                    &mut connections,
+                )?;
+            }
+        }
+        if param.grid.area_link_density > 0.0 {
+            Self::generate_ring_links(
+                &area_indices,
+                (param.grid.area_link_density, param.grid.area_link_capacity),
+                param,
+                &mut agents,
+                &mut mygraph,
+                &mut connections,
+            )?;
+        }
+        Ok((
+            mygraph,
+            agents,
+            root,
+            areas,
+            netstations,
+            households,
+            generators,
+            ev_chargers,
+            heat_pumps,
+            batteries,
+            connections,
+        ))
+    }
+
+    /// Add a line below the parent if the capacity is given. The line is placed in the tree between the parent and the
+    /// next agent that is created. Returns the index that the next agent should use as parent.
+    fn generate_line(
+        parent_index: BaseUint,
+        capacity: Watt,
+        param: &ModelParameters,
+        agents: &mut AgentList,
+        mygraph: &mut MyGraph,
+        connections: &mut ConnectionList,
+    ) -> Result<BaseUint, ModelError> {
+        if capacity <= Watt(0) {
+            return Ok(parent_index);
+        }
+        let line_index = agents.len() as BaseUint;
+        let line = Connection::new_line(
+            line_index,
+            parent_index,
+            line_index + 1,
+            capacity,
+            param.grid.line_loss_factor,
+        );
+        let l = Arc::new(RwLock::new(line));
+        agents.push(l.clone());
+        connections.push(l);
+
+        mygraph.add_node(AgentKind::Connection, line_index)?;
+        mygraph.add_edge(parent_index, line_index)?;
+        Ok(line_index)
+    }
+
+    /// Link the agents given in a ring using [Connection] agents. Each pair of neighbours in the ring is linked with
+    /// the density as chance.
+    fn generate_ring_links(
+        indices: &[BaseUint],
+        (density, capacity): (BaseFloat, Watt),
+        param: &mut ModelParameters,
+        agents: &mut AgentList,
+        mygraph: &mut MyGraph,
+        connections: &mut ConnectionList,
+    ) -> Result<(), ModelError> {
+        let num_links = match indices.len() {
+            0 | 1 => 0,
+            2 => 1,
+            n => n,
+        };
+        for k in 0..num_links {
+            param.seed = param.streams.stream(RngPurpose::Link, indices[k]);
+            if density <= random_percentage(&mut param.seed) {
+                continue;
+            }
+            let (from, to) = (indices[k], indices[(k + 1) % indices.len()]);
+            let connection_index = agents.len() as BaseUint;
+            let connection = Connection::new(connection_index, from, to, capacity, param.grid.line_loss_factor);
+            let c = Arc::new(RwLock::new(connection));
+            agents.push(c.clone());
+            connections.push(c);
+
+            mygraph.add_node(AgentKind::Connection, connection_index)?;
+            mygraph.add_link(from, connection_index, to)?;
+        }
+        Ok(())
     }
 
     /// Creating the graph and agents and adding agents to the graph. Graph is
-    /// tree. Ordinality: Area -> Netstation -> Households -> PV.
+    /// tree. Ordinality: Area -> Netstation -> Households -> PV / Battery / EvCharger / HeatPump. Generators are
+    /// attached to the Root or an Area. The areas and netstations follow the imported topology if one is given, see
+    /// [Topology](crate::grid::Topology). Optionally, netstations and areas are fed by lines, and netstations within an
+    /// area and areas are linked horizontally by connections.
     pub fn new(mut model_param: ModelParameters) -> Result<Self, ModelError> {
         let model_name: &str = &model_param.name.clone();
         info!("{model_name} - Agent and Graph generation");
-        let (mygraph, agents, root, areas, netstations, households) =
-            Self::generate_graph_and_agents(&mut model_param)?;
+        let (
+            mygraph,
+            agents,
+            root,
+            areas,
+            netstations,
+            households,
+            mut generators,
+            ev_chargers,
+            heat_pumps,
+            batteries,
+            connections,
+        ) = Self::generate_graph_and_agents(&mut model_param)?;
+        generators.sort_by(|a, b| {
+            a.read_arc_recursive()
+                .marginal_cost
+                .total_cmp(&b.read_arc_recursive().marginal_cost)
+        });
 
         info!("{model_name} - Populating agents with their children");
-        // Populate the agents with references to their children
-        for rw_agent in &agents {
-            let mut agent = rw_agent.write();
-            for child in mygraph.get_children(&(*agent.index() as i32))? {
-                agent.children_mut().push(agents[child as usize].clone());
-            }
-        }
+        Self::populate_children(&agents, &mygraph)?;
+        let layout = GridLayout::new(
+            &agents,
+            &households,
+            &generators,
+            &ev_chargers,
+            &heat_pumps,
+            &batteries,
+            &connections,
+        );
 
         let reserve_power = ReservePower {
             lower_limit:   model_param.grid.energy_storage * -1,
@@ -137,24 +441,55 @@ impl Model {
             watt_per_step: model_param.grid.max_gen_inc_tick,
         };
 
+        let synchronous_area = model_param.grid.synchronous_area;
+
         debug!("{model_name} - finished building the model");
         Ok(Self {
             param: model_param,
             graph: mygraph,
             agents,
+            current_step: Steps(0),
+            output_handles: vec![],
+            observers: vec![],
+            stopped_by: None,
+            stop_state: StopState::default(),
             reserve_power,
+            synchronous_area,
+            blackout: None,
+            imbalance: Watt(0),
             root,
             areas,
             netstations,
             households,
+            generators,
+            ev_chargers,
+            heat_pumps,
+            batteries,
+            layout,
+            connections,
         })
     }
 
-    /// Run the model. Generates the outcome by calling the step function.
+    /// Populate the agents with references to their children according to the graph.
+    fn populate_children(agents: &AgentList, graph: &MyGraph) -> Result<(), ModelError> {
+        for rw_agent in agents {
+            let mut agent = rw_agent.write();
+            for child in graph.get_children(&(*agent.index() as i32))? {
+                agent.children_mut().push(agents[child as usize].clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the model. Generates the outcome by calling the step function. A model restored from a checkpoint only
+    /// takes the remaining steps. Saves a checkpoint at the end if a checkpoint file is given.
     pub fn run(&mut self) -> Result<(), ModelError> {
         let name = self.param.name.clone();
         info!("{} - Running", name);
-        self.step(self.param.steps)?;
+        self.resume(self.param.steps - self.current_step)?;
+        if let Some(checkpoint_file) = &self.param.checkpoint_file {
+            self.save_checkpoint(checkpoint_file)?;
+        }
         info!("{} - Finished running", name);
         Ok(())
     }
@@ -164,8 +499,10 @@ impl Model {
 mod model_tests {
 
     use super::*;
-    use crate::grid::PowerState;
-    use crate::util::{output_graph_to_png, Steps};
+    use crate::agent::{BatteryCommand, GeneratorParam};
+    use crate::attack::BatteryAttack;
+    use crate::grid::{AreaTopology, InfectionState, NoiseModel, NoiseParam, PowerState, RestorationParam, Topology};
+    use crate::util::{mHz, mPercent, output_graph_to_png, BaseInt, WattHour};
 
     #[test]
     #[ignore]
@@ -183,6 +520,261 @@ mod model_tests {
         assert_eq!(model.graph.get_children(&5).expect("Couldnt get children"), vec![6, 7]);
     }
 
+    #[test]
+    fn lines_between_levels() {
+        let mut param = ModelParameters::test();
+        param.grid.ns_line_capacity = Watt(1_000);
+        param.grid.line_loss_factor = 0.02;
+        let mut model = Model::new(param).unwrap();
+        assert_eq!(model.connections.len(), 2);
+        assert_eq!(model.graph.get_children(&1).expect("Couldnt get children"), vec![2, 6]);
+        assert_eq!(model.graph.get_children(&2).expect("Couldnt get children"), vec![3]);
+
+        model.step(Steps(10)).expect("Error in taking steps");
+        let losses: Watt = model.connections.iter().map(|c| c.read_arc_recursive().losses).sum();
+        assert!(losses > Watt(0));
+        assert_eq!(model.root.read_arc_recursive().powerstate.power_lost, losses);
+        // The losses are consumed by the lines
+        let households: Watt = model
+            .households
+            .iter()
+            .map(|h| h.read_arc_recursive().powerstate.power_used)
+            .sum();
+        let generators: Watt = model
+            .generators
+            .iter()
+            .map(|g| g.read_arc_recursive().powerstate.power_used)
+            .sum();
+        assert_eq!(
+            model.root.read_arc_recursive().powerstate.power_used,
+            households + generators + losses
+        );
+    }
+
+    #[test]
+    fn imported_topology() {
+        let netstation = |households: Option<BaseInt>, rating: Option<Watt>| NetstationTopology {
+            households,
+            rating,
+            ..NetstationTopology::default()
+        };
+        let mut param = ModelParameters::test();
+        param.grid.topology = Some(Topology {
+            areas: vec![
+                AreaTopology {
+                    name:        "north".to_string(),
+                    netstations: vec![netstation(Some(4), Some(Watt(400_000))), netstation(None, None)],
+                },
+                AreaTopology {
+                    name:        "south".to_string(),
+                    netstations: vec![netstation(Some(1), None)],
+                },
+            ],
+        });
+        let mut model = Model::new(param).unwrap();
+        assert_eq!(model.areas.len(), 2);
+        assert_eq!(model.netstations.len(), 3);
+        assert_eq!(model.netstations[0].read_arc_recursive().households.len(), 4);
+        assert_eq!(model.netstations[2].read_arc_recursive().households.len(), 1);
+        assert_eq!(model.connections.len(), 1);
+        assert_eq!(model.connections[0].read_arc_recursive().capacity, Watt(400_000));
+
+        model.step(Steps(2)).expect("Error in taking steps");
+    }
+
+    #[test]
+    fn batteries_infected_by_own_attack() {
+        let mut param = ModelParameters::test();
+        param.grid.battery_adoption = 1.0;
+        param.grid.battery_capacity = WattHour(10_000);
+        param.attack.percentage_vuln_devices = 1.0;
+        param.attack.infection_rate_per_step = 1.0;
+        param.attack.battery_behaviour = vec![BatteryAttack {
+            begin:   Steps(0),
+            end:     Steps(5),
+            command: BatteryCommand::FullCharge,
+        }];
+        let mut model = Model::new(param).unwrap();
+        assert_eq!(model.batteries.len(), model.households.len());
+        for household in &model.households {
+            let (index, battery) = {
+                let household = household.read_arc_recursive();
+                (
+                    household.index,
+                    household.battery.clone().expect("Household without battery"),
+                )
+            };
+            let battery = battery.read_arc_recursive().index;
+            assert!(model
+                .graph
+                .get_children(&(index as BaseInt))
+                .unwrap()
+                .contains(&(battery as BaseInt)));
+        }
+
+        model.step(Steps(3)).expect("Error in taking steps");
+        assert!(model
+            .batteries
+            .iter()
+            .all(|b| b.read_arc_recursive().infection_state == InfectionState::Infected));
+        // The PV installations are only infected during an attack on them
+        assert!(model
+            .households
+            .iter()
+            .all(|h| h.read_arc_recursive().power_generation.infection_state != InfectionState::Infected));
+    }
+
+    #[test]
+    fn fleet_balances_attack_mismatch() {
+        let mut param = ModelParameters::test();
+        param.grid.generators = vec![GeneratorParam {
+            location:          0,
+            capacity:          Watt(1_000_000_000),
+            min_stable_output: Watt(0),
+            ramp_rate:         Watt(1_000_000_000),
+            marginal_cost:     1.0,
+            trips:             vec![],
+        }];
+        param.attack.percentage_vuln_devices = 1.0;
+        param.attack.infection_rate_per_step = 1.0;
+        param.attack.infection_start = Steps(0);
+        param.attack.attack_behaviour[0].begin = Steps(0);
+        let mut model = Model::new(param).unwrap();
+
+        let mut last_mismatch = Watt(0);
+        for _ in 0..10 {
+            let result = model.step_once().expect("Error in taking a step");
+            let mismatch = model
+                .households
+                .iter()
+                .map(|h| h.read().powerstate.power_error)
+                .sum::<Watt>();
+            let generator = model.generators[0].read().clone();
+            // The fleet delivers the mismatch of the households in the previous step on top of its schedule
+            assert_eq!(
+                result.grid_information.fleet_dispatch.unwrap().balancing,
+                -last_mismatch
+            );
+            assert_eq!(generator.output - generator.schedule, -last_mismatch);
+            assert_eq!(model.imbalance, mismatch - last_mismatch);
+            last_mismatch = mismatch;
+        }
+        assert_ne!(last_mismatch, Watt(0));
+    }
+
+    #[test]
+    fn stochastic_noise_shared_by_neighbours() {
+        let mut param = ModelParameters::test();
+        param.grid.noise = Some(NoiseParam {
+            model:                 NoiseModel::Ar1,
+            correlation:           0.9,
+            neighbour_correlation: 0.5,
+        });
+        let mut model = Model::new(param).unwrap();
+        let neighbour_seed = |h: usize| {
+            model.households[h]
+                .read_arc_recursive()
+                .power_generation
+                .consumption_noise_process
+                .as_ref()
+                .map(|p| p.neighbour_seed)
+        };
+        assert!(neighbour_seed(0).is_some());
+        assert_eq!(neighbour_seed(0), neighbour_seed(1));
+        assert_ne!(neighbour_seed(0), neighbour_seed(2));
+        assert!(model.households[0]
+            .read_arc_recursive()
+            .power_generation
+            .consumption_noise_param
+            .is_empty());
+
+        model.step(Steps(10)).expect("Error in taking steps");
+    }
+
+    #[test]
+    fn independent_of_thread_count() {
+        let run = |num_threads: usize| {
+            let mut param = ModelParameters::test();
+            param.attack.infection_start = Steps(0);
+            param.attack.infection_rate_per_step = 0.2;
+            param.attack.patch_start = Steps(0);
+            param.attack.patch_rate_per_step = 0.05;
+            param.attack.attack_behaviour[0].generation_modifier = 1.0;
+            param.attack.attack_behaviour[0].report_modifier = 1.0;
+            let mut model = Model::new(param).unwrap();
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .expect("Couldnt build thread pool")
+                .install(|| model.step(Steps(40)))
+                .expect("Error in taking steps");
+            model
+                .agents
+                .iter()
+                .map(|a| {
+                    let agent = a.read_arc_recursive();
+                    let infection_state = agent.power_gen().map(|pg| pg.infection_state);
+                    serde_json::to_string(&(agent.powerstate(), infection_state)).unwrap()
+                })
+                .collect::<Vec<String>>()
+        };
+        let single = run(1);
+        assert!(single.iter().any(|s| s.contains("Infected")));
+        assert_eq!(single, run(4));
+    }
+
+    #[test]
+    fn step_once_gives_result() {
+        let mut model = Model::new(ModelParameters::test()).unwrap();
+        let first = model.step_once().expect("Error in taking a step");
+        let second = model.step_once().expect("Error in taking a step");
+        assert_eq!((first.step, second.step), (Steps(0), Steps(1)));
+        assert_eq!(model.current_step, Steps(2));
+        assert!(second.timings.substeps.iter().any(|(s, _)| s == "boundary check"));
+        assert_eq!(
+            second.grid_information.power_state.power_used,
+            model.root.read().powerstate.power_used
+        );
+    }
+
+    #[tokio::test]
+    async fn step_inside_runtime() {
+        let mut model = Model::new(ModelParameters::test()).unwrap();
+        model.step(Steps(2)).expect("Error in taking steps");
+        assert_eq!(model.step_once().expect("Error in taking a step").step, Steps(2));
+    }
+
+    #[test]
+    fn restoration_after_blackout() {
+        let mut param = ModelParameters::test();
+        param.grid.restoration = Some(RestorationParam {
+            black_start_time:   Steps(1),
+            sequence:           vec![],
+            load_pickup_limit:  Watt(1),
+            pv_reconnect_delay: Steps(1),
+            pv_reconnect_band:  mHz(100),
+        });
+        let mut model = Model::new(param).unwrap();
+        model.netstations.iter().for_each(|ns| ns.write().de_energise());
+        model.blackout = Some(Blackout::new(Steps(0)));
+
+        // One netstation per step, as the load pickup limit is low
+        model.step(Steps(2)).expect("Error in taking steps");
+        let blackout = model.blackout.expect("No blackout");
+        assert_eq!(blackout.restored, mPercent(50_000));
+        assert_eq!(
+            model.root.read().powerstate.power_used,
+            model.agents[2].read().powerstate().power_used
+        );
+
+        // The second netstation is energised at the next step
+        let result = model.step_once().expect("Error in taking a step");
+        assert_eq!(result.step, Steps(2));
+        let blackout = result.grid_information.blackout.expect("No blackout");
+        assert_eq!(blackout.restored, mPercent(100_000));
+        assert_eq!(blackout.recovery_time, Some(Steps(2)));
+    }
+
     #[test]
     fn grid_power_sum_clean() {
         let param = ModelParameters::test();
@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::fs::{create_dir_all, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::info;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::{AgentList, AgentRef, AgentTrait, Area, Battery, Connection, EvCharger, Generator, HeatPump, Household, Netstation, Root};
+use crate::grid::{Blackout, Grid, ReservePower, SynchronousArea};
+use crate::model::{GridLayout, Model, ModelParameters, MyGraph, StopState};
+use crate::util::{BaseUint, ModelError, RngStreams, Steps, UtilError, Watt};
+
+/// The complete state of a running [Model], from which it can be restored.
+///
+/// The agents are kept per kind, with the references between them given by their index. The series of the standard
+/// profiles and the replayed meters are not part of the checkpoint, they are attached again from the [Grid] the model
+/// is restored with. The configuration of the model is not part of the checkpoint either, so many attack scenarios can
+/// be branched from one warmed-up grid state.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The next step the model takes.
+    pub step:                  Steps,
+    /// The graph of the model.
+    pub graph:                 MyGraph,
+    /// The [Root] agent.
+    pub root:                  Root,
+    /// The [Area] agents.
+    pub areas:                 Vec<Area>,
+    /// The [Netstation] agents.
+    pub netstations:           Vec<Netstation>,
+    /// The [Household] agents.
+    pub households:            Vec<Household>,
+    /// The [Generator] agents, in merit order.
+    pub generators:            Vec<Generator>,
+    /// The [EvCharger] agents.
+    pub ev_chargers:           Vec<EvCharger>,
+    /// The [HeatPump] agents.
+    pub heat_pumps:            Vec<HeatPump>,
+    /// The [Battery] agents.
+    pub batteries:             Vec<Battery>,
+    /// The [Connection] agents.
+    pub connections:           Vec<Connection>,
+    /// The indices of the netstations of each area.
+    pub area_netstations:      Vec<Vec<BaseUint>>,
+    /// The indices of the households of each netstation.
+    pub netstation_households: Vec<Vec<BaseUint>>,
+    /// The reserve power of the grid.
+    pub reserve_power:         ReservePower,
+    /// The synchronous area the grid is connected to, if any.
+    pub synchronous_area:      Option<SynchronousArea>,
+    /// The current blackout, if any.
+    pub blackout:              Option<Blackout>,
+    /// The power mismatch measured in the last step.
+    pub imbalance:             Watt,
+    /// What the stop rules remember of the steps taken.
+    pub stop_state:            StopState,
+    /// The streams of random numbers of the model.
+    pub streams:               RngStreams,
+    /// The streams of random numbers of the attack.
+    pub attack_streams:        RngStreams,
+}
+
+impl Model {
+    /// The complete state of the model. The state kept in the layout is stored in the agents first, see
+    /// [GridLayout::store].
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.layout.store(&self.households, &self.connections);
+        let index = |agent: &dyn AgentTrait| *agent.index();
+        Checkpoint {
+            step:                  self.current_step,
+            graph:                 self.graph.clone(),
+            root:                  self.root.read_arc_recursive().clone(),
+            areas:                 self.areas.iter().map(|a| a.read_arc_recursive().clone()).collect(),
+            netstations:           self
+                .netstations
+                .iter()
+                .map(|ns| ns.read_arc_recursive().clone())
+                .collect(),
+            households:            self.households.iter().map(|h| h.read_arc_recursive().clone()).collect(),
+            generators:            self.generators.iter().map(|g| g.read_arc_recursive().clone()).collect(),
+            ev_chargers:           self
+                .ev_chargers
+                .iter()
+                .map(|ev| ev.read_arc_recursive().clone())
+                .collect(),
+            heat_pumps:            self
+                .heat_pumps
+                .iter()
+                .map(|hp| hp.read_arc_recursive().clone())
+                .collect(),
+            batteries:             self.batteries.iter().map(|b| b.read_arc_recursive().clone()).collect(),
+            connections:           self
+                .connections
+                .iter()
+                .map(|c| c.read_arc_recursive().clone())
+                .collect(),
+            area_netstations:      self
+                .areas
+                .iter()
+                .map(|a| {
+                    a.read_arc_recursive()
+                        .netstations
+                        .iter()
+                        .map(|ns| index(&*ns.read_arc_recursive()))
+                        .collect()
+                })
+                .collect(),
+            netstation_households: self
+                .netstations
+                .iter()
+                .map(|ns| {
+                    ns.read_arc_recursive()
+                        .households
+                        .iter()
+                        .map(|h| index(&*h.read_arc_recursive()))
+                        .collect()
+                })
+                .collect(),
+            reserve_power:         self.reserve_power,
+            synchronous_area:      self.synchronous_area,
+            blackout:              self.blackout,
+            imbalance:             self.imbalance,
+            stop_state:            self.stop_state.clone(),
+            streams:               self.param.streams,
+            attack_streams:        self.param.attack.streams,
+        }
+    }
+
+    /// Save the complete state of the model to a gzipped JSON file.
+    pub fn save_checkpoint(&self, filepath: impl AsRef<Path>) -> Result<(), ModelError> {
+        let filepath = filepath.as_ref();
+        info!("{} - Saving checkpoint at step {}", self.param.name, self.current_step);
+        if let Some(folder) = filepath.parent().filter(|f| !f.as_os_str().is_empty()) {
+            create_dir_all(folder).map_err(|e| file_error(filepath, &e))?;
+        }
+        let file = File::create(filepath).map_err(|e| file_error(filepath, &e))?;
+        let mut compresser = GzEncoder::new(BufWriter::new(file), Compression::default());
+        serde_json::to_writer(&mut compresser, &self.checkpoint())
+            .map_err(|e| ModelError::CheckpointError(e.to_string()))?;
+        compresser
+            .finish()
+            .and_then(|mut bf| bf.flush())
+            .map_err(|e| file_error(filepath, &e))?;
+        Ok(())
+    }
+
+    /// Restore a model from the checkpoint saved in the given file. See [Model::from_checkpoint].
+    pub fn load_checkpoint(model_param: ModelParameters, filepath: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let filepath = filepath.as_ref();
+        let file = File::open(filepath).map_err(|e| file_error(filepath, &e))?;
+        let checkpoint: Checkpoint = serde_json::from_reader(GzDecoder::new(BufReader::new(file)))
+            .map_err(|e| ModelError::CheckpointError(e.to_string()))?;
+        Self::from_checkpoint(model_param, checkpoint)
+    }
+
+    /// Restore a model from a checkpoint. The parameters give the configuration of the model, e.g. the attack that is
+    /// run from the restored state, while the state of the grid and the streams of random numbers come from the
+    /// checkpoint.
+    pub fn from_checkpoint(mut model_param: ModelParameters, checkpoint: Checkpoint) -> Result<Self, ModelError> {
+        model_param.streams = checkpoint.streams;
+        model_param.attack.streams = checkpoint.attack_streams;
+        // The other effects of the events of the scenario are part of the state of the agents
+        model_param.grid.bulk_consumption += model_param.scenario.bulk_load_change_before(checkpoint.step);
+
+        let mut households = checkpoint.households;
+        for household in &mut households {
+            Self::attach_series(household, &model_param.grid)?;
+        }
+
+        let num_agents = 1
+            + checkpoint.areas.len()
+            + checkpoint.netstations.len()
+            + households.len()
+            + checkpoint.generators.len()
+            + checkpoint.ev_chargers.len()
+            + checkpoint.heat_pumps.len()
+            + checkpoint.batteries.len()
+            + checkpoint.connections.len();
+        let mut agents: Vec<Option<AgentRef>> = vec![None; num_agents];
+        let root = place(vec![checkpoint.root], &mut agents)?.remove(0);
+        let areas = place(checkpoint.areas, &mut agents)?;
+        let netstations = place(checkpoint.netstations, &mut agents)?;
+        let households = place(households, &mut agents)?;
+        let generators = place(checkpoint.generators, &mut agents)?;
+        let ev_chargers = place(checkpoint.ev_chargers, &mut agents)?;
+        let heat_pumps = place(checkpoint.heat_pumps, &mut agents)?;
+        let batteries = place(checkpoint.batteries, &mut agents)?;
+        let connections = place(checkpoint.connections, &mut agents)?;
+        let agents: AgentList = agents
+            .into_iter()
+            .collect::<Option<_>>()
+            .ok_or_else(|| ModelError::CheckpointError("The indices of the agents are not consecutive".to_string()))?;
+        Self::populate_children(&agents, &checkpoint.graph)?;
+        let layout = GridLayout::new(
+            &agents,
+            &households,
+            &generators,
+            &ev_chargers,
+            &heat_pumps,
+            &batteries,
+            &connections,
+        );
+
+        let netstation_by_index: HashMap<BaseUint, _> =
+            netstations.iter().map(|ns| (ns.read().index, ns.clone())).collect();
+        for (area, indices) in areas.iter().zip(&checkpoint.area_netstations) {
+            area.write().netstations = pick(&netstation_by_index, indices)?;
+        }
+        let household_by_index: HashMap<BaseUint, _> = households.iter().map(|h| (h.read().index, h.clone())).collect();
+        for (netstation, indices) in netstations.iter().zip(&checkpoint.netstation_households) {
+            netstation.write().households = pick(&household_by_index, indices)?;
+        }
+        let battery_by_index: HashMap<BaseUint, _> = batteries.iter().map(|b| (b.read().index, b.clone())).collect();
+        for household in &households {
+            let mut household = household.write();
+            household.battery = household
+                .children
+                .iter()
+                .find_map(|c| battery_by_index.get(c.read().index()).cloned());
+        }
+
+        info!("{} - Restored checkpoint at step {}", model_param.name, checkpoint.step);
+        Ok(Self {
+            param: model_param,
+            graph: checkpoint.graph,
+            agents,
+            current_step: checkpoint.step,
+            output_handles: vec![],
+            observers: vec![],
+            stopped_by: None,
+            stop_state: checkpoint.stop_state,
+            reserve_power: checkpoint.reserve_power,
+            synchronous_area: checkpoint.synchronous_area,
+            blackout: checkpoint.blackout,
+            imbalance: checkpoint.imbalance,
+            root,
+            areas,
+            netstations,
+            households,
+            generators,
+            ev_chargers,
+            heat_pumps,
+            batteries,
+            layout,
+            connections,
+        })
+    }
+
+    /// Attach the series of the standard profile and the replayed meter of a household again, as these are not part
+    /// of a checkpoint.
+    fn attach_series(household: &mut Household, grid: &Grid) -> Result<(), ModelError> {
+        let power_generation = &mut household.power_generation;
+        if let Some(profile) = power_generation.consumption_profile.as_mut() {
+            *profile = grid
+                .profiles
+                .as_ref()
+                .and_then(|p| p.get(&profile.category))
+                .ok_or_else(|| {
+                    ModelError::CheckpointError(format!("{} is not a category of the profiles", profile.category))
+                })?;
+        }
+        if let Some(replay) = power_generation.replay.as_mut() {
+            *replay = grid
+                .replay
+                .as_ref()
+                .and_then(|m| m.get(&replay.meter, replay.shift, replay.scale))
+                .ok_or_else(|| ModelError::CheckpointError(format!("{} is not a replayed meter", replay.meter)))?;
+        }
+        Ok(())
+    }
+}
+
+/// Wrap the agents and place them in the list of agents at their index.
+fn place<T: AgentTrait + Send + Sync + 'static>(
+    list: Vec<T>,
+    agents: &mut [Option<AgentRef>],
+) -> Result<Vec<Arc<RwLock<T>>>, ModelError> {
+    let mut res = vec![];
+    for agent in list {
+        let index = *agent.index() as usize;
+        let agent = Arc::new(RwLock::new(agent));
+        match agents.get_mut(index) {
+            Some(slot @ None) => *slot = Some(agent.clone()),
+            _ => {
+                return Err(ModelError::CheckpointError(format!(
+                    "The index {index} of an agent is not unique or out of range"
+                )))
+            }
+        }
+        res.push(agent);
+    }
+    Ok(res)
+}
+
+/// The agents with the given indices.
+fn pick<T>(
+    by_index: &HashMap<BaseUint, Arc<RwLock<T>>>,
+    indices: &[BaseUint],
+) -> Result<Vec<Arc<RwLock<T>>>, ModelError> {
+    indices
+        .iter()
+        .map(|i| {
+            by_index
+                .get(i)
+                .cloned()
+                .ok_or_else(|| ModelError::CheckpointError(format!("There is no agent with index {i}")))
+        })
+        .collect()
+}
+
+fn file_error(filepath: &Path, e: &std::io::Error) -> ModelError {
+    UtilError::FileSystemError(format!("Path: {} - {e}", filepath.display())).into()
+}
+
+#[cfg(test)]
+mod checkpoint_tests {
+    use super::*;
+
+    fn agent_states(model: &Model) -> Vec<String> {
+        model
+            .agents
+            .iter()
+            .map(|a| serde_json::to_string(a.read_arc_recursive().powerstate()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn resume_from_checkpoint() {
+        let mut model = Model::new(ModelParameters::test()).unwrap();
+        model.step(Steps(20)).expect("Error in taking steps");
+        let filepath = std::env::temp_dir().join(format!("checkpoint_test_{}.json.gz", std::process::id()));
+        model.save_checkpoint(&filepath).expect("Couldnt save checkpoint");
+
+        let mut restored = Model::load_checkpoint(ModelParameters::test(), &filepath).expect("Couldnt load checkpoint");
+        std::fs::remove_file(&filepath).ok();
+        assert_eq!(restored.current_step, Steps(20));
+        assert_eq!(restored.stop_state, model.stop_state);
+        assert_eq!(restored.agents.len(), model.agents.len());
+        assert_eq!(
+            restored.graph.get_children(&1).unwrap(),
+            model.graph.get_children(&1).unwrap()
+        );
+        assert_eq!(
+            restored.netstations[0].read().households.len(),
+            model.netstations[0].read().households.len()
+        );
+
+        model.resume(Steps(20)).expect("Error in taking steps");
+        restored.resume(Steps(20)).expect("Error in taking steps");
+        assert_eq!(restored.current_step, Steps(40));
+        assert_eq!(agent_states(&restored), agent_states(&model));
+    }
+}
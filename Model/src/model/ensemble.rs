@@ -0,0 +1,231 @@
+use log::info;
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+use statrs::distribution::{ContinuousCDF, Normal};
+
+use crate::model::{Checkpoint, Model, ModelParameters};
+use crate::util::{BaseFloat, ModelError, RngStreams, Steps};
+
+/// Runs one configuration of the [Model] for many seeds in parallel, a Monte Carlo ensemble.
+///
+/// By default the topology is generated once, from the seed of the configuration, and each member starts from a copy
+/// of it with its own streams of random numbers. The members then only differ in the draws made while running, e.g.
+/// which devices are infected or patched. Without reusing the topology each member generates its own grid from its
+/// seed, so the grid itself varies as well.
+///
+/// The members do not write output or checkpoints.
+#[derive(Clone, Debug)]
+pub struct Ensemble {
+    /// The configuration all members run.
+    pub param:          ModelParameters,
+    /// The master seed of each member.
+    pub seeds:          Vec<u64>,
+    /// Start all members from the topology generated from the seed of the configuration?
+    pub reuse_topology: bool,
+    /// The confidence level of the bands, e.g. 0.95. Between 0 and 1.
+    pub confidence:     BaseFloat,
+}
+
+/// The mean of a series over the members of an [Ensemble] at a single step, with the confidence band of the mean.
+///
+/// All members are part of each step. A member that stopped early keeps the last value of its series, so the members
+/// that are stopped by a rule do not drop out of the mean.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Band {
+    /// Mean over the members.
+    pub mean:    f64,
+    /// Lower bound of the confidence band.
+    pub lower:   f64,
+    /// Upper bound of the confidence band.
+    pub upper:   f64,
+    /// Number of members.
+    pub members: usize,
+    /// Number of members that stopped before the step, with their last value carried forward.
+    pub stopped: usize,
+}
+
+/// The aggregated time series of an [Ensemble], one band per step.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EnsembleResult {
+    /// Frequency of the grid in mHz.
+    pub frequency:           Vec<Band>,
+    /// Usage of the reserve power in W.
+    pub reserve_usage:       Vec<Band>,
+    /// Number of boundary warnings, both critical and not.
+    pub violations:          Vec<Band>,
+    /// Number of critical boundary warnings.
+    pub critical_violations: Vec<Band>,
+}
+
+/// The series of a single member, one value per step taken.
+#[derive(Clone, Debug, Default)]
+struct MemberSeries {
+    frequency:           Vec<f64>,
+    reserve_usage:       Vec<f64>,
+    violations:          Vec<f64>,
+    critical_violations: Vec<f64>,
+}
+
+impl Ensemble {
+    /// Creates an ensemble of the configuration for the given seeds, reusing the topology with bands of 95%.
+    pub fn new(param: ModelParameters, seeds: impl IntoIterator<Item = u64>) -> Self {
+        Self {
+            param,
+            seeds: seeds.into_iter().collect(),
+            reuse_topology: true,
+            confidence: 0.95,
+        }
+    }
+
+    /// Run all members for the given number of steps and aggregate their series. A member that meets one of the
+    /// [ModelParameters::stop_rules] ends its series early.
+    pub fn run(&self, num_steps: Steps) -> Result<EnsembleResult, ModelError> {
+        if !(self.confidence > 0.0 && self.confidence < 1.0) {
+            return Err(ModelError::ParamError {
+                msg:     format!("the confidence should be between 0 and 1, not {}", self.confidence),
+                context: "ensemble".to_string(),
+            });
+        }
+        let mut param = self.param.clone();
+        param.enable_output = false;
+        param.checkpoint_file = None;
+        let topology = if self.reuse_topology {
+            info!("{} - Generating the topology of the ensemble", param.name);
+            Some(Model::new(param.clone())?.checkpoint())
+        } else {
+            None
+        };
+
+        let members: Vec<MemberSeries> = self
+            .seeds
+            .par_iter()
+            .map(|seed| Self::run_member(&param, topology.as_ref(), *seed, num_steps))
+            .collect::<Result<_, _>>()?;
+        let z = Normal::new(0.0, 1.0)
+            .map_err(|e| ModelError::ParamError {
+                msg:     e.to_string(),
+                context: "ensemble".to_string(),
+            })?
+            .inverse_cdf(0.5 + f64::from(self.confidence) / 2.0);
+        let bands = |series: fn(&MemberSeries) -> &Vec<f64>| bands(members.iter().map(series), z);
+        Ok(EnsembleResult {
+            frequency:           bands(|m| &m.frequency),
+            reserve_usage:       bands(|m| &m.reserve_usage),
+            violations:          bands(|m| &m.violations),
+            critical_violations: bands(|m| &m.critical_violations),
+        })
+    }
+
+    fn run_member(
+        param: &ModelParameters,
+        topology: Option<&Checkpoint>,
+        seed: u64,
+        num_steps: Steps,
+    ) -> Result<MemberSeries, ModelError> {
+        let mut param = param.clone();
+        param.name = format!("{}-{seed}", param.name);
+        let streams = RngStreams::new(seed);
+        param.streams = streams;
+        param.attack.streams = streams;
+        let mut model = match topology {
+            Some(topology) => Model::from_checkpoint(param, topology.clone())?,
+            None => Model::new(param)?,
+        };
+        // The checkpoint brings the streams of the topology, the member draws from its own
+        model.param.streams = streams;
+        model.param.attack.streams = streams;
+
+        let mut series = MemberSeries::default();
+        for _ in 0..num_steps.0 {
+            let result = model.step_once()?;
+            let info = &result.grid_information;
+            series.frequency.push(f64::from(info.freq_state.now.0));
+            series.reserve_usage.push(info.reserve_power.current_usage.0 as f64);
+            series.violations.push(result.warnings.len() as f64);
+            series
+                .critical_violations
+                .push(result.warnings.iter().filter(|w| w.critical).count() as f64);
+            if result.stopped_by.is_some() {
+                break;
+            }
+        }
+        model.flush_output()?;
+        Ok(series)
+    }
+}
+
+/// The band at each step over the series of the members, with `z` the number of standard errors of the band. The
+/// series that end early are carried forward with their last value.
+fn bands<'a>(series: impl Iterator<Item = &'a Vec<f64>> + Clone, z: f64) -> Vec<Band> {
+    let num_steps = series.clone().map(Vec::len).max().unwrap_or(0);
+    (0..num_steps)
+        .map(|step| {
+            let values: Vec<f64> = series
+                .clone()
+                .filter_map(|s| s.get(step).or_else(|| s.last()).copied())
+                .collect();
+            let stopped = series.clone().filter(|s| s.len() <= step).count();
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            let variance = if values.len() > 1 {
+                values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)
+            } else {
+                0.0
+            };
+            let margin = z * (variance / n).sqrt();
+            Band {
+                mean,
+                lower: mean - margin,
+                upper: mean + margin,
+                members: values.len(),
+                stopped,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod ensemble_tests {
+    use super::*;
+
+    #[test]
+    fn bands_over_members() {
+        let series = [vec![1.0, 2.0, 3.0], vec![3.0, 4.0]];
+        let result = bands(series.iter(), 1.96);
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0].mean, 2.0);
+        assert!((result[0].upper - (2.0 + 1.96)).abs() < 1e-9);
+        // The second member stopped, its last value is carried forward
+        assert_eq!(result[2].members, 2);
+        assert_eq!(result[2].stopped, 1);
+        assert_eq!(result[2].mean, 3.5);
+        assert_eq!(result[1].stopped, 0);
+    }
+
+    #[test]
+    fn confidence_between_zero_and_one() {
+        let mut ensemble = Ensemble::new(ModelParameters::test(), [1]);
+        for confidence in [0.0, 1.0, 1.5] {
+            ensemble.confidence = confidence;
+            assert!(ensemble.run(Steps(1)).is_err());
+        }
+    }
+
+    #[test]
+    fn ensemble_runs_all_seeds() {
+        let mut param = ModelParameters::test();
+        param.attack.infection_start = Steps(0);
+        param.attack.infection_rate_per_step = 0.5;
+        param.attack.attack_behaviour[0].report_modifier = 1.0;
+        param.attack.attack_behaviour[0].generation_modifier = 1.0;
+        let ensemble = Ensemble::new(param, [1, 2, 3]);
+        let result = ensemble.run(Steps(4)).unwrap();
+        assert_eq!(result.frequency.len(), 4);
+        assert!(result.frequency.iter().all(|b| b.members == 3));
+        assert!(result
+            .reserve_usage
+            .iter()
+            .all(|b| b.lower <= b.mean && b.mean <= b.upper));
+        assert_eq!(ensemble.run(Steps(4)).unwrap(), result);
+    }
+}
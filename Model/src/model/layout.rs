@@ -0,0 +1,660 @@
+use rayon::prelude::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+
+use crate::agent::{AgentKind, AgentList, BatteryList, Connection, ConnectionList, EvChargerList, GeneratorList, HeatPumpList, HouseholdList, LineStatistics};
+#[allow(unused_imports)]
+use crate::agent::{AgentTrait, Area, Battery, EvCharger, Generator, HeatPump, Household, Netstation, Root};
+use crate::grid::{InfectionCount, InfectionState, PowerState};
+use crate::util::{BaseFloat, BaseUint, Steps, Watt};
+
+/// The power of a list of agents, stored per field in contiguous arrays.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PowerColumns {
+    /// Power that is generated.
+    pub power_generated: Vec<Watt>,
+    /// Power that is used.
+    pub power_used:      Vec<Watt>,
+    /// Power that is reported to be used.
+    pub power_reported:  Vec<Watt>,
+    /// Error between power used and power reported.
+    pub power_error:     Vec<Watt>,
+    /// Power lost in the lines below the agent.
+    pub power_lost:      Vec<Watt>,
+}
+
+impl PowerColumns {
+    /// The columns of the given powerstates, in their order.
+    pub fn from_states<'a>(states: impl IntoIterator<Item = &'a PowerState>) -> Self {
+        Self::from_fields(states.into_iter().map(Self::fields))
+    }
+
+    /// The columns of the given fields of powerstates, in their order. See [PowerColumns::fields].
+    pub fn from_fields(fields: impl IntoIterator<Item = [Watt; 5]>) -> Self {
+        let mut columns = Self::default();
+        for [generated, used, reported, error, lost] in fields {
+            columns.power_generated.push(generated);
+            columns.power_used.push(used);
+            columns.power_reported.push(reported);
+            columns.power_error.push(error);
+            columns.power_lost.push(lost);
+        }
+        columns
+    }
+
+    /// The fields of the powerstate, in the order of the columns.
+    pub const fn fields(state: &PowerState) -> [Watt; 5] {
+        [
+            state.power_generated,
+            state.power_used,
+            state.power_reported,
+            state.power_error,
+            state.power_lost,
+        ]
+    }
+
+    /// Add the power at the position to the sum, with the fields in the order of the columns.
+    #[inline]
+    pub fn add_to(&self, position: usize, sum: &mut [Watt; 5]) {
+        sum[0] += self.power_generated[position];
+        sum[1] += self.power_used[position];
+        sum[2] += self.power_reported[position];
+        sum[3] += self.power_error[position];
+        sum[4] += self.power_lost[position];
+    }
+
+    /// Add the fields of each row to the power at its position. Rows without a position are left out.
+    pub fn add_rows(&mut self, rows: &[[Watt; 5]], positions: &[Option<usize>]) {
+        for (row, position) in rows.iter().zip(positions) {
+            if let Some(position) = *position {
+                self.power_generated[position] += row[0];
+                self.power_used[position] += row[1];
+                self.power_reported[position] += row[2];
+                self.power_error[position] += row[3];
+                self.power_lost[position] += row[4];
+            }
+        }
+    }
+
+    /// Add the losses of a line to the power at the position, see [PowerState::add_losses].
+    pub fn add_losses(&mut self, position: usize, losses: Watt) {
+        self.power_used[position] += losses;
+        self.power_lost[position] += losses;
+        self.power_error[position] -= losses;
+    }
+
+    /// Set the powerstate to the power at the position.
+    pub fn write_to(&self, position: usize, state: &mut PowerState) {
+        state.power_generated = self.power_generated[position];
+        state.power_used = self.power_used[position];
+        state.power_reported = self.power_reported[position];
+        state.power_error = self.power_error[position];
+        state.power_lost = self.power_lost[position];
+    }
+}
+
+/// The state of the connections, stored per field in contiguous arrays in the order of the connections of the model.
+///
+/// The capacity, loss factor and whether a connection is closed are read when the layout is created.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConnectionColumns {
+    /// Maximum power that can flow over the connection.
+    pub capacity:    Vec<Watt>,
+    /// Fraction of the capacity that is lost when the flow equals the capacity.
+    pub loss_factor: Vec<BaseFloat>,
+    /// Is the connection closed, i.e. can power flow over it?
+    pub closed:      Vec<bool>,
+    /// Current flow over the connection. Positive indicates a flow from `from` to `to`.
+    pub flow:        Vec<Watt>,
+    /// Power lost over the connection in this step.
+    pub losses:      Vec<Watt>,
+    /// Is the flow over the connection above its capacity?
+    pub overloaded:  Vec<bool>,
+}
+
+impl ConnectionColumns {
+    /// The columns of the given connections, in their order.
+    pub fn from_connections(connections: &ConnectionList) -> Self {
+        let mut columns = Self::default();
+        for connection in connections {
+            let connection = connection.read();
+            columns.capacity.push(connection.capacity);
+            columns.loss_factor.push(connection.loss_factor);
+            columns.closed.push(connection.closed);
+            columns.flow.push(connection.flow);
+            columns.losses.push(connection.losses);
+            columns.overloaded.push(connection.overloaded);
+        }
+        columns
+    }
+
+    /// Set the flow over the connection at the position, see [Connection::load].
+    pub fn load(&mut self, position: usize, flow: Watt) {
+        let (losses, overloaded) =
+            Connection::losses_and_overload(self.capacity[position], self.loss_factor[position], flow);
+        self.flow[position] = flow;
+        self.losses[position] = losses;
+        self.overloaded[position] = overloaded;
+    }
+
+    /// The losses and congestion over all connections.
+    pub fn statistics(&self) -> LineStatistics {
+        LineStatistics {
+            losses:     self.losses.iter().copied().sum(),
+            overloaded: self.overloaded.iter().filter(|o| **o).count() as BaseUint,
+        }
+    }
+}
+
+/// The infection states of the PV installations of the households and of the devices, in the order of the lists of
+/// the model.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InfectionColumns {
+    /// The PV installations of the [Household] agents.
+    pub households:  Vec<InfectionState>,
+    /// The [EvCharger] agents.
+    pub ev_chargers: Vec<InfectionState>,
+    /// The [HeatPump] agents.
+    pub heat_pumps:  Vec<InfectionState>,
+    /// The [Battery] agents.
+    pub batteries:   Vec<InfectionState>,
+}
+
+/// The position of the household each device belongs to, in the order of the lists of the model.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeviceOwners {
+    /// The owners of the [EvCharger] agents.
+    pub ev_chargers: Vec<Option<usize>>,
+    /// The owners of the [HeatPump] agents.
+    pub heat_pumps:  Vec<Option<usize>>,
+    /// The owners of the [Battery] agents.
+    pub batteries:   Vec<Option<usize>>,
+}
+
+/// Where the power of an agent is kept.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PowerSource {
+    /// The household at the position in the power of the households.
+    Household(usize),
+    /// The generator at the position in the power of the generators.
+    Generator(usize),
+    /// The agent at the position in the power of the level, for the agents with children.
+    Level(usize, usize),
+    /// An agent without power of its own, such as a device or a netstation without households.
+    Childless,
+}
+
+/// A horizontal link of a [Level], with the position of its connection and of both ends in the power of their level
+/// if they have children.
+#[derive(Copy, Clone, Debug)]
+pub struct Link {
+    /// The position of the connection in the columns of the connections.
+    pub connection: usize,
+    /// The level and position of the start in the power of the level.
+    pub from:       Option<(usize, usize)>,
+    /// The level and position of the end in the power of the level.
+    pub to:         Option<(usize, usize)>,
+}
+
+/// The agents at one height of the tree, with their children in a contiguous array. The children of the agent at
+/// position `i` are at positions `offsets[i]..offsets[i + 1]`.
+#[derive(Clone, Debug, Default)]
+pub struct Level {
+    /// The index of each agent of the level.
+    pub indices:   Vec<BaseUint>,
+    /// Where the power of each child is kept, ordered by parent.
+    pub sources:   Vec<PowerSource>,
+    /// Where the children of each agent start, with the number of children as last element.
+    pub offsets:   Vec<usize>,
+    /// The position of the connection of each agent that is a line between two levels of the tree.
+    pub lines:     Vec<Option<usize>>,
+    /// The power of the agents of the level, as aggregated in the last step.
+    pub power:     PowerColumns,
+    /// The infection states of the PV installations and devices of the households below each agent of the level, as
+    /// counted in the last step.
+    pub infection: Vec<InfectionCount>,
+    /// The horizontal links between agents of the level.
+    pub links:     Vec<Link>,
+}
+
+/// The state of the grid in a step, stored per field in contiguous arrays, and the tree of the grid from the bottom up.
+///
+/// The tree is used to aggregate the power of the children to their parents. The agents are grouped in levels by their
+/// height in the tree, so all children of a level are done before it. The power of the [Household] and [Generator]
+/// agents and of the agents of each level is kept in columns owned by the layout, the power of each parent is the sum
+/// over its range of children, a segmented reduction. The infection states of the households and their devices are
+/// counted up the tree in the same way. The households that are not energised are left out, as their
+/// [Netstation] is not energised either. The agents of each level share their power mismatch over their horizontal
+/// links before the level above is done, so the sharing is part of the power of all agents above them.
+///
+/// The households, devices and generators are locked once during a step, in the pass that runs them. They write their
+/// power and infection state to the layout, the devices add their power to the household they belong to. The [Root],
+/// [Area] and [Netstation] agents read their power from the layout when they are checked. The power of the households
+/// with their devices and the state of the [Connection] agents are written to the agents by [GridLayout::store], which
+/// the model calls before its agents are written to the output or a checkpoint and at the end of a run.
+#[derive(Clone, Debug, Default)]
+pub struct GridLayout {
+    /// The step of the state kept in the layout.
+    pub step:        Steps,
+    /// The levels from the bottom of the tree up, starting at the parents of the households.
+    pub levels:      Vec<Level>,
+    /// The power of the households with their devices, in the order of the households of the model.
+    pub households:  PowerColumns,
+    /// Is each household energised?
+    pub supplied:    Vec<bool>,
+    /// The power of the generators, in the order of the generators of the model.
+    pub generators:  PowerColumns,
+    /// The state of the connections, in the order of the connections of the model.
+    pub connections: ConnectionColumns,
+    /// The infection states of the households and their devices.
+    pub infection:   InfectionColumns,
+    /// The household each device belongs to.
+    pub owners:      DeviceOwners,
+    /// Where the power of each agent is kept, by the index of the agent.
+    pub sources:     Vec<PowerSource>,
+}
+
+impl GridLayout {
+    /// Creates the layout from the children of the agents and the connections, with the current state of the agents.
+    /// The index of a child is always larger than that of its parent.
+    pub fn new(
+        agents: &AgentList,
+        households: &HouseholdList,
+        generators: &GeneratorList,
+        ev_chargers: &EvChargerList,
+        heat_pumps: &HeatPumpList,
+        batteries: &BatteryList,
+        connections: &ConnectionList,
+    ) -> Self {
+        let mut heights = vec![0; agents.len()];
+        for (index, agent) in agents.iter().enumerate().rev() {
+            let agent = agent.read();
+            if *agent.kind() != AgentKind::Household {
+                heights[index] = agent
+                    .children()
+                    .iter()
+                    .map(|c| heights[*c.read().index() as usize] + 1)
+                    .max()
+                    .unwrap_or(0);
+            }
+        }
+
+        let mut sources = vec![PowerSource::Childless; agents.len()];
+        let mut owner = vec![None; agents.len()];
+        for (position, household) in households.iter().enumerate() {
+            let household = household.read();
+            sources[household.index as usize] = PowerSource::Household(position);
+            for child in &household.children {
+                owner[*child.read().index() as usize] = Some(position);
+            }
+        }
+        for (position, generator) in generators.iter().enumerate() {
+            sources[generator.read().index as usize] = PowerSource::Generator(position);
+        }
+        let mut line = vec![None; agents.len()];
+        for (position, connection) in connections.iter().enumerate() {
+            line[connection.read().index as usize] = Some(position);
+        }
+
+        let mut levels = vec![Level::default(); heights.iter().max().copied().unwrap_or(0)];
+        let mut children = vec![vec![]; levels.len()];
+        for (index, (agent, height)) in agents.iter().zip(&heights).enumerate() {
+            if *height == 0 {
+                continue;
+            }
+            let level = &mut levels[height - 1];
+            sources[index] = PowerSource::Level(height - 1, level.indices.len());
+            level.offsets.push(children[height - 1].len());
+            level.indices.push(index as BaseUint);
+            level.lines.push(line[index]);
+            children[height - 1].extend(agent.read().children().iter().map(|c| *c.read().index() as usize));
+        }
+        let slot = |index: usize| match sources[index] {
+            PowerSource::Level(level, position) => Some((level, position)),
+            _ => None,
+        };
+        for (position, connection) in connections.iter().enumerate() {
+            let connection = connection.read();
+            let (from, to) = (connection.from as usize, connection.to as usize);
+            let height = heights[from].max(heights[to]);
+            if !connection.vertical && height > 0 {
+                levels[height - 1].links.push(Link {
+                    connection: position,
+                    from:       slot(from),
+                    to:         slot(to),
+                });
+            }
+        }
+        for (level, children) in levels.iter_mut().zip(&children) {
+            level.offsets.push(children.len());
+            level.sources = children.iter().map(|c| sources[*c]).collect();
+            let states: Vec<PowerState> = level
+                .indices
+                .iter()
+                .map(|i| agents[*i as usize].read().powerstate().clone())
+                .collect();
+            level.power = PowerColumns::from_states(&states);
+            level.infection = vec![InfectionCount::default(); level.indices.len()];
+        }
+
+        let states: Vec<PowerState> = households.iter().map(|h| h.read().powerstate.clone()).collect();
+        let generator_states: Vec<PowerState> = generators.iter().map(|g| g.read().powerstate.clone()).collect();
+        Self {
+            step: agents.first().map_or(Steps(0), |a| *a.read().step()),
+            levels,
+            households: PowerColumns::from_states(&states),
+            supplied: households.iter().map(|h| h.read().supply.energised).collect(),
+            generators: PowerColumns::from_states(&generator_states),
+            connections: ConnectionColumns::from_connections(connections),
+            infection: InfectionColumns {
+                households:  households
+                    .iter()
+                    .map(|h| h.read().power_generation.infection_state)
+                    .collect(),
+                ev_chargers: ev_chargers.iter().map(|e| e.read().infection_state).collect(),
+                heat_pumps:  heat_pumps.iter().map(|h| h.read().infection_state).collect(),
+                batteries:   batteries.iter().map(|b| b.read().infection_state).collect(),
+            },
+            owners: DeviceOwners {
+                ev_chargers: ev_chargers.iter().map(|e| owner[e.read().index as usize]).collect(),
+                heat_pumps:  heat_pumps.iter().map(|h| owner[h.read().index as usize]).collect(),
+                batteries:   batteries.iter().map(|b| owner[b.read().index as usize]).collect(),
+            },
+            sources,
+        }
+    }
+
+    /// Set the power of each agent with children to the sum of the power of its children, level by level. A line
+    /// carries the power drawn by its child and consumes its losses, see [Connection]. After each level, its agents
+    /// share their power mismatch over their links.
+    pub fn aggregate(&mut self) {
+        for height in 0..self.levels.len() {
+            let (lower, rest) = self.levels.split_at_mut(height);
+            let (lower, level) = (&*lower, &mut rest[0]);
+            let (households, supplied, generators) = (&self.households, &self.supplied, &self.generators);
+            let power: Vec<[Watt; 5]> = level
+                .offsets
+                .par_iter()
+                .zip(&level.offsets[1..])
+                .map(|(begin, end)| {
+                    let mut sum = [Watt(0); 5];
+                    for source in &level.sources[*begin..*end] {
+                        match *source {
+                            PowerSource::Household(position) if supplied[position] => {
+                                households.add_to(position, &mut sum);
+                            }
+                            PowerSource::Generator(position) => generators.add_to(position, &mut sum),
+                            PowerSource::Level(below, position) => lower[below].power.add_to(position, &mut sum),
+                            PowerSource::Household(_) | PowerSource::Childless => (),
+                        }
+                    }
+                    sum
+                })
+                .collect();
+            level.power = PowerColumns::from_fields(power);
+            for (position, line) in level.lines.iter().enumerate() {
+                if let Some(connection) = *line {
+                    let flow = level.power.power_used[position] - level.power.power_generated[position];
+                    self.connections.load(connection, flow);
+                    level.power.add_losses(position, self.connections.losses[connection]);
+                }
+            }
+            self.share_over_links(height);
+        }
+    }
+
+    /// Share the power mismatch over the horizontal links of the level. Done in order, as links can share an end. The
+    /// losses over a link are consumed by the end that receives the flow.
+    fn share_over_links(&mut self, height: usize) {
+        for position in 0..self.levels[height].links.len() {
+            let link = self.levels[height].links[position];
+            let error = |slot: Option<(usize, usize)>| {
+                slot.map_or(Watt(0), |(level, position)| {
+                    self.levels[level].power.power_error[position]
+                })
+            };
+            let flow = Connection::shared_flow(
+                self.connections.closed[link.connection],
+                self.connections.capacity[link.connection],
+                error(link.from),
+                error(link.to),
+            );
+            self.connections.load(link.connection, flow);
+            let losses = self.connections.losses[link.connection];
+
+            if let Some((level, position)) = link.from {
+                self.levels[level].power.power_error[position] -= flow;
+            }
+            if let Some((level, position)) = link.to {
+                self.levels[level].power.power_error[position] += flow;
+            }
+            let receiving = if flow > Watt(0) { link.to } else { link.from };
+            if let Some((level, position)) = receiving {
+                self.levels[level].power.add_losses(position, losses);
+            }
+        }
+    }
+
+    /// Count the infection states of the PV installations and devices of the households below each agent with children,
+    /// level by level. All households are counted, whether they are energised or not.
+    pub fn count_infection(&mut self) {
+        let mut households: Vec<InfectionCount> = self
+            .infection
+            .households
+            .par_iter()
+            .map(|state| InfectionCount::from(*state))
+            .collect();
+        for (states, owners) in [
+            (&self.infection.ev_chargers, &self.owners.ev_chargers),
+            (&self.infection.heat_pumps, &self.owners.heat_pumps),
+            (&self.infection.batteries, &self.owners.batteries),
+        ] {
+            for (state, owner) in states.iter().zip(owners) {
+                if let Some(owner) = *owner {
+                    households[owner] = households[owner] + InfectionCount::from(*state);
+                }
+            }
+        }
+
+        for height in 0..self.levels.len() {
+            let (lower, rest) = self.levels.split_at_mut(height);
+            let (lower, level) = (&*lower, &mut rest[0]);
+            level.infection = level
+                .offsets
+                .par_iter()
+                .zip(&level.offsets[1..])
+                .map(|(begin, end)| {
+                    level.sources[*begin..*end]
+                        .iter()
+                        .map(|source| match *source {
+                            PowerSource::Household(position) => households[position],
+                            PowerSource::Level(below, position) => lower[below].infection[position],
+                            PowerSource::Generator(_) | PowerSource::Childless => InfectionCount::default(),
+                        })
+                        .sum()
+                })
+                .collect();
+        }
+    }
+
+    /// The infection states of the PV installations and devices of the households below the agent with the given
+    /// index, as counted in the last step. See [GridLayout::count_infection].
+    pub fn infection(&self, index: BaseUint) -> InfectionCount {
+        match self.sources[index as usize] {
+            PowerSource::Level(level, position) => self.levels[level].infection[position],
+            _ => InfectionCount::default(),
+        }
+    }
+
+    /// Set the powerstate to the power kept for the agent with the given index. The powerstate of an agent without
+    /// power of its own is left as is.
+    pub fn write_power(&self, index: BaseUint, state: &mut PowerState) {
+        match self.sources[index as usize] {
+            PowerSource::Household(position) => self.households.write_to(position, state),
+            PowerSource::Generator(position) => self.generators.write_to(position, state),
+            PowerSource::Level(level, position) => self.levels[level].power.write_to(position, state),
+            PowerSource::Childless => (),
+        }
+    }
+
+    /// The power used by the households that are children of the agent with the given index in the last step,
+    /// whether they are energised or not.
+    pub fn load(&self, index: BaseUint) -> Watt {
+        let PowerSource::Level(level, position) = self.sources[index as usize] else {
+            return Watt(0);
+        };
+        let level = &self.levels[level];
+        level.sources[level.offsets[position]..level.offsets[position + 1]]
+            .iter()
+            .filter_map(|source| match *source {
+                PowerSource::Household(household) => Some(self.households.power_used[household]),
+                _ => None,
+            })
+            .sum()
+    }
+
+    /// The reported power of the energised households, the forecast of their demand.
+    pub fn forecast(&self) -> Watt {
+        self.households
+            .power_reported
+            .iter()
+            .zip(&self.supplied)
+            .filter(|(_, supplied)| **supplied)
+            .map(|(reported, _)| *reported)
+            .sum()
+    }
+
+    /// Write the state kept in the layout to the [Household] and [Connection] agents, so they can be read through the
+    /// [AgentTrait] and written to the output. The other agents are updated during the step.
+    pub fn store(&self, households: &HouseholdList, connections: &ConnectionList) {
+        households.par_iter().enumerate().for_each(|(position, household)| {
+            let mut household = household.write();
+            household.step = self.step;
+            self.households.write_to(position, &mut household.powerstate);
+        });
+        for (position, connection) in connections.iter().enumerate() {
+            let mut connection = connection.write();
+            connection.step = self.step;
+            connection.flow = self.connections.flow[position];
+            connection.losses = self.connections.losses[position];
+            connection.overloaded = self.connections.overloaded[position];
+            if !connection.vertical {
+                connection.powerstate.power_used = connection.flow;
+                connection.powerstate.power_lost = connection.losses;
+            }
+        }
+        for level in &self.levels {
+            for (position, line) in level.lines.iter().enumerate() {
+                if let Some(line) = *line {
+                    level
+                        .power
+                        .write_to(position, &mut connections[line].write().powerstate);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod layout_tests {
+    use super::*;
+    use crate::model::{Model, ModelParameters};
+    use crate::util::Steps;
+
+    #[test]
+    fn layout_matches_children() {
+        let mut param = ModelParameters::test();
+        param.grid.n_areas = 2;
+        param.grid.ns_line_capacity = Watt(1_000_000);
+        let mut model = Model::new(param).unwrap();
+        let layout = &model.layout;
+        // Netstations, their lines, areas and the root
+        assert_eq!(layout.levels.len(), 4);
+        assert_eq!(layout.levels[0].indices.len(), model.netstations.len());
+        assert_eq!(layout.levels[0].sources.len(), model.households.len());
+        assert_eq!(layout.levels[3].indices.len(), 1);
+
+        model.step(Steps(2)).expect("Error in taking steps");
+        let households = model
+            .households
+            .iter()
+            .map(|h| h.read().powerstate.power_used)
+            .sum::<Watt>();
+        let netstations = model
+            .netstations
+            .iter()
+            .map(|ns| ns.read().powerstate.power_used)
+            .sum::<Watt>();
+        assert_eq!(households, netstations);
+    }
+
+    #[test]
+    fn infection_counts_devices() {
+        let mut param = ModelParameters::test();
+        param.grid.n_areas = 2;
+        param.grid.battery_adoption = 1.0;
+        let mut model = Model::new(param).unwrap();
+        for battery in model.batteries.iter().step_by(2) {
+            battery.write().infection_state = InfectionState::Infected;
+        }
+        model.step(Steps(3)).expect("Error in taking steps");
+
+        let batteries: Vec<InfectionCount> = model
+            .batteries
+            .iter()
+            .map(|b| InfectionCount::from(b.read().infection_state))
+            .collect();
+        assert!(batteries.iter().any(|b| b.infected > 0));
+        let expected = model
+            .households
+            .iter()
+            .map(|h| InfectionCount::from(h.read().power_generation.infection_state))
+            .chain(batteries)
+            .sum::<InfectionCount>();
+        assert_eq!(model.layout.infection(model.root.read().index), expected);
+        let areas = model
+            .areas
+            .iter()
+            .map(|a| model.layout.infection(a.read().index))
+            .sum::<InfectionCount>();
+        assert_eq!(areas, expected);
+    }
+
+    #[test]
+    fn sharing_included_in_root() {
+        let mismatch = |link_density: f32| {
+            let mut param = ModelParameters::test();
+            param.grid.n_areas = 3;
+            param.grid.ns_link_density = link_density;
+            param.grid.ns_link_capacity = Watt(10_000);
+            param.grid.area_link_density = link_density;
+            param.grid.area_link_capacity = Watt(10_000);
+            param.grid.line_loss_factor = 0.02;
+            param.attack.percentage_vuln_devices = 1.0;
+            param.attack.infection_rate_per_step = 0.5;
+            let mut model = Model::new(param).unwrap();
+            model.step(Steps(12)).expect("Error in taking steps");
+            // The compensation of the last step is applied to the root agent, not to the power kept in the layout
+            let leaves = model
+                .agents
+                .iter()
+                .map(|a| a.read())
+                .filter(|a| matches!(a.kind(), AgentKind::Household | AgentKind::Generator))
+                .map(|a| a.powerstate().power_error)
+                .sum::<Watt>();
+            let losses = model.layout.connections.losses.iter().copied().sum::<Watt>();
+            let mut root = PowerState::new();
+            model.layout.write_power(model.root.read().index, &mut root);
+            (root, leaves, losses)
+        };
+        let (root, leaves, losses) = mismatch(0.0);
+        assert_eq!(losses, Watt(0));
+        assert_eq!(root.power_error, leaves);
+
+        // The losses over the links are consumed within the grid
+        let (linked_root, linked_leaves, losses) = mismatch(1.0);
+        assert!(losses > Watt(0));
+        assert_eq!(linked_leaves, leaves);
+        assert_eq!(linked_root.power_error, leaves - losses);
+        assert_eq!(linked_root.power_lost, losses);
+        assert_eq!(linked_root.power_used, root.power_used + losses);
+    }
+}
@@ -6,67 +6,90 @@ use serde::{Serialize};
 
 use crate::{attack::Attack};
 use crate::grid::Grid;
-use crate::util::Steps;
+#[allow(unused_imports)]
+use crate::model::Checkpoint;
+use crate::model::{Scenario, StopRule};
+use crate::util::{RngStreams, Steps};
 
 /// Struct that defines the model parameters
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct ModelParameters {
     /// Naming for the model. Used for logs etc.
-    pub name:               String,
+    pub name:                String,
     /// Number of steps in the Model
-    pub steps:              Steps,
-    /// Seed for the random generators, same
-    /// behaviour between runs
+    pub steps:               Steps,
+    /// The streams of random numbers, derived from the seed given. Same behaviour between runs, regardless of the
+    /// number of threads.
+    pub streams:             RngStreams,
+    /// The stream of random numbers drawn from while the grid is generated: the number of netstations and households,
+    /// the properties of the households and their devices, and the horizontal links. It is set from the streams before
+    /// each agent or link is drawn, the steps of the model only draw from the streams.
     #[serde(skip)]
-    #[serde(default = "default_smallrng")]
-    pub seed:               SmallRng,
+    pub seed:                SmallRng,
     /// Folder to store logs
-    pub logfolder:          String,
+    pub logfolder:           String,
     /// Chrono - format string to determine the filename. [link](https://docs.rs/chrono/latest/chrono/format/strftime/index.html)
-    pub logfile:            String,
+    pub logfile:             String,
     /// Enable the output of the node states per step.
-    pub enable_output:      bool,
+    pub enable_output:       bool,
     /// Type of output, avro or json.
-    pub type_output:        String,
+    pub type_output:         String,
     /// Folder to store the output
-    pub outputdatafolder:   String,
+    pub outputdatafolder:    String,
     /// Chrono - format string to determine the filename. [link](https://docs.rs/chrono/latest/chrono/format/strftime/index.html)
-    pub outputdatafile:     String,
+    pub outputdatafile:      String,
     /// The codec that is used. Currently supported: "snappy", "deflate",
     /// 'null", See also [Codec].
     #[serde(skip)]
-    pub outputcodec:        Codec,
+    pub outputcodec:         Codec,
     /// The attack struct of this model. See [Attack].
     #[serde(skip)]
-    pub attack:             Attack,
+    pub attack:              Attack,
     /// The Grid struct of this model. See [Grid].
     #[serde(skip)]
-    pub grid:               Grid,
+    pub grid:                Grid,
     /// The log level of the executable.
     #[serde(skip)]
-    pub loglevel:           LevelFilter,
-    /// Stop running the model when there is a grid frequency error.
-    pub stop_on_freq_error: bool,
+    pub loglevel:            LevelFilter,
+    /// The rules that end the run early. See [StopRule].
+    pub stop_rules:          Vec<StopRule>,
+    /// The file the checkpoints of the model are saved to, if any. See [Checkpoint].
+    pub checkpoint_file:     Option<String>,
+    /// Number of steps between two checkpoints. Only saved at the end of the run if zero.
+    pub checkpoint_interval: Steps,
+    /// The checkpoint the model is restored from, instead of generating a new grid.
+    pub resume_file:         Option<String>,
+    /// The file the graph of the generated grid is exported to, if any. See [Model::export_graph].
+    pub graph_export_file:   Option<String>,
+    /// The timeline of events that perturb the grid. See [Scenario].
+    #[serde(skip)]
+    pub scenario:            Scenario,
 }
 
 impl ModelParameters {
     /// Creates a test version to be used for testing within the crate.
     pub fn test() -> Self {
         Self {
-            name:               "Test".to_string(),
-            seed:               SmallRng::seed_from_u64(117),
-            steps:              Steps(96 * 365),
-            logfolder:          "logs".to_string(),
-            logfile:            chrono::Local::now().format("%Y%m%d-%H_%M_%S").to_string(),
-            enable_output:      false,
-            outputdatafolder:   "data".to_string(),
-            outputdatafile:     chrono::Local::now().format("%Y%m%d-%H_%M_%S").to_string(),
-            outputcodec:        Codec::Null,
-            attack:             Attack::test(),
-            grid:               Grid::_test(),
-            loglevel:           LevelFilter::Debug,
-            type_output:        "json".to_string(),
-            stop_on_freq_error: false,
+            name:                "Test".to_string(),
+            streams:             RngStreams::new(117),
+            seed:                SmallRng::seed_from_u64(117),
+            steps:               Steps(96 * 365),
+            logfolder:           "logs".to_string(),
+            logfile:             chrono::Local::now().format("%Y%m%d-%H_%M_%S").to_string(),
+            enable_output:       false,
+            outputdatafolder:    "data".to_string(),
+            outputdatafile:      chrono::Local::now().format("%Y%m%d-%H_%M_%S").to_string(),
+            outputcodec:         Codec::Null,
+            attack:              Attack::test(),
+            grid:                Grid::_test(),
+            loglevel:            LevelFilter::Debug,
+            type_output:         "json".to_string(),
+            stop_rules:          vec![],
+            checkpoint_file:     None,
+            checkpoint_interval: Steps(0),
+            resume_file:         None,
+            graph_export_file:   None,
+            scenario:            Scenario::default(),
         }
     }
 }
@@ -2,26 +2,33 @@ use std::ops::{Deref, DerefMut};
 
 use petgraph::adj::NodeIndex;
 use petgraph::dot::{Config, Dot};
+use petgraph::visit::EdgeRef;
 use petgraph::{Directed, Graph};
+use serde::{Deserialize, Serialize};
 
 use crate::agent::AgentKind;
 use crate::util::{BaseInt, BaseUint, ModelError};
 
+/// Weight of the edges that form the tree of the grid.
+pub const TREE_EDGE: BaseInt = 1;
+/// Weight of the edges that link agents horizontally through a [Connection](crate::agent::Connection).
+pub const LINK_EDGE: BaseInt = 2;
+
 /// A newtype struct for encapsulating the graph. It defines the types of the
 /// graph.
-#[derive(Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct MyGraph {
     /// Inner graph
     pub content: Graph<BaseInt, BaseInt, Directed>,
 }
 
 impl MyGraph {
-    /// Get children from graph using the graph index of the parent.
+    /// Get children from graph using the graph index of the parent. Only follows the edges of the tree.
     pub fn get_children(&self, node_index: &BaseInt) -> Result<Vec<BaseInt>, ModelError> {
         let mut res: Vec<BaseInt> = vec![];
         let node = NodeIndex::from(*node_index as BaseUint);
-        for neighbour in self.neighbors(node) {
-            res.push(neighbour.index() as BaseInt);
+        for edge in self.edges(node).filter(|e| *e.weight() == TREE_EDGE) {
+            res.push(edge.target().index() as BaseInt);
         }
         res.sort_unstable();
         Ok(res)
@@ -32,8 +39,20 @@ impl MyGraph {
 
     /// Directed edge add
     pub fn add_edge(&mut self, from: BaseUint, to: BaseUint) -> Result<(), ModelError> {
+        self.content.add_edge(
+            NodeIndex::from(from as BaseUint),
+            NodeIndex::from(to as BaseUint),
+            TREE_EDGE,
+        );
+        Ok(())
+    }
+
+    /// Add a horizontal link between two agents through the connection agent. Links are not part of the tree.
+    pub fn add_link(&mut self, from: BaseUint, connection: BaseUint, to: BaseUint) -> Result<(), ModelError> {
+        self.content
+            .add_edge(NodeIndex::from(from), NodeIndex::from(connection), LINK_EDGE);
         self.content
-            .add_edge(NodeIndex::from(from as BaseUint), NodeIndex::from(to as BaseUint), 1);
+            .add_edge(NodeIndex::from(connection), NodeIndex::from(to), LINK_EDGE);
         Ok(())
     }
 
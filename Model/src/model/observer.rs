@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::grid::GridWarning;
+use crate::model::{Model, StepResult};
+use crate::util::{mPercent, Steps};
+
+/// Implementing this trait lets structs observe the [Model] while it runs, e.g. to collect metrics in-process instead
+/// of scraping the logs. Observers are added with [Model::add_observer].
+///
+/// Each hook gets read access to the model, and thereby to its agents. During a run, the power of the households with
+/// their devices and the state of the connections are read from the [GridLayout](crate::model::GridLayout) of the
+/// model. All hooks do nothing by default, so an observer only implements the ones it needs.
+pub trait Observer {
+    /// Called before the step starts.
+    fn on_step_start(&mut self, _model: &Model, _step: Steps) {}
+    /// Called after each substep of the step has finished, with the name of the substep.
+    fn on_substep(&mut self, _model: &Model, _step: Steps, _substep: &str) {}
+    /// Called for each warning of the boundaries of the grid, both critical and not.
+    fn on_warning(&mut self, _model: &Model, _step: Steps, _warning: &GridWarning) {}
+    /// Called after the step has finished, with its result.
+    fn on_step_end(&mut self, _model: &Model, _result: &StepResult) {}
+}
+
+/// An observer that is shared, so the caller keeps access to what it collects while the model runs.
+impl<T: Observer> Observer for Arc<Mutex<T>> {
+    fn on_step_start(&mut self, model: &Model, step: Steps) { self.lock().on_step_start(model, step) }
+
+    fn on_substep(&mut self, model: &Model, step: Steps, substep: &str) { self.lock().on_substep(model, step, substep) }
+
+    fn on_warning(&mut self, model: &Model, step: Steps, warning: &GridWarning) {
+        self.lock().on_warning(model, step, warning)
+    }
+
+    fn on_step_end(&mut self, model: &Model, result: &StepResult) { self.lock().on_step_end(model, result) }
+}
+
+/// Collects the share of infected PV installations and devices of each area at each step.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AreaInfectionCurves {
+    /// The share of infected devices of each area, for each step observed.
+    pub curves: Vec<Vec<mPercent>>,
+}
+
+impl Observer for AreaInfectionCurves {
+    fn on_step_end(&mut self, _model: &Model, result: &StepResult) {
+        let area_states = &result.grid_information.area_states;
+        self.curves.resize(area_states.len(), vec![]);
+        for (curve, area_state) in self.curves.iter_mut().zip(area_states) {
+            curve.push(area_state.infected);
+        }
+    }
+}
+
+impl Model {
+    /// Add an observer that is called during each step of the model.
+    pub fn add_observer(&mut self, observer: impl Observer + Send + Sync + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Call the given hook on all observers. The observers are taken out of the model while they are called, so they
+    /// can read the model.
+    pub(crate) fn notify(&mut self, mut hook: impl FnMut(&mut dyn Observer, &Self)) {
+        if self.observers.is_empty() {
+            return;
+        }
+        let mut observers = std::mem::take(&mut self.observers);
+        for observer in &mut observers {
+            hook(observer.as_mut(), self);
+        }
+        self.observers = observers;
+    }
+}
+
+#[cfg(test)]
+mod observer_tests {
+    use super::*;
+    use crate::model::ModelParameters;
+
+    /// Counts the calls of each hook.
+    #[derive(Default)]
+    struct Counter {
+        start:   usize,
+        substep: usize,
+        end:     usize,
+    }
+
+    impl Observer for Counter {
+        fn on_step_start(&mut self, model: &Model, step: Steps) {
+            assert_eq!(model.current_step, step);
+            self.start += 1;
+        }
+
+        fn on_substep(&mut self, _model: &Model, _step: Steps, _substep: &str) { self.substep += 1; }
+
+        fn on_step_end(&mut self, model: &Model, result: &StepResult) {
+            assert_eq!(model.current_step, result.step + Steps(1));
+            self.end += 1;
+        }
+    }
+
+    #[test]
+    fn observers_are_called() {
+        let mut model = Model::new(ModelParameters::test()).unwrap();
+        let counter = Arc::new(Mutex::new(Counter::default()));
+        let curves = Arc::new(Mutex::new(AreaInfectionCurves::default()));
+        model.add_observer(counter.clone());
+        model.add_observer(curves.clone());
+        model.step(Steps(3)).expect("Error in taking steps");
+
+        let (start, substep, end) = {
+            let counter = counter.lock();
+            (counter.start, counter.substep, counter.end)
+        };
+        assert_eq!((start, end), (3, 3));
+        assert!(substep > 3 * 5);
+        assert_eq!(curves.lock().curves.len(), model.areas.len());
+        assert!(curves.lock().curves.iter().all(|c| c.len() == 3));
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::HashSet;
+
+use log::{info, warn};
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+use crate::agent::GeneratorTrip;
+#[allow(unused_imports)]
+use crate::agent::{AgentKind, AgentTrait, Generator, Household, Netstation};
+use crate::grid::InfectionState;
+use crate::model::Model;
+use crate::util::{random_percentage, BaseFloat, BaseInt, BaseUint, RngPurpose, Steps, Watt};
+
+/// The agents an event of a [Scenario] is applied to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventTarget {
+    /// All agents the event applies to.
+    All,
+    /// The agents in the given areas, numbered from 1.
+    Areas(Vec<usize>),
+    /// The agents with the given indices in the graph.
+    Agents(Vec<BaseUint>),
+}
+
+/// What happens when an event of a [Scenario] fires. A duration of zero lasts until the end of the run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ScenarioEventKind {
+    /// The targeted [Generator] agents trip for the duration.
+    GeneratorOutage {
+        /// The generators that trip.
+        target:   EventTarget,
+        /// Number of steps the generators are offline.
+        duration: Steps,
+    },
+    /// The bulk consumption changes by the given power.
+    BulkLoadChange(Watt),
+    /// The targeted [Netstation] agents and their households lose their supply for the duration.
+    NetstationDisconnection {
+        /// The netstations that are disconnected.
+        target:   EventTarget,
+        /// Number of steps the netstations are disconnected.
+        duration: Steps,
+    },
+    /// The room of the reserve power changes to the given capacity, both to produce less and to produce more.
+    ReserveCapacityChange(Watt),
+    /// The operator curtails the PV installations of the targeted [Household] agents for the duration.
+    PvCurtailment {
+        /// The households whose PV installation is curtailed.
+        target:   EventTarget,
+        /// Number of steps the PV installations are curtailed.
+        duration: Steps,
+    },
+    /// A patch is released. A share of the vulnerable and infected devices of the targeted [Household] agents is
+    /// patched at once.
+    PatchRelease {
+        /// The households whose devices are patched.
+        target: EventTarget,
+        /// Share of the devices that is patched.
+        share:  BaseFloat,
+    },
+}
+
+/// An event of a [Scenario], fired at the given step.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioEvent {
+    /// The step the event fires.
+    pub step: Steps,
+    /// What happens.
+    pub kind: ScenarioEventKind,
+}
+
+impl ScenarioEvent {
+    /// The step the effect of the event ends, if it has a duration.
+    fn end(&self) -> Option<Steps> {
+        match &self.kind {
+            ScenarioEventKind::GeneratorOutage { duration, .. }
+            | ScenarioEventKind::NetstationDisconnection { duration, .. }
+            | ScenarioEventKind::PvCurtailment { duration, .. } => {
+                (*duration > Steps(0)).then(|| self.step + *duration)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A timeline of events that perturb the grid at given steps, as given by the ScenarioParameters file.
+///
+/// Events fire at the start of the step, before the households run and before the attack. The effects of events with a
+/// duration end at the start of the step the duration is over.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    /// The events of the scenario.
+    pub events: Vec<ScenarioEvent>,
+}
+
+impl Scenario {
+    /// Does the scenario have any events?
+    pub fn is_empty(&self) -> bool { self.events.is_empty() }
+
+    /// The change of the bulk consumption by the events that fired before the given step.
+    pub fn bulk_load_change_before(&self, step: Steps) -> Watt {
+        self.events
+            .iter()
+            .filter(|e| e.step < step)
+            .filter_map(|e| match e.kind {
+                ScenarioEventKind::BulkLoadChange(change) => Some(change),
+                _ => None,
+            })
+            .sum()
+    }
+}
+
+impl Model {
+    /// Apply the events of the scenario that fire at the given step, and end the effects of those whose duration is
+    /// over.
+    pub(crate) fn apply_scenario(&mut self, step: Steps) {
+        if self.param.scenario.is_empty() {
+            return;
+        }
+        let scenario = std::mem::take(&mut self.param.scenario);
+        for event in scenario.events.iter().filter(|e| e.end() == Some(step)) {
+            self.end_event(event, step);
+        }
+        for event in scenario.events.iter().filter(|e| e.step == step) {
+            info!("Scenario - {:?} at step {step}", event.kind);
+            self.fire_event(event, step);
+        }
+        self.param.scenario = scenario;
+    }
+
+    fn fire_event(&mut self, event: &ScenarioEvent, step: Steps) {
+        match &event.kind {
+            ScenarioEventKind::GeneratorOutage { target, .. } => {
+                let targeted = self.targeted(target, AgentKind::Generator);
+                let trip = GeneratorTrip {
+                    begin: step,
+                    end:   event.end().unwrap_or(Steps(BaseInt::MAX)),
+                };
+                self.generators
+                    .iter()
+                    .filter(|g| is_targeted(&targeted, g.read_arc_recursive().index))
+                    .for_each(|g| g.write_arc().trips.push(trip));
+            }
+            ScenarioEventKind::BulkLoadChange(change) => self.param.grid.bulk_consumption += *change,
+            ScenarioEventKind::NetstationDisconnection { target, .. } => {
+                let targeted = self.targeted(target, AgentKind::Netstation);
+                self.netstations
+                    .par_iter()
+                    .filter(|ns| is_targeted(&targeted, ns.read_arc_recursive().index))
+                    .for_each(|ns| ns.write_arc().de_energise());
+            }
+            ScenarioEventKind::ReserveCapacityChange(capacity) => {
+                let reserve = &mut self.reserve_power;
+                reserve.lower_limit = *capacity * -1;
+                reserve.upper_limit = *capacity;
+                reserve.current_usage = reserve.current_usage.clamp(reserve.lower_limit, reserve.upper_limit);
+            }
+            ScenarioEventKind::PvCurtailment { target, .. } => {
+                let targeted = self.targeted(target, AgentKind::Household);
+                let reconnect_at = event.end().unwrap_or(Steps(BaseInt::MAX));
+                self.households
+                    .par_iter()
+                    .filter(|h| is_targeted(&targeted, h.read_arc_recursive().index))
+                    .for_each(|h| {
+                        let mut h = h.write_arc();
+                        h.supply.pv_connected = false;
+                        h.supply.pv_clean_connected = false;
+                        h.supply.pv_reconnect_at = reconnect_at;
+                    });
+            }
+            ScenarioEventKind::PatchRelease { target, share } => self.release_patch(target, *share, step),
+        }
+    }
+
+    /// End the effect of the event. PV installations reconnect directly, unless the grid is restored after a blackout,
+    /// in which case they follow the reconnection rules of the restoration. Netstations stay de-energised while the
+    /// blackout lasts, the restoration picks them up in its order.
+    fn end_event(&self, event: &ScenarioEvent, step: Steps) {
+        let (pv_reconnect_delay, reconnect_now) = self
+            .param
+            .grid
+            .restoration
+            .as_ref()
+            .map_or((Steps(0), true), |r| (r.pv_reconnect_delay, false));
+        match &event.kind {
+            ScenarioEventKind::NetstationDisconnection { .. }
+                if self.blackout.is_some_and(|b| b.recovery_time.is_none()) => {}
+            ScenarioEventKind::NetstationDisconnection { target, .. } => {
+                let targeted = self.targeted(target, AgentKind::Netstation);
+                self.netstations
+                    .par_iter()
+                    .filter(|ns| is_targeted(&targeted, ns.read_arc_recursive().index))
+                    .for_each(|ns| {
+                        let mut ns = ns.write_arc();
+                        ns.energise(step, pv_reconnect_delay);
+                        ns.households
+                            .iter()
+                            .for_each(|h| h.write_arc().supply.reconnect_pv(step, reconnect_now, false));
+                    });
+            }
+            ScenarioEventKind::PvCurtailment { target, .. } => {
+                let targeted = self.targeted(target, AgentKind::Household);
+                self.households
+                    .par_iter()
+                    .filter(|h| is_targeted(&targeted, h.read_arc_recursive().index))
+                    .for_each(|h| h.write_arc().supply.reconnect_pv(step, reconnect_now, false));
+            }
+            _ => (),
+        }
+    }
+
+    /// Patch a share of the power generation, batteries, EV chargers and heat pumps of the targeted households.
+    fn release_patch(&self, target: &EventTarget, share: BaseFloat, step: Steps) {
+        let streams = self.param.streams;
+        let will_patch =
+            |index: BaseUint| random_percentage(&mut streams.stream_at(RngPurpose::Scenario, index, step)) < share;
+        let patch = |state: InfectionState, index: BaseUint| state.progress(false, will_patch(index));
+
+        let targeted = self.targeted(target, AgentKind::Household);
+        let devices: HashSet<BaseUint> = self
+            .households
+            .par_iter()
+            .filter(|h| is_targeted(&targeted, h.read_arc_recursive().index))
+            .flat_map_iter(|h| {
+                let mut h = h.write_arc();
+                let index = h.index;
+                h.power_generation.infection_state = patch(h.power_generation.infection_state, index);
+                h.children
+                    .iter()
+                    .map(|c| *c.read_arc_recursive().index())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        self.ev_chargers
+            .par_iter()
+            .filter(|ev| devices.contains(&ev.read_arc_recursive().index))
+            .for_each(|ev| {
+                let mut ev = ev.write_arc();
+                ev.infection_state = patch(ev.infection_state, ev.index);
+            });
+        self.heat_pumps
+            .par_iter()
+            .filter(|hp| devices.contains(&hp.read_arc_recursive().index))
+            .for_each(|hp| {
+                let mut hp = hp.write_arc();
+                hp.infection_state = patch(hp.infection_state, hp.index);
+            });
+        self.batteries
+            .par_iter()
+            .filter(|b| devices.contains(&b.read_arc_recursive().index))
+            .for_each(|b| {
+                let mut b = b.write_arc();
+                b.infection_state = patch(b.infection_state, b.index);
+            });
+    }
+
+    /// The indices of the agents of the given kind that are targeted, or none if all are.
+    fn targeted(&self, target: &EventTarget, kind: AgentKind) -> Option<HashSet<BaseUint>> {
+        match target {
+            EventTarget::All => None,
+            EventTarget::Agents(indices) => Some(indices.iter().copied().collect()),
+            EventTarget::Areas(areas) => {
+                let mut indices = HashSet::new();
+                for area_number in areas {
+                    let Some(area) = area_number.checked_sub(1).and_then(|a| self.areas.get(a)) else {
+                        warn!("Scenario - area {area_number} does not exist");
+                        continue;
+                    };
+                    let area = area.read_arc_recursive();
+                    match kind {
+                        AgentKind::Netstation => {
+                            indices.extend(area.netstations.iter().map(|ns| ns.read_arc_recursive().index));
+                        }
+                        AgentKind::Household => indices.extend(area.netstations.iter().flat_map(|ns| {
+                            ns.read_arc_recursive()
+                                .households
+                                .iter()
+                                .map(|h| h.read_arc_recursive().index)
+                                .collect::<Vec<_>>()
+                        })),
+                        _ => indices.extend(
+                            area.children
+                                .iter()
+                                .map(|c| c.read_arc_recursive())
+                                .filter(|c| *c.kind() == kind)
+                                .map(|c| *c.index()),
+                        ),
+                    }
+                }
+                Some(indices)
+            }
+        }
+    }
+}
+
+/// Is the agent with the given index targeted?
+fn is_targeted(targeted: &Option<HashSet<BaseUint>>, index: BaseUint) -> bool {
+    targeted.as_ref().is_none_or(|t| t.contains(&index))
+}
+
+#[cfg(test)]
+mod scenario_tests {
+    use super::*;
+    use crate::grid::{Blackout, RestorationParam};
+    use crate::model::ModelParameters;
+    use crate::util::mHz;
+
+    #[test]
+    fn events_fire_and_end() {
+        let mut param = ModelParameters::test();
+        let event = |step, kind| ScenarioEvent {
+            step: Steps(step),
+            kind,
+        };
+        param.scenario = Scenario {
+            events: vec![
+                event(
+                    1,
+                    ScenarioEventKind::NetstationDisconnection {
+                        target:   EventTarget::Areas(vec![1]),
+                        duration: Steps(2),
+                    },
+                ),
+                event(
+                    1,
+                    ScenarioEventKind::PvCurtailment {
+                        target:   EventTarget::All,
+                        duration: Steps(0),
+                    },
+                ),
+                event(1, ScenarioEventKind::BulkLoadChange(Watt(100))),
+                event(1, ScenarioEventKind::ReserveCapacityChange(Watt(5_000))),
+                event(
+                    1,
+                    ScenarioEventKind::PatchRelease {
+                        target: EventTarget::All,
+                        share:  1.0,
+                    },
+                ),
+            ],
+        };
+        let bulk_consumption = param.grid.bulk_consumption;
+        let mut model = Model::new(param).unwrap();
+        model.step(Steps(2)).expect("Error in taking steps");
+
+        assert!(model.netstations.iter().all(|ns| !ns.read_arc_recursive().energised));
+        assert_eq!(model.param.grid.bulk_consumption, bulk_consumption + Watt(100));
+        assert_eq!(model.reserve_power.upper_limit, Watt(5_000));
+        assert!(model.households.iter().all(|h| {
+            let h = h.read_arc_recursive();
+            !h.supply.pv_connected
+                && matches!(
+                    h.power_generation.infection_state,
+                    InfectionState::NotVulnerable | InfectionState::Patched
+                )
+        }));
+
+        model.resume(Steps(2)).expect("Error in taking steps");
+        assert!(model.netstations.iter().all(|ns| ns.read_arc_recursive().energised));
+        assert!(model
+            .households
+            .iter()
+            .all(|h| !h.read_arc_recursive().supply.pv_connected));
+    }
+
+    #[test]
+    fn disconnection_ends_during_blackout() {
+        let mut param = ModelParameters::test();
+        param.grid.restoration = Some(RestorationParam {
+            black_start_time:   Steps(3),
+            sequence:           vec![],
+            load_pickup_limit:  Watt(1_000_000_000),
+            pv_reconnect_delay: Steps(1),
+            pv_reconnect_band:  mHz(100),
+        });
+        param.scenario = Scenario {
+            events: vec![ScenarioEvent {
+                step: Steps(0),
+                kind: ScenarioEventKind::NetstationDisconnection {
+                    target:   EventTarget::All,
+                    duration: Steps(2),
+                },
+            }],
+        };
+        let mut model = Model::new(param).unwrap();
+        model.netstations.iter().for_each(|ns| ns.write().de_energise());
+        model.blackout = Some(Blackout::new(Steps(0)));
+
+        // The disconnection ends at step 2, before the black start
+        model.step(Steps(3)).expect("Error in taking steps");
+        assert!(model.netstations.iter().all(|ns| !ns.read_arc_recursive().energised));
+        assert!(model
+            .households
+            .iter()
+            .all(|h| !h.read_arc_recursive().supply.energised));
+
+        model.resume(Steps(1)).expect("Error in taking steps");
+        assert!(model.netstations.iter().all(|ns| ns.read_arc_recursive().energised));
+        assert_eq!(model.blackout.expect("No blackout").recovery_time, Some(Steps(3)));
+    }
+}
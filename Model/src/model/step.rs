@@ -1,38 +1,82 @@
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use log::{debug, error, info, warn};
 use parking_lot::RwLock;
 // #[cfg(feature = "multi_thread")]
-use rayon::{iter::Either, prelude::{IntoParallelRefIterator, ParallelIterator}};
+use rayon::{iter::Either, prelude::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator}};
 use serde::{Serialize, Deserialize};
-use tokio::task::JoinHandle;
 
-use crate::agent::{AgentKind, AgentList, AgentTrait, Area, Household, Netstation, Root};
-use crate::attack::Attack;
-use crate::grid::{BoundaryAgentTrait, FreqState, Grid, GridState, GridWarning, InfectionState, InfectionStatistics, PowerState, ReservePower};
-use crate::model::Model;
+use crate::agent::{AgentKind, AgentList, AgentTrait, Area, FleetDispatch, GeneratorList, LineStatistics, Netstation, Root};
+use crate::grid::{AreaState, Blackout, BoundaryAgentTrait, FreqState, Grid, GridState, GridWarning, InfectionState, InfectionStatistics, PowerState, ReservePower, SynchronousArea, NOMINAL_FREQUENCY};
+use crate::model::{GridLayout, Model, PowerColumns, StopRule, StopState};
 // #[cfg(feature = "single_thread")]
 // use crate::norayon::prelude::*;
-use crate::util::{ModelError, Steps};
+use crate::util::{mPercent, BaseInt, ModelError, Steps, UtilError, Watt};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GridInformation {
     pub infection_statistics: InfectionStatistics,
+    pub battery_statistics:   Option<InfectionStatistics>,
+    pub ev_statistics:        Option<InfectionStatistics>,
+    pub heat_pump_statistics: Option<InfectionStatistics>,
     pub freq_state:           FreqState,
     pub power_state:          PowerState,
     pub reserve_power:        ReservePower,
+    pub synchronous_area:     Option<SynchronousArea>,
+    pub fleet_dispatch:       Option<FleetDispatch>,
+    pub line_statistics:      Option<LineStatistics>,
+    pub area_states:          Vec<AreaState>,
+    pub blackout:             Option<Blackout>,
+}
+
+/// The result of a single step of the model.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StepResult {
+    /// The step that was taken.
+    pub step:             Steps,
+    /// The state of the grid after the step.
+    pub grid_information: GridInformation,
+    /// The warnings of the boundaries of the grid, both critical and not.
+    pub warnings:         Vec<GridWarning>,
+    /// How long the step and its substeps took.
+    pub timings:          StepTimings,
+    /// The rule that ends the run after this step, if any. See [StopRule].
+    pub stopped_by:       Option<StopRule>,
+}
+
+/// How long a step and its substeps took.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct StepTimings {
+    /// The substeps in order, with the time each took.
+    pub substeps: Vec<(String, Duration)>,
+    /// The time the whole step took.
+    pub total:    Duration,
+}
+
+impl StepTimings {
+    /// Record that the substep has finished, the next substep starts now.
+    fn record(&mut self, substep: &str, start: &mut Instant) {
+        debug!("Substep {substep}");
+        let now = Instant::now();
+        self.substeps.push((substep.to_string(), now - *start));
+        self.total += now - *start;
+        *start = now;
+    }
 }
 
 impl<'a> Model {
-    /// Take the next step in the model. A step is also called a tick in some
+    /// Take the given number of steps in the model, starting at step 0. A step is also called a tick in some
     /// literature.
+    pub fn step(&'a mut self, num_steps: Steps) -> Result<(), ModelError> { self.run_steps(Steps(0), num_steps) }
 
-    // #[cfg_attr(feature = "multi_thread", tokio::main)]
-    // #[cfg_attr(feature = "single_thread", tokio::main)]
-    #[tokio::main]
-    pub async fn step(&'a mut self, num_steps: Steps) -> Result<(), ModelError> {
-        let mut handles: Vec<JoinHandle<()>> = vec![];
+    /// Take the given number of steps in the model, continuing from the current step. Used to continue a model that
+    /// is restored from a checkpoint.
+    pub fn resume(&'a mut self, num_steps: Steps) -> Result<(), ModelError> {
+        self.run_steps(self.current_step, num_steps)
+    }
 
+    fn run_steps(&'a mut self, first: Steps, num_steps: Steps) -> Result<(), ModelError> {
         info!("Model Param: {}", serde_json::to_string(&self.param).unwrap());
         info!("Grid Param: {}", serde_json::to_string(&self.param.grid).unwrap());
         info!("Attack Param: {}", serde_json::to_string(&self.param.attack).unwrap());
@@ -42,114 +86,422 @@ impl<'a> Model {
         info!("Number of Area agents: {}", self.areas.len());
         info!("Number of Netstation agents: {}", self.netstations.len());
         info!("Number of Household agents: {}", self.households.len());
-
-        for step_inner in 0..num_steps.0 {
-            let step = Steps(step_inner);
-            info!("{} - Taking step {}", self.param.name, step);
-
-            debug!("Substep update step");
-            // Set internal step of agents to current step
-            Self::update_step(&self.agents, step);
-
-            debug!("Substep new powerstate");
-            // New powerstate for Agents with [`PowerGeneration`] units
-            Self::new_power_state(&self.households);
-
-            debug!("Substep attack_and_patch");
-            // Attack and Patch
-            Self::attack_and_patch(&self.households, &mut self.param.attack, step);
-
-            debug!("Substep powerstate from children");
-            // Powerstate from children
-            Self::power_state_from_children(&self.netstations, &self.areas, &self.root);
-
-            // Get states
-            let states: Vec<InfectionState> = self
-                .households
-                .par_iter()
-                .map(|hh| hh.read_arc_recursive().power_generation.infection_state)
-                .collect();
-            // #[cfg(feature = "multi_thread")]
-            let infstats = tokio::spawn(async move { InfectionStatistics::new(&states) });
-            // #[cfg(feature = "single_thread")]
-            // let infstats = InfectionStatistics::new(&states);
-
-            // Grid compensation
-            debug!("Substep grid compensation");
-            Self::grid_frequency_compensation(self);
-
-            debug!("Substep power mismatch impact");
-            // Impact from power mismatch
-            Self::power_mismatch_impact(&self.netstations, &self.root, &self.param.grid);
-
-            debug!("Substep boundary check");
-            // Bounds check
-            if Self::boundary_check(self, &self.netstations, &self.root).is_err() {
-                return Err(ModelError::NeedToStop);
+        info!("Number of Generator agents: {}", self.generators.len());
+        info!("Number of EvCharger agents: {}", self.ev_chargers.len());
+        info!("Number of HeatPump agents: {}", self.heat_pumps.len());
+        info!("Number of Battery agents: {}", self.batteries.len());
+        info!("Number of Connection agents: {}", self.connections.len());
+
+        self.current_step = first;
+        self.stopped_by = None;
+        // A new run starts counting for the stop rules again, a resumed run continues the counts
+        if first == Steps(0) {
+            self.stop_state = StopState::default();
+        }
+        let mut res = Ok(());
+        for _ in 0..num_steps.0 {
+            match self.step_once() {
+                Ok(result) if result.stopped_by.is_some() => break,
+                Ok(_) => (),
+                Err(e) => {
+                    res = Err(e);
+                    break;
+                }
             }
+        }
+        self.layout.store(&self.households, &self.connections);
+        let flushed = self.flush_output();
+        res.and(flushed)
+    }
+
+    /// Take the next step in the model and return its result.
+    ///
+    /// The step does not need an async runtime, so it can be called from synchronous code as well as from within an
+    /// existing runtime, e.g. through `spawn_blocking`. The output of the step is written in the background, call
+    /// [Model::flush_output] to wait until it is written.
+    pub fn step_once(&'a mut self) -> Result<StepResult, ModelError> {
+        let step = self.current_step;
+        info!("{} - Taking step {}", self.param.name, step);
+        // Wait for the output of the previous step, so at most one step is being written
+        self.flush_output()?;
+        self.notify(|o, m| o.on_step_start(m, step));
+        let mut timings = StepTimings::default();
+        let mut start = Instant::now();
+
+        // Events of the scenario
+        self.apply_scenario(step);
+        self.finish_substep(step, "scenario events", &mut timings, &mut start);
+
+        // Restore the grid after a blackout
+        if self.param.grid.restoration.is_some() {
+            Self::restore(self, step);
+        }
+        self.finish_substep(step, "restoration", &mut timings, &mut start);
+
+        // Attack, patch and power of the households
+        self.param.attack.check_current_attack(step);
+        self.layout.step = step;
+        self.run_households(step)?;
+        self.finish_substep(step, "household power", &mut timings, &mut start);
+
+        // Charge the electric vehicles, run the heat pumps and operate the batteries
+        self.run_devices(step)?;
+        self.finish_substep(step, "household devices", &mut timings, &mut start);
+
+        // Dispatch the generation fleet
+        let fleet_dispatch = Self::dispatch_generators(
+            &self.generators,
+            &mut self.layout,
+            self.imbalance,
+            &self.param.grid,
+            step,
+        );
+        self.finish_substep(step, "generator dispatch", &mut timings, &mut start);
+
+        // Powerstate from children
+        self.layout.aggregate();
+        {
+            let mut root = self.root.write_arc();
+            root.update_step(step);
+            self.layout.write_power(root.index, &mut root.powerstate);
+            self.imbalance = root.powerstate.power_error;
+        }
+        let line_statistics = (!self.connections.is_empty()).then(|| self.layout.connections.statistics());
+        self.finish_substep(step, "powerstate from children", &mut timings, &mut start);
+
+        // Get states
+        self.layout.count_infection();
+        let infection = &self.layout.infection;
+        let infection_statistics = InfectionStatistics::new(&infection.households);
+        let battery_statistics =
+            (!infection.batteries.is_empty()).then(|| InfectionStatistics::new(&infection.batteries));
+        let ev_statistics =
+            (!infection.ev_chargers.is_empty()).then(|| InfectionStatistics::new(&infection.ev_chargers));
+        let heat_pump_statistics =
+            (!infection.heat_pumps.is_empty()).then(|| InfectionStatistics::new(&infection.heat_pumps));
+        self.finish_substep(step, "infection statistics", &mut timings, &mut start);
+
+        // Tie-line exchange
+        Self::tie_line_exchange(self);
+        self.finish_substep(step, "tie-line exchange", &mut timings, &mut start);
+
+        // Grid compensation
+        Self::grid_frequency_compensation(self);
+        self.finish_substep(step, "grid compensation", &mut timings, &mut start);
+
+        // Impact from power mismatch
+        Self::power_mismatch_impact(&self.netstations, &self.root, &self.layout, &self.param.grid, step);
+        self.finish_substep(step, "power mismatch impact", &mut timings, &mut start);
+
+        // Bounds check
+        let warnings = Self::boundary_check(&self.netstations, &self.areas, &self.root, &self.layout, step);
+        for warning in &warnings {
+            self.notify(|o, m| o.on_warning(m, step, warning));
+        }
+        Self::black_out(self, step);
+        self.finish_substep(step, "boundary check", &mut timings, &mut start);
+
+        // debug!("Substep update Powerstate History");
+        // Update PowerGeneration history
+        // Self::update_history(&self.agents);
 
-            // debug!("Substep update Powerstate History");
-            // Update PowerGeneration history
-            // Self::update_history(&self.agents);
+        // Output
+        if self.param.enable_output {
+            self.output(step)?;
+            self.finish_substep(step, "model output", &mut timings, &mut start);
+        }
 
-            // Output
-            if self.param.enable_output {
-                debug!("Substep model output");
-                self.output(step, &mut handles)?;
+        let root_info = self.root.read_arc_recursive();
+
+        let grid_information = GridInformation {
+            infection_statistics,
+            battery_statistics,
+            ev_statistics,
+            heat_pump_statistics,
+            freq_state: root_info.freq_state.clone(),
+            power_state: root_info.powerstate.clone(),
+            reserve_power: self.reserve_power,
+            synchronous_area: self.synchronous_area,
+            fleet_dispatch,
+            line_statistics,
+            area_states: self.areas.iter().map(|a| a.read_arc_recursive().area_state).collect(),
+            blackout: self.blackout,
+        };
+        drop(root_info);
+
+        debug!("Grid information - {:?}", &grid_information.infection_statistics);
+        debug!("Grid information - {:?}", grid_information.battery_statistics);
+        debug!("Grid information - {:?}", grid_information.ev_statistics);
+        debug!("Grid information - {:?}", grid_information.heat_pump_statistics);
+        debug!("Grid information - {:?}", &grid_information.freq_state);
+        debug!("Grid information - {:?}", &grid_information.power_state);
+        debug!("Grid information - {:?}", &grid_information.reserve_power);
+        debug!("Grid information - {:?}", grid_information.synchronous_area);
+        debug!("Grid information - {:?}", grid_information.fleet_dispatch);
+        debug!("Grid information - {:?}", grid_information.line_statistics);
+        debug!("Grid information - {:?}", grid_information.area_states);
+        debug!("Grid information - {:?}", grid_information.blackout);
+        info!(
+            "Grid information - {}",
+            &serde_json::to_string(&grid_information).unwrap()
+        );
+
+        // Checkpoint
+        self.current_step = step + Steps(1);
+        if let Some(checkpoint_file) = &self.param.checkpoint_file {
+            if self.param.checkpoint_interval > Steps(0) && self.current_step.0 % self.param.checkpoint_interval.0 == 0
+            {
+                self.save_checkpoint(checkpoint_file)?;
+                self.finish_substep(step, "checkpoint", &mut timings, &mut start);
             }
-            
-            let root_info = self.root.read_arc_recursive();
-            
-            let grid_information = GridInformation {
-                // #[cfg(feature = "multi_thread")]
-                infection_statistics:                                   infstats.await?,
-                // #[cfg(feature = "single_thread")]
-                // infection_statistics:                                   infstats,
-                freq_state:                                             root_info.freq_state.clone(),
-                power_state:                                            root_info.powerstate.clone(),
-                reserve_power:                                          self.reserve_power,
-            };
-            
-            debug!("Grid information - {:?}", &grid_information.infection_statistics);
-            debug!("Grid information - {:?}", &grid_information.freq_state);
-            debug!("Grid information - {:?}", &grid_information.power_state);
-            debug!("Grid information - {:?}", &grid_information.reserve_power);
-            info!(
-                "Grid information - {}",
-                &serde_json::to_string(&grid_information).unwrap()
-            );
         }
-        for handle in handles {
-            handle.await.unwrap();
+
+        let mut result = StepResult {
+            step,
+            grid_information,
+            warnings,
+            timings,
+            stopped_by: None,
+        };
+        result.stopped_by = self.stop_state.check(&self.param.stop_rules, &result);
+        if let Some(rule) = result.stopped_by {
+            info!("{} - Stopping after step {step}, {rule:?} is met", self.param.name);
+            self.stopped_by = Some(rule);
+        }
+        self.notify(|o, m| o.on_step_end(m, &result));
+        Ok(result)
+    }
+
+    /// Record the time the substep took and notify the observers. The time the observers take is not recorded.
+    fn finish_substep(&mut self, step: Steps, substep: &str, timings: &mut StepTimings, start: &mut Instant) {
+        timings.record(substep, start);
+        self.notify(|o, m| o.on_substep(m, step, substep));
+        *start = Instant::now();
+    }
+
+    /// Wait until the output of the steps taken is written. Returns the first error of writing the output, if any.
+    pub fn flush_output(&mut self) -> Result<(), ModelError> {
+        let mut res = Ok(());
+        for handle in self.output_handles.drain(..) {
+            let written = handle.join().unwrap_or_else(|_| {
+                Err(ModelError::OutputError(
+                    "The thread writing the output panicked".to_string(),
+                ))
+            });
+            res = res.and(written);
         }
+        res
+    }
 
+    /// Run the households: the [PowerGeneration](crate::grid::PowerGeneration) unit is patched or infected, its power
+    /// is changed by the attack if infected and the PV installation reconnects or disconnects. Each household is
+    /// locked once, its power, supply and infection state are kept in the layout.
+    fn run_households(&mut self, step: Steps) -> Result<(), ModelError> {
+        let frequency_allows =
+            self.param.grid.restoration.as_ref().map(|restoration| {
+                restoration.frequency_allows_reconnect(self.root.read_arc_recursive().freq_state.now)
+            });
+        let attack = &self.param.attack;
+        let households: Vec<([Watt; 5], bool, InfectionState)> = self
+            .households
+            .par_iter()
+            .map(|h| {
+                let mut h = h.write_arc();
+                h.update_step(step);
+                h.clean_power_gen()?;
+                attack.try_to_patch_and_infect(&mut h, step);
+                attack.modify_infected_device(&mut h);
+                if let Some(frequency_allows) = frequency_allows {
+                    attack.reconnect_pv(&mut h, step, frequency_allows);
+                }
+                if !h.supply.pv_connected || !h.supply.pv_clean_connected {
+                    h.disconnect_pv();
+                }
+                Ok((
+                    PowerColumns::fields(&h.powerstate),
+                    h.supply.energised,
+                    h.power_generation.infection_state,
+                ))
+            })
+            .collect::<Result<_, UtilError>>()?;
+        self.layout.households = PowerColumns::from_fields(households.iter().map(|(power, ..)| *power));
+        self.layout.supplied = households.iter().map(|(_, supplied, _)| *supplied).collect();
+        self.layout.infection.households = households.iter().map(|(.., state)| *state).collect();
         Ok(())
     }
 
-    /// Update step
-    pub fn update_step(agents: &AgentList, step: Steps) {
-        agents.par_iter().for_each(|a| a.write_arc().update_step(step));
+    /// Run the devices of the households: the vehicles that departed draw their next visit and charge, the heat pumps
+    /// run and the batteries operate on the net consumption of their household. Infected devices follow the command
+    /// of the attack, if there is one. Each device is locked once, its power is added to its household in the layout.
+    fn run_devices(&mut self, step: Steps) -> Result<(), ModelError> {
+        let attack = &self.param.attack;
+        let ev_command = attack.ev_command();
+        let ev_param = self.param.grid.ev_charger.as_ref();
+        let streams = &self.param.streams;
+        let ev_chargers: Vec<([Watt; 5], InfectionState)> = self
+            .ev_chargers
+            .par_iter()
+            .map(|e| {
+                let mut e = e.write_arc();
+                e.update_step(step);
+                attack.try_to_patch_and_infect_ev_charger(&mut e, step);
+                if let Some(ev_param) = ev_param {
+                    e.plan_visit(ev_param, streams)?;
+                }
+                e.charge(ev_command);
+                Ok((PowerColumns::fields(&e.powerstate), e.infection_state))
+            })
+            .collect::<Result<_, UtilError>>()?;
+
+        let heat_pump_command = attack.heat_pump_command();
+        let outdoor_temperature = self.param.grid.outdoor_temperature.temperature(&step);
+        let heat_pumps: Vec<([Watt; 5], InfectionState)> = self
+            .heat_pumps
+            .par_iter()
+            .map(|h| {
+                let mut h = h.write_arc();
+                h.update_step(step);
+                attack.try_to_patch_and_infect_heat_pump(&mut h, step);
+                h.run(outdoor_temperature, heat_pump_command);
+                (PowerColumns::fields(&h.powerstate), h.infection_state)
+            })
+            .collect();
+
+        let layout = &mut self.layout;
+        let power: Vec<[Watt; 5]> = ev_chargers.iter().map(|(power, _)| *power).collect();
+        layout.households.add_rows(&power, &layout.owners.ev_chargers);
+        layout.infection.ev_chargers = ev_chargers.iter().map(|(_, state)| *state).collect();
+        let power: Vec<[Watt; 5]> = heat_pumps.iter().map(|(power, _)| *power).collect();
+        layout.households.add_rows(&power, &layout.owners.heat_pumps);
+        layout.infection.heat_pumps = heat_pumps.iter().map(|(_, state)| *state).collect();
+
+        // The batteries after the other devices, as they follow the net consumption of the household
+        let battery_command = attack.battery_command();
+        let households = &layout.households;
+        let batteries: Vec<([Watt; 5], InfectionState)> = self
+            .batteries
+            .par_iter()
+            .zip(layout.owners.batteries.par_iter())
+            .map(|(b, owner)| {
+                let mut b = b.write_arc();
+                b.update_step(step);
+                attack.try_to_patch_and_infect_battery(&mut b, step);
+                let net_consumption =
+                    owner.map_or(Watt(0), |h| households.power_used[h] - households.power_generated[h]);
+                b.operate(net_consumption, battery_command);
+                (PowerColumns::fields(&b.powerstate), b.infection_state)
+            })
+            .collect();
+        let power: Vec<[Watt; 5]> = batteries.iter().map(|(power, _)| *power).collect();
+        layout.households.add_rows(&power, &layout.owners.batteries);
+        layout.infection.batteries = batteries.iter().map(|(_, state)| *state).collect();
+        Ok(())
     }
 
-    // New powerstate for [`PowerGeneration`] units
-    #[inline]
-    fn new_power_state(hh: &Vec<Arc<RwLock<Household>>>) {
-        // only households
-        hh.par_iter().for_each(|a| {
-            a.write_arc().clean_power_gen();
-        });
+    /// Restore the grid after a blackout. After the black start time, the netstations are energised in the order of
+    /// the restoration, within the load pickup limit. The load of a netstation is that of its households in the last
+    /// step. The PV installations reconnect when the households run, if allowed.
+    fn restore(&mut self, step: Steps) {
+        let Some(restoration) = &self.param.grid.restoration else {
+            return;
+        };
+        if self.blackout.is_some_and(|b| b.recovery_time.is_some()) {
+            self.blackout = None;
+        }
+        if let Some(blackout) = self.blackout.as_mut() {
+            if step >= blackout.start + restoration.black_start_time {
+                let mut picked_up = Watt(0);
+                let mut num_energised = 0;
+                'restoration: for area_index in restoration.order(self.areas.len()) {
+                    let mut area = self.areas[area_index].write_arc();
+                    for ns in &area.netstations {
+                        let mut ns = ns.write_arc();
+                        if ns.energised {
+                            continue;
+                        }
+                        let load = Watt(self.layout.load(ns.index).0.abs());
+                        if num_energised > 0 && picked_up + load > restoration.load_pickup_limit {
+                            break 'restoration;
+                        }
+                        picked_up += load;
+                        num_energised += 1;
+                        ns.energise(step, restoration.pv_reconnect_delay);
+                    }
+                    area.energised = true;
+                }
+                debug!("Restoration - energised {num_energised} netstations with a load of {picked_up}");
+
+                let energised = self
+                    .netstations
+                    .iter()
+                    .filter(|ns| ns.read_arc_recursive().energised)
+                    .count() as BaseInt;
+                blackout.restored = mPercent::share(energised, self.netstations.len() as BaseInt);
+                if energised == self.netstations.len() as BaseInt {
+                    blackout.recovery_time = Some(step - blackout.start);
+                    info!("Restoration finished after {} steps", step - blackout.start);
+                }
+            }
+        }
+    }
+
+    /// A critical frequency error causes a blackout if the grid can be restored. All netstations are de-energised and
+    /// the black start brings the frequency back to nominal.
+    fn black_out(&mut self, step: Steps) {
+        if self.param.grid.restoration.is_none() {
+            return;
+        }
+        let mut root = self.root.write_arc();
+        if !root.freq_boundary.is_critical() {
+            return;
+        }
+        error!("Blackout at step {step}");
+        root.freq_state.update(NOMINAL_FREQUENCY);
+        root.freq_boundary.reset();
+        self.areas.iter().for_each(|a| a.write_arc().energised = false);
+        self.netstations.par_iter().for_each(|ns| ns.write_arc().de_energise());
+        self.blackout = Some(Blackout::new(step));
     }
 
+    /// Dispatch the generation fleet in merit order. The fleet is scheduled to follow the forecasted demand, the
+    /// reported demand of the households together with the bulk consumption. On top of the schedule, the fleet
+    /// balances the power mismatch measured in the previous step that was not yet balanced by the fleet itself.
     #[inline]
-    fn attack_and_patch(hh: &Vec<Arc<RwLock<Household>>>, attack: &mut Attack, step: Steps) {
-        // Check on current attack
-        attack.check_current_attack(step);
-        // only households
-        // Patching and infecting
-        attack.try_to_patch_and_infect(hh);
-
-        // Attacking
-        attack.modify_infected_devices(hh);
+    fn dispatch_generators(
+        generators: &GeneratorList,
+        layout: &mut GridLayout,
+        imbalance: Watt,
+        grid_param: &Grid,
+        step: Steps,
+    ) -> Option<FleetDispatch> {
+        if generators.is_empty() {
+            return None;
+        }
+        let forecast = layout.forecast() + grid_param.bulk_consumption;
+        // The measured mismatch includes the output of the fleet beyond its schedule
+        let delivered = layout.generators.power_error.iter().copied().sum::<Watt>();
+        let balancing = delivered - imbalance;
+
+        let mut dispatch = FleetDispatch {
+            forecast,
+            balancing,
+            ..Default::default()
+        };
+        let mut remaining_forecast = forecast;
+        let mut remaining = forecast + balancing;
+        let mut power = Vec::with_capacity(generators.len());
+        for generator in generators {
+            let mut g = generator.write_arc();
+            g.update_step(step);
+            remaining_forecast -= g.schedule(remaining_forecast);
+            remaining -= g.dispatch(remaining);
+            g.ramp();
+            dispatch.setpoint += g.setpoint;
+            dispatch.output += g.output;
+            power.push(PowerColumns::fields(&g.powerstate));
+        }
+        layout.generators = PowerColumns::from_fields(power);
+        Some(dispatch)
     }
 
     /// Create a vector that copies the Arc of the agents with the desired AgentKind.
@@ -163,25 +515,13 @@ impl<'a> Model {
     }
 
     #[inline]
-    fn power_state_from_children(
-        ns: &Vec<Arc<RwLock<Netstation>>>,
-        area: &Vec<Arc<RwLock<Area>>>,
+    fn power_mismatch_impact(
+        ns: &[Arc<RwLock<Netstation>>],
         root: &Arc<RwLock<Root>>,
+        layout: &GridLayout,
+        grid_param: &Grid,
+        step: Steps,
     ) {
-        // ns
-        ns.par_iter()
-            .for_each(|agent| agent.write_arc().calc_power_from_child());
-
-        // area
-        area.par_iter()
-            .for_each(|agent| agent.write_arc().calc_power_from_child());
-
-        // root
-        root.write_arc().calc_power_from_child();
-    }
-
-    #[inline]
-    fn power_mismatch_impact(ns: &[Arc<RwLock<Netstation>>], root: &Arc<RwLock<Root>>, grid_param: &Grid) {
         // Frequency
         let power_total = root.read_arc_recursive().powerstate.power_used;
         let power_error = root.read_arc_recursive().powerstate.power_error;
@@ -195,6 +535,8 @@ impl<'a> Model {
         // Voltage
         ns.par_iter().for_each(|net| {
             let mut n = net.write_arc();
+            n.update_step(step);
+            layout.write_power(n.index, &mut n.powerstate);
             let power_total = n.powerstate.power_used;
             let power_error = n.powerstate.power_error;
             let new_volt = n
@@ -204,6 +546,20 @@ impl<'a> Model {
         });
     }
 
+    /// Exchange power with the synchronous area over the tie-lines, based on the current frequency of the grid.
+    #[inline]
+    pub fn tie_line_exchange(&mut self) {
+        if let Some(synchronous_area) = &mut self.synchronous_area {
+            let mut root = self.root.write_arc();
+            let exchange = synchronous_area.exchange(root.freq_state.now, root.powerstate.power_error);
+            debug!(
+                "Exchange with synchronous area - {}",
+                serde_json::to_string(&exchange).unwrap()
+            );
+            root.powerstate_mut().power_error -= exchange;
+        }
+    }
+
     /// Try to compensate for the power mismatch on the [Root] node.
     #[inline]
     pub fn grid_frequency_compensation(&mut self) {
@@ -216,21 +572,27 @@ impl<'a> Model {
     #[inline]
 
     fn boundary_check(
-        model: &Self,
         ns: &Vec<Arc<RwLock<Netstation>>>,
+        area: &Vec<Arc<RwLock<Area>>>,
         root: &Arc<RwLock<Root>>,
-    ) -> Result<(), ModelError> {
+        layout: &GridLayout,
+        step: Steps,
+    ) -> Vec<GridWarning> {
         let frequency_warnings: Option<GridWarning> = root.write_arc().boundary_check();
-        let need_to_stop = frequency_warnings.is_some();
-        if let Some(fw) = frequency_warnings {
+        if let Some(fw) = &frequency_warnings {
             if fw.critical {
-                error!("Frequency error - {}", serde_json::to_string(&fw).unwrap());
+                error!("Frequency error - {}", serde_json::to_string(fw).unwrap());
             } else {
-                warn!("Frequency warning - {}", serde_json::to_string(&fw).unwrap());
+                warn!("Frequency warning - {}", serde_json::to_string(fw).unwrap());
             }
         }
 
-        let voltage_warnings = ns.par_iter().filter_map(|a| a.write_arc().boundary_check());
+        let voltage_warnings = ns.par_iter().filter_map(|a| {
+            let mut a = a.write_arc();
+            let mut gw = a.boundary_check()?;
+            gw.infectionstatistics = Some(layout.infection(a.index).into());
+            Some(gw)
+        });
         // #[cfg(feature = "single_thread")]
         // let (v_errors, v_warnings): (Vec<GridWarning>, Vec<GridWarning>) = voltage_warnings.partition(|x| x.critical);
         // #[cfg(feature = "multi_thread")]
@@ -245,11 +607,35 @@ impl<'a> Model {
             warn!("Voltage warning - {}", serde_json::to_string(&v_warnings).unwrap());
         }
 
-        if model.param.stop_on_freq_error && need_to_stop {
-            Err(ModelError::NeedToStop)
-        } else {
-            Ok(())
+        // Areas after the netstations, as they depend on their voltage state
+        let area_warnings = area.par_iter().filter_map(|a| {
+            let mut a = a.write_arc();
+            a.update_step(step);
+            layout.write_power(a.index, &mut a.powerstate);
+            let infection = layout.infection(a.index);
+            a.update_area_state(infection);
+            let mut gw = a.boundary_check()?;
+            gw.infectionstatistics = Some(infection.into());
+            Some(gw)
+        });
+        let (a_errors, a_warnings): (Vec<GridWarning>, Vec<GridWarning>) =
+            area_warnings.partition_map(|x| if x.critical { Either::Left(x) } else { Either::Right(x) });
+
+        if !a_errors.is_empty() {
+            error!("Area error - {}", serde_json::to_string(&a_errors).unwrap());
         }
+
+        if !a_warnings.is_empty() {
+            warn!("Area warning - {}", serde_json::to_string(&a_warnings).unwrap());
+        }
+
+        frequency_warnings
+            .into_iter()
+            .chain(v_errors)
+            .chain(v_warnings)
+            .chain(a_errors)
+            .chain(a_warnings)
+            .collect()
     }
 
     /// Update the history of the agents given.
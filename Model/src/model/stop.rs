@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+#[allow(unused_imports)]
+use crate::agent::Netstation;
+use crate::model::StepResult;
+use crate::util::{mHz, mPercent, Steps};
+
+/// A rule that ends the run of the [Model](crate::model::Model) early, checked at the end of each step. The step that
+/// meets the rule is completed, including its output.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopRule {
+    /// Any frequency warning, critical or not.
+    FrequencyWarning,
+    /// Critical voltage errors at the given number of [Netstation] agents or more in a single step.
+    VoltageErrors(usize),
+    /// The reserve power is used up to one of its limits for the given number of consecutive steps.
+    ReserveExhausted(Steps),
+    /// The share of infected power generation devices reaches the given share.
+    InfectionShare(mPercent),
+    /// The frequency changes no more than the tolerance between steps, for the given number of consecutive steps.
+    SteadyState {
+        /// Maximum change of the frequency in a single step.
+        tolerance: mHz,
+        /// Number of consecutive steps.
+        steps:     Steps,
+    },
+}
+
+/// What the stop rules need to remember between steps.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StopState {
+    /// Number of consecutive steps the condition of each rule holds, for the rules that need it.
+    pub counters:       Vec<Steps>,
+    /// The frequency at the end of the previous step.
+    pub last_frequency: Option<mHz>,
+}
+
+impl StopState {
+    /// Update the state with the result of the step and give the first of the rules that is met, if any.
+    pub fn check(&mut self, rules: &[StopRule], result: &StepResult) -> Option<StopRule> {
+        let info = &result.grid_information;
+        let reserve = info.reserve_power;
+        let exhausted = reserve.current_usage == reserve.upper_limit || reserve.current_usage == reserve.lower_limit;
+        let frequency = info.freq_state.now;
+        let change = self.last_frequency.map(|last| mHz((frequency.0 - last.0).abs()));
+        self.last_frequency = Some(frequency);
+
+        self.counters.resize(rules.len(), Steps(0));
+        for (rule, counter) in rules.iter().zip(&mut self.counters) {
+            let holds = match rule {
+                StopRule::ReserveExhausted(_) => exhausted,
+                StopRule::SteadyState { tolerance, .. } => change.is_some_and(|c| c <= *tolerance),
+                _ => continue,
+            };
+            *counter = if holds { *counter + Steps(1) } else { Steps(0) };
+        }
+
+        rules
+            .iter()
+            .zip(&self.counters)
+            .find(|(rule, counter)| match rule {
+                StopRule::FrequencyWarning => result.warnings.iter().any(|w| w.freq_state.is_some()),
+                StopRule::VoltageErrors(num) => {
+                    result
+                        .warnings
+                        .iter()
+                        .filter(|w| w.critical && w.volt_state.is_some())
+                        .count()
+                        >= *num
+                }
+                StopRule::ReserveExhausted(steps) | StopRule::SteadyState { steps, .. } => *counter >= steps,
+                StopRule::InfectionShare(share) => {
+                    let statistics = &info.infection_statistics;
+                    mPercent::share(statistics.num_infected, statistics.total) >= *share
+                }
+            })
+            .map(|(rule, _)| *rule)
+    }
+}
+
+#[cfg(test)]
+mod stop_tests {
+    use super::*;
+    use crate::model::{Model, ModelParameters};
+
+    #[test]
+    fn run_ends_on_rule() {
+        let mut param = ModelParameters::test();
+        let steady = StopRule::SteadyState {
+            tolerance: mHz(1_000_000),
+            steps:     Steps(3),
+        };
+        param.stop_rules = vec![StopRule::InfectionShare(mPercent(100_000)), steady];
+        let mut model = Model::new(param).unwrap();
+        model.step(Steps(50)).expect("Error in taking steps");
+
+        // The first step has no change of the frequency, the rule holds from the second step on
+        assert_eq!(model.stopped_by, Some(steady));
+        assert_eq!(model.current_step, Steps(4));
+        assert_eq!(model.stop_state.counters, vec![Steps(0), Steps(3)]);
+
+        // A new run counts from the start again
+        model.step(Steps(50)).expect("Error in taking steps");
+        assert_eq!(model.current_step, Steps(4));
+    }
+}
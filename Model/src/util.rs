@@ -6,12 +6,14 @@ mod configparser;
 mod errors;
 mod logging;
 mod output;
+mod rng;
 mod stats;
 mod types;
 
 pub use errors::*;
 pub use logging::*;
 pub use output::*;
+pub use rng::*;
 pub use stats::*;
 pub use types::*;
 
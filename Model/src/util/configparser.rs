@@ -1,8 +1,12 @@
 mod attackparameters;
 mod gridparameters;
 mod modelparameters;
+mod scenarioparameters;
 
 use std::{fmt::{Debug, Display}};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
 use std::str::FromStr;
 
 pub use attackparameters::*;
@@ -10,7 +14,7 @@ use configparser::ini::Ini;
 pub use gridparameters::*;
 pub use modelparameters::*;
 
-use crate::util::ConfigError;
+use crate::util::{ConfigError, UtilError};
 
 pub fn subparse<T>(key: &str, config: &Ini, variant: &str) -> Result<T, ConfigError>
 where
@@ -34,6 +38,18 @@ where
     )
 }
 
+pub fn subparse_or<T>(key: &str, config: &Ini, variant: &str, default: T) -> Result<T, ConfigError>
+where
+    T: FromStr + Debug,
+    <T as FromStr>::Err: Display,
+{
+    if config.get(variant, key).is_some() {
+        subparse(key, config, variant)
+    } else {
+        Ok(default)
+    }
+}
+
 pub fn gen_vec_tuples_string<T>(s: &str) -> Result<Vec<(T, T)>, ConfigError>
 where
     T: FromStr,
@@ -61,6 +77,73 @@ where
     Ok(res)
 }
 
+pub fn gen_vec_lists<T>(s: &str) -> Result<Vec<Vec<T>>, ConfigError>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Debug + Display,
+{
+    let trim: &[_] = &['[', ']', '(', ')'];
+    let s1 = s.trim_matches(trim);
+    let s2 = s1.replace(' ', "");
+    if s2.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut res = vec![];
+    for list in s2.split("),(") {
+        let mut parsed = vec![];
+        for element in list.split(',') {
+            match element.parse::<T>() {
+                Ok(v) => parsed.push(v),
+                Err(e) => return Err(ConfigError::ParsingError(format!("{element:?} - {:?}", e.to_string()))),
+            }
+        }
+        res.push(parsed);
+    }
+    Ok(res)
+}
+
+/// Reads a whole file to a string.
+pub fn read_file(filepath: impl AsRef<Path>) -> Result<String, ConfigError> {
+    let mut file = BufReader::new(match File::open(filepath) {
+        Ok(f) => f,
+        Err(e) => return Err(UtilError::FileSystemError(e.to_string()).into()),
+    });
+    let mut content = String::new();
+    match file.read_to_string(&mut content) {
+        Ok(_) => Ok(content),
+        Err(e) => Err(UtilError::IOError { source: e }.into()),
+    }
+}
+
+/// Splits a CSV file in its header and rows. The delimiter is a semicolon if the header contains one, with a decimal
+/// comma in the values, and a comma otherwise.
+pub fn gen_csv_table(s: &str) -> Result<(Vec<String>, Vec<Vec<String>>), ConfigError> {
+    let mut lines = s.lines().filter(|l| !l.trim().is_empty());
+    let Some(header) = lines.next() else {
+        return Err(ConfigError::ParsingError("The CSV file is empty".to_string()));
+    };
+    let delimiter = if header.contains(';') { ';' } else { ',' };
+    let header = header
+        .split(delimiter)
+        .map(|c| c.trim().trim_matches('"').to_string())
+        .collect();
+    let rows = lines
+        .map(|l| {
+            l.split(delimiter)
+                .map(|v| {
+                    let v = v.trim().trim_matches('"');
+                    if delimiter == ';' {
+                        v.replace(',', ".")
+                    } else {
+                        v.to_string()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    Ok((header, rows))
+}
+
 pub fn gen_vec_attack<T, L>(s: &str) -> Result<Vec<(T, T, L, L)>, ConfigError>
 where
     T: FromStr,
@@ -1,17 +1,15 @@
 use std::path::Path;
-use std::sync::Arc;
 
 use configparser::ini::Ini;
-use parking_lot::RwLock;
-use rand::rngs::SmallRng;
-use rand::SeedableRng;
 
-use crate::attack::{Attack, AttackBehaviour};
-use crate::util::{gen_vec_attack, subparse, BaseFloat, BaseInt, ConfigError, Steps};
+use crate::agent::{EvCommand, HeatPumpCommand};
+use crate::attack::{Attack, AttackBehaviour, BatteryAttack, EvAttack, HeatPumpAttack, ReconnectAttack};
+use crate::agent::BatteryCommand;
+use crate::util::{gen_vec_attack, gen_vec_lists, subparse, subparse_or, BaseFloat, BaseInt, ConfigError, RngStreams, Steps};
 
 impl Attack {
-    /// Create an [Attack] struct of the given filepath. Allows for an override for the variant if desired. It creates a
-    /// new RNG seed from the RNG seed given.
+    /// Create an [Attack] struct of the given filepath. Allows for an override for the variant if desired. It derives
+    /// its streams of random numbers from the RNG seed given.
     pub fn from_config(filepath: impl AsRef<Path>, variant: &str, rngseed: u64) -> Result<Self, ConfigError> {
         let mut config = Ini::new();
         match config.load(&filepath) {
@@ -38,6 +36,10 @@ impl Attack {
                 generation_modifier,
             })
             .collect();
+        let battery_behaviour = Self::battery_behaviour_from_config(&config, variant)?;
+        let ev_behaviour = Self::ev_behaviour_from_config(&config, variant)?;
+        let heat_pump_behaviour = Self::heat_pump_behaviour_from_config(&config, variant)?;
+        let reconnect_behaviour = Self::reconnect_behaviour_from_config(&config, variant)?;
         Ok(Self {
             infection_rate_per_step,
             patch_rate_per_step,
@@ -46,30 +48,161 @@ impl Attack {
             patch_start,
             patch_stop,
             attack_behaviour,
+            battery_behaviour,
+            ev_behaviour,
+            heat_pump_behaviour,
+            reconnect_behaviour,
             percentage_vuln_devices,
-            seed: Arc::new(RwLock::new(SmallRng::seed_from_u64(rngseed))),
+            streams: RngStreams::new(rngseed),
             current_attack: None,
+            current_battery_attack: None,
+            current_ev_attack: None,
+            current_heat_pump_attack: None,
+            current_reconnect_attack: None,
         })
     }
 
+    /// Parses the commands given to infected batteries. Given as (begin, end, command), where a command of 1 is a full
+    /// charge and -1 a full discharge.
+    fn battery_behaviour_from_config(config: &Ini, variant: &str) -> Result<Vec<BatteryAttack>, ConfigError> {
+        let battery_behaviour_out: Vec<Vec<BaseInt>> =
+            gen_vec_lists::<BaseInt>(&subparse_or("battery_behaviour", config, variant, String::new())?)?;
+        let mut battery_behaviour = vec![];
+        for behaviour in battery_behaviour_out {
+            if behaviour.len() < 3 {
+                return Err(ConfigError::NumElementsTooLow(format!(
+                    "Number of elements is too low. Expected 3, got {}",
+                    behaviour.len()
+                )));
+            }
+            let command = match behaviour[2] {
+                1 => BatteryCommand::FullCharge,
+                -1 => BatteryCommand::FullDischarge,
+                c => {
+                    return Err(ConfigError::NotPermittedOption(format!(
+                        "Battery command {c} is not permitted, use 1 to charge or -1 to discharge"
+                    )))
+                }
+            };
+            battery_behaviour.push(BatteryAttack {
+                begin: Steps(behaviour[0]),
+                end: Steps(behaviour[1]),
+                command,
+            });
+        }
+        Ok(battery_behaviour)
+    }
+
+    /// Parses the commands given to infected EV chargers. Given as (begin, end, command), where a command of 1 starts
+    /// and 0 stops the charging.
+    fn ev_behaviour_from_config(config: &Ini, variant: &str) -> Result<Vec<EvAttack>, ConfigError> {
+        let ev_behaviour_out: Vec<Vec<BaseInt>> =
+            gen_vec_lists::<BaseInt>(&subparse_or("ev_behaviour", config, variant, String::new())?)?;
+        let mut ev_behaviour = vec![];
+        for behaviour in ev_behaviour_out {
+            if behaviour.len() < 3 {
+                return Err(ConfigError::NumElementsTooLow(format!(
+                    "Number of elements is too low. Expected 3, got {}",
+                    behaviour.len()
+                )));
+            }
+            let command = match behaviour[2] {
+                1 => EvCommand::StartCharging,
+                0 => EvCommand::StopCharging,
+                c => {
+                    return Err(ConfigError::NotPermittedOption(format!(
+                        "EV command {c} is not permitted, use 1 to start or 0 to stop charging"
+                    )))
+                }
+            };
+            ev_behaviour.push(EvAttack {
+                begin: Steps(behaviour[0]),
+                end: Steps(behaviour[1]),
+                command,
+            });
+        }
+        Ok(ev_behaviour)
+    }
+
+    /// Parses the periods in which infected PV inverters ignore the reconnection rules. Given as (begin, end).
+    fn reconnect_behaviour_from_config(config: &Ini, variant: &str) -> Result<Vec<ReconnectAttack>, ConfigError> {
+        let reconnect_behaviour_out: Vec<Vec<BaseInt>> =
+            gen_vec_lists::<BaseInt>(&subparse_or("reconnect_behaviour", config, variant, String::new())?)?;
+        let mut reconnect_behaviour = vec![];
+        for behaviour in reconnect_behaviour_out {
+            if behaviour.len() < 2 {
+                return Err(ConfigError::NumElementsTooLow(format!(
+                    "Number of elements is too low. Expected 2, got {}",
+                    behaviour.len()
+                )));
+            }
+            reconnect_behaviour.push(ReconnectAttack {
+                begin: Steps(behaviour[0]),
+                end:   Steps(behaviour[1]),
+            });
+        }
+        Ok(reconnect_behaviour)
+    }
+
+    /// Parses the commands given to infected heat pumps. Given as (begin, end, command, setpoint), where a command of 0
+    /// changes the setpoint, 1 forces the heat pump on and -1 forces it off. The setpoint is only used by command 0.
+    fn heat_pump_behaviour_from_config(config: &Ini, variant: &str) -> Result<Vec<HeatPumpAttack>, ConfigError> {
+        let heat_pump_behaviour_out: Vec<Vec<BaseFloat>> =
+            gen_vec_lists::<BaseFloat>(&subparse_or("heat_pump_behaviour", config, variant, String::new())?)?;
+        let mut heat_pump_behaviour = vec![];
+        for behaviour in heat_pump_behaviour_out {
+            if behaviour.len() < 4 {
+                return Err(ConfigError::NumElementsTooLow(format!(
+                    "Number of elements is too low. Expected 4, got {}",
+                    behaviour.len()
+                )));
+            }
+            let command = match behaviour[2] as BaseInt {
+                0 => HeatPumpCommand::Setpoint(behaviour[3]),
+                1 => HeatPumpCommand::ForceOn,
+                -1 => HeatPumpCommand::ForceOff,
+                c => {
+                    return Err(ConfigError::NotPermittedOption(format!(
+                        "Heat pump command {c} is not permitted, use 0 for a setpoint, 1 to force on or -1 to force \
+                         off"
+                    )))
+                }
+            };
+            heat_pump_behaviour.push(HeatPumpAttack {
+                begin: Steps(behaviour[0] as BaseInt),
+                end: Steps(behaviour[1] as BaseInt),
+                command,
+            });
+        }
+        Ok(heat_pump_behaviour)
+    }
+
     /// Creates a test version to be used for testing within the crate.
     pub(crate) fn test() -> Self {
         Self {
-            infection_rate_per_step: 0.002,
-            patch_rate_per_step:     0.002,
-            infection_start:         Steps(100),
-            infection_stop:          Steps(1000),
-            patch_start:             Steps(800),
-            patch_stop:              Steps(60000),
-            attack_behaviour:        vec![AttackBehaviour {
+            infection_rate_per_step:  0.002,
+            patch_rate_per_step:      0.002,
+            infection_start:          Steps(100),
+            infection_stop:           Steps(1000),
+            patch_start:              Steps(800),
+            patch_stop:               Steps(60000),
+            attack_behaviour:         vec![AttackBehaviour {
                 begin:               Steps(10),
                 end:                 Steps(100),
                 report_modifier:     2.0,
                 generation_modifier: 0.5,
             }],
-            percentage_vuln_devices: 0.5,
-            seed:                    Arc::new(RwLock::new(SmallRng::seed_from_u64(2010))),
-            current_attack:          None,
+            battery_behaviour:        vec![],
+            ev_behaviour:             vec![],
+            heat_pump_behaviour:      vec![],
+            reconnect_behaviour:      vec![],
+            percentage_vuln_devices:  0.5,
+            streams:                  RngStreams::new(2010),
+            current_attack:           None,
+            current_battery_attack:   None,
+            current_ev_attack:        None,
+            current_heat_pump_attack: None,
+            current_reconnect_attack: None,
         }
     }
 }
@@ -4,8 +4,9 @@ use std::path::Path;
 
 use configparser::ini::Ini;
 
-use crate::grid::{Boundaries, BoundaryBand, BoundaryUnitTrait, Grid, GridBoundaryState, NormalBand};
-use crate::util::{gen_vec_tuples_string, mHz, mVolt, subparse, BaseFloat, BaseInt, ConfigError, Minutes, Steps, UtilError, Watt};
+use crate::agent::{EvChargerParam, GeneratorParam, GeneratorTrip, HeatPumpParam};
+use crate::grid::{Appliance, ApplianceParam, Boundaries, BoundaryBand, BoundaryUnitTrait, Grid, GridBoundaryState, MeterData, NoiseModel, NoiseParam, NormalBand, OutdoorTemperature, RateLimit, RestorationParam, StandardProfiles, SynchronousArea, Topology};
+use crate::util::{gen_vec_lists, gen_vec_tuples_string, mHz, mPercent, mVolt, subparse, subparse_or, BaseFloat, BaseInt, ConfigError, Minutes, Steps, UtilError, Watt, WattHour};
 
 impl Grid {
     /// Creates a test version to be used for testing within the crate.
@@ -14,16 +15,38 @@ impl Grid {
             n_areas:                                  1,
             energy_storage:                           Watt(10_000),
             max_gen_inc_tick:                         Watt(100),
+            topology:                                 None,
             ns_per_a:                                 (2, 3),
             hs_per_ns:                                (2, 3),
             pv_adoption:                              0.5,
+            battery_adoption:                         0.0,
+            battery_capacity:                         WattHour(10_000),
+            battery_power:                            (Watt(3_000), Watt(3_000)),
+            ns_line_capacity:                         Watt(0),
+            area_line_capacity:                       Watt(0),
+            line_loss_factor:                         0.0,
+            ns_link_density:                          0.0,
+            ns_link_capacity:                         Watt(0),
+            area_link_density:                        0.0,
+            area_link_capacity:                       Watt(0),
+            ev_charger:                               None,
+            heat_pump:                                None,
+            restoration:                              None,
+            outdoor_temperature:                      OutdoorTemperature::default(),
             percentage_noise_on_power:                0.1,
             num_noise_functions:                      3,
+            noise:                                    None,
+            profiles:                                 None,
+            replay:                                   None,
+            appliances:                               None,
             household_power_consumption_distribution: (Watt(10_000), Watt(100_000)),
             bulk_consumption:                         Watt(10_000),
+            generators:                               vec![],
             volt_modifier:                            1.0,
+            synchronous_area:                         None,
             volt_boundary:                            Boundaries::<mVolt>::default(),
             freq_boundary:                            Boundaries::<mHz>::default(),
+            area_boundary:                            Boundaries::<mPercent>::default(),
             percentage_generation_of_usage:           0.2,
         }
     }
@@ -37,9 +60,21 @@ impl Grid {
         };
         let volt_modifier: BaseFloat = subparse("attack_modifier", &config, "voltage")?;
 
-        let n_areas: BaseInt = subparse("n_areas", &config, variant)?;
+        let topology = Self::topology_from_config(&config, variant)?;
+        let n_areas: BaseInt = match &topology {
+            Some(topology) => topology.areas.len() as BaseInt,
+            None => subparse("n_areas", &config, variant)?,
+        };
         let energy_storage: Watt = subparse("energy_storage", &config, variant)?;
         let pv_adoption: BaseFloat = subparse::<BaseFloat>("pv_adoption", &config, variant)?;
+        let battery_adoption: BaseFloat = subparse_or("battery_adoption", &config, variant, 0.0)?;
+        let battery_capacity: WattHour = subparse_or("battery_capacity", &config, variant, WattHour(0))?;
+        let battery_power: String = subparse_or("battery_power", &config, variant, String::new())?;
+        let battery_power: (Watt, Watt) = if battery_power.is_empty() {
+            (Watt(0), Watt(0))
+        } else {
+            gen_vec_tuples_string::<Watt>(&battery_power)?[0]
+        };
         let max_gen_inc_tick: BaseInt = subparse("max_gen_inc_tick", &config, variant)?;
         let percentage_noise_on_power: f32 = subparse::<BaseFloat>("percentage_noise_on_power", &config, variant)?;
         let percentage_generation_of_usage: f32 =
@@ -53,27 +88,366 @@ impl Grid {
         let power_consumption_bounds: (Watt, Watt) =
             gen_vec_tuples_string::<Watt>(&subparse::<String>("power_consumption_bounds", &config, variant)?)?[0];
 
+        let tie_line_capacity: String = subparse_or("tie_line_capacity", &config, variant, String::new())?;
+        let synchronous_area = if tie_line_capacity.is_empty() {
+            None
+        } else {
+            let (import_capacity, export_capacity) = gen_vec_tuples_string::<Watt>(&tie_line_capacity)?[0];
+            Some(SynchronousArea {
+                import_capacity,
+                export_capacity,
+                power_frequency_characteristic: subparse("power_frequency_characteristic", &config, variant)?,
+                current_exchange: Watt(0),
+            })
+        };
+
+        let ns_line_capacity: Watt = subparse_or("ns_line_capacity", &config, variant, Watt(0))?;
+        let area_line_capacity: Watt = subparse_or("area_line_capacity", &config, variant, Watt(0))?;
+        let line_loss_factor: BaseFloat = subparse_or("line_loss_factor", &config, variant, 0.0)?;
+        let ns_link_density: BaseFloat = subparse_or("ns_link_density", &config, variant, 0.0)?;
+        let ns_link_capacity: Watt = subparse_or("ns_link_capacity", &config, variant, Watt(0))?;
+        let area_link_density: BaseFloat = subparse_or("area_link_density", &config, variant, 0.0)?;
+        let area_link_capacity: Watt = subparse_or("area_link_capacity", &config, variant, Watt(0))?;
+
+        let generators = Self::generators_from_config(&config, variant)?;
+        let ev_charger = Self::ev_charger_from_config(&config, variant)?;
+        let heat_pump = Self::heat_pump_from_config(&config, variant)?;
+        let restoration = Self::restoration_from_config(&config, variant)?;
+        let noise = Self::noise_from_config(&config, variant)?;
+        let profiles = Self::profiles_from_config(&config, variant)?;
+        let replay = Self::replay_from_config(&config, variant)?;
+        let appliances = Self::appliances_from_config(&config, variant)?;
+        let outdoor_temperature = Self::outdoor_temperature_from_config(&config, variant)?;
+
         let volt_boundary = Boundaries::<mVolt>::from_config_file(&filepath, "voltage")?;
         let freq_boundary = Boundaries::<mHz>::from_config_file(&filepath, "frequency")?;
+        let area_boundary = if config.sections().iter().any(|s| s == "area") {
+            Boundaries::<mPercent>::from_config_file(&filepath, "area")?
+        } else {
+            Boundaries::<mPercent>::default()
+        };
         Ok(Self {
             n_areas,
+            topology,
             energy_storage,
             ns_per_a,
             hs_per_ns,
             pv_adoption,
+            battery_adoption,
+            battery_capacity,
+            battery_power,
+            ns_line_capacity,
+            area_line_capacity,
+            line_loss_factor,
+            ns_link_density,
+            ns_link_capacity,
+            area_link_density,
+            area_link_capacity,
+            ev_charger,
+            heat_pump,
+            restoration,
+            noise,
+            profiles,
+            replay,
+            appliances,
+            outdoor_temperature,
             num_noise_functions,
             household_power_consumption_distribution: power_consumption_bounds,
             percentage_noise_on_power,
             max_gen_inc_tick: Watt(max_gen_inc_tick as i64),
             bulk_consumption,
+            generators,
             volt_modifier,
+            synchronous_area,
             volt_boundary,
             freq_boundary,
+            area_boundary,
             percentage_generation_of_usage,
         })
     }
 }
 
+impl Grid {
+    /// Parses the generation fleet. Generators are given as (location, capacity, min_stable_output, ramp_rate,
+    /// marginal_cost) and trips as (generator, begin, end), where generator is the position in the list of generators.
+    fn generators_from_config(config: &Ini, variant: &str) -> Result<Vec<GeneratorParam>, ConfigError> {
+        let generators_vec: Vec<Vec<f64>> =
+            gen_vec_lists::<f64>(&subparse_or("generators", config, variant, String::new())?)?;
+        let trips_vec: Vec<Vec<BaseInt>> =
+            gen_vec_lists::<BaseInt>(&subparse_or("generator_trips", config, variant, String::new())?)?;
+
+        let mut generators = vec![];
+        for generator in generators_vec {
+            if generator.len() < 5 {
+                return Err(ConfigError::NumElementsTooLow(format!(
+                    "Number of elements is too low. Expected 5, got {}",
+                    generator.len()
+                )));
+            }
+            generators.push(GeneratorParam {
+                location:          generator[0] as BaseInt,
+                capacity:          Watt(generator[1] as i64),
+                min_stable_output: Watt(generator[2] as i64),
+                ramp_rate:         Watt(generator[3] as i64),
+                marginal_cost:     generator[4] as BaseFloat,
+                trips:             vec![],
+            });
+        }
+
+        for trip in trips_vec {
+            if trip.len() < 3 {
+                return Err(ConfigError::NumElementsTooLow(format!(
+                    "Number of elements is too low. Expected 3, got {}",
+                    trip.len()
+                )));
+            }
+            match generators.get_mut(trip[0] as usize) {
+                Some(generator) => generator.trips.push(GeneratorTrip {
+                    begin: Steps(trip[1]),
+                    end:   Steps(trip[2]),
+                }),
+                None => {
+                    return Err(ConfigError::NotPermittedOption(format!(
+                        "Trip for generator {} that does not exist",
+                        trip[0]
+                    )))
+                }
+            }
+        }
+        Ok(generators)
+    }
+
+    /// Parses the parameters of the EV chargers. Only present if the adoption is given. Arrival and departure are given
+    /// as (center, std deviation) in steps of the day and the energy demand in Wh.
+    fn ev_charger_from_config(config: &Ini, variant: &str) -> Result<Option<EvChargerParam>, ConfigError> {
+        let adoption: BaseFloat = subparse_or("ev_adoption", config, variant, -1.0)?;
+        if adoption < 0.0 {
+            return Ok(None);
+        }
+        let (arrival_center, arrival_std) =
+            gen_vec_tuples_string::<BaseInt>(&subparse::<String>("ev_arrival", config, variant)?)?[0];
+        let (departure_center, departure_std) =
+            gen_vec_tuples_string::<BaseInt>(&subparse::<String>("ev_departure", config, variant)?)?[0];
+        let (demand_center, demand_std) =
+            gen_vec_tuples_string::<i64>(&subparse::<String>("ev_energy_demand", config, variant)?)?[0];
+        let charging_power: Vec<Watt> =
+            gen_vec_lists::<Watt>(&subparse::<String>("ev_charging_power", config, variant)?)?
+                .into_iter()
+                .flatten()
+                .collect();
+        if charging_power.is_empty() {
+            return Err(ConfigError::NumElementsTooLow(
+                "Number of elements is too low. Expected at least 1 charging power".to_string(),
+            ));
+        }
+        Ok(Some(EvChargerParam {
+            adoption,
+            arrival: (Steps(arrival_center), Steps(arrival_std)),
+            departure: (Steps(departure_center), Steps(departure_std)),
+            energy_demand: (WattHour(demand_center), WattHour(demand_std)),
+            charging_power,
+            smart_share: subparse_or("ev_smart_share", config, variant, 0.0)?,
+        }))
+    }
+
+    /// Parses the parameters of the heat pumps. Only present if the adoption is given. The setpoint, resistance and
+    /// capacitance are given as (center, std deviation).
+    fn heat_pump_from_config(config: &Ini, variant: &str) -> Result<Option<HeatPumpParam>, ConfigError> {
+        let adoption: BaseFloat = subparse_or("heat_pump_adoption", config, variant, -1.0)?;
+        if adoption < 0.0 {
+            return Ok(None);
+        }
+        Ok(Some(HeatPumpParam {
+            adoption,
+            electrical_power: subparse("heat_pump_power", config, variant)?,
+            cop: subparse("heat_pump_cop", config, variant)?,
+            deadband: subparse_or("heat_pump_deadband", config, variant, 1.0)?,
+            setpoint: gen_vec_tuples_string::<BaseFloat>(&subparse::<String>("heat_pump_setpoint", config, variant)?)?
+                [0],
+            resistance: gen_vec_tuples_string::<BaseFloat>(&subparse::<String>("house_resistance", config, variant)?)?
+                [0],
+            capacitance: gen_vec_tuples_string::<BaseFloat>(&subparse::<String>(
+                "house_capacitance",
+                config,
+                variant,
+            )?)?[0],
+        }))
+    }
+
+    /// Parses the restoration after a blackout. Only present if a black start time is given. The reconnection band of
+    /// the PV inverters is given in Hz.
+    fn restoration_from_config(config: &Ini, variant: &str) -> Result<Option<RestorationParam>, ConfigError> {
+        let black_start_time: BaseInt = subparse_or("black_start_time", config, variant, -1)?;
+        if black_start_time < 0 {
+            return Ok(None);
+        }
+        let sequence = gen_vec_lists::<usize>(&subparse_or("restoration_sequence", config, variant, String::new())?)?
+            .into_iter()
+            .flatten()
+            .collect();
+        let pv_reconnect_band: BaseFloat = subparse_or("pv_reconnect_band", config, variant, 0.1)?;
+        Ok(Some(RestorationParam {
+            black_start_time: Steps(black_start_time),
+            sequence,
+            load_pickup_limit: subparse("load_pickup_limit", config, variant)?,
+            pv_reconnect_delay: Steps(subparse_or("pv_reconnect_delay", config, variant, 1)?),
+            pv_reconnect_band: mHz((pv_reconnect_band * 1000.0) as BaseInt),
+        }))
+    }
+
+    /// Parses the standard profiles of the consumption. Only present if a profile file is given. Each category has a
+    /// share of the households. The file and the categories are read as is, as they may contain underscores.
+    fn profiles_from_config(config: &Ini, variant: &str) -> Result<Option<StandardProfiles>, ConfigError> {
+        let Some(profile_file) = config.get(variant, "profile_file") else {
+            return Ok(None);
+        };
+        let categories: Vec<String> =
+            gen_vec_lists::<String>(&config.get(variant, "profile_categories").unwrap_or_default())?
+                .into_iter()
+                .flatten()
+                .collect();
+        let shares: Vec<BaseFloat> =
+            gen_vec_lists::<BaseFloat>(&subparse::<String>("profile_shares", config, variant)?)?
+                .into_iter()
+                .flatten()
+                .collect();
+        if categories.is_empty() || shares.len() < categories.len() {
+            return Err(ConfigError::NumElementsTooLow(format!(
+                "Number of elements is too low. Expected a share for each of the {} categories, got {}",
+                categories.len(),
+                shares.len()
+            )));
+        }
+        Ok(Some(StandardProfiles::from_csv_file(
+            profile_file,
+            categories.into_iter().zip(shares).collect(),
+        )?))
+    }
+
+    /// Parses the bottom-up load model. Only present if appliances are given. Each appliance is given as (power,
+    /// duration in steps, activations per day, first step, last step of the day, adoption). Wake up and bed time are
+    /// given as (center, std deviation) in steps of the day, the chances per step.
+    fn appliances_from_config(config: &Ini, variant: &str) -> Result<Option<ApplianceParam>, ConfigError> {
+        let appliances_vec: Vec<Vec<BaseFloat>> =
+            gen_vec_lists::<BaseFloat>(&subparse_or("appliances", config, variant, String::new())?)?;
+        if appliances_vec.is_empty() {
+            return Ok(None);
+        }
+        let mut appliances = vec![];
+        for appliance in appliances_vec {
+            if appliance.len() < 6 {
+                return Err(ConfigError::NumElementsTooLow(format!(
+                    "Number of elements is too low. Expected 6, got {}",
+                    appliance.len()
+                )));
+            }
+            appliances.push(Appliance {
+                power:       Watt(appliance[0] as i64),
+                duration:    Steps(appliance[1] as BaseInt),
+                activations: appliance[2],
+                begin:       Steps(appliance[3] as BaseInt),
+                end:         Steps(appliance[4] as BaseInt),
+                adoption:    appliance[5],
+                remaining:   Steps(0),
+            });
+        }
+        let (wake_up_center, wake_up_std) =
+            gen_vec_tuples_string::<BaseInt>(&subparse_or("wake_up", config, variant, "(28, 4)".to_string())?)?[0];
+        let (bed_time_center, bed_time_std) =
+            gen_vec_tuples_string::<BaseInt>(&subparse_or("bed_time", config, variant, "(92, 4)".to_string())?)?[0];
+        Ok(Some(ApplianceParam {
+            appliances,
+            base_load: subparse_or("appliance_base_load", config, variant, Watt(0))?,
+            wake_up: (Steps(wake_up_center), Steps(wake_up_std)),
+            bed_time: (Steps(bed_time_center), Steps(bed_time_std)),
+            away_chance: subparse_or("away_chance", config, variant, 0.0)?,
+            return_chance: subparse_or("return_chance", config, variant, 1.0)?,
+        }))
+    }
+
+    /// Parses the imported topology. Only present if a topology file is given, which is read as is as it may contain
+    /// underscores.
+    fn topology_from_config(config: &Ini, variant: &str) -> Result<Option<Topology>, ConfigError> {
+        match config.get(variant, "topology_file").filter(|f| !f.is_empty()) {
+            Some(topology_file) => Ok(Some(Topology::from_file(topology_file)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses the measured time series that are replayed. Only present if a meter file is given. The files are read as
+    /// is, as they may contain underscores. The shift is given in steps and the scale as (center, std deviation).
+    fn replay_from_config(config: &Ini, variant: &str) -> Result<Option<MeterData>, ConfigError> {
+        let Some(meter_file) = config.get(variant, "replay_file") else {
+            return Ok(None);
+        };
+        let generation_file = config.get(variant, "replay_generation_file");
+        let scale: String = subparse_or("replay_scale", config, variant, String::new())?;
+        let scale = gen_vec_tuples_string::<BaseFloat>(&scale)?
+            .first()
+            .copied()
+            .unwrap_or((1.0, 0.0));
+        Ok(Some(MeterData::from_csv_files(
+            meter_file,
+            generation_file,
+            Steps(subparse_or("replay_shift", config, variant, 0)?),
+            scale,
+        )?))
+    }
+
+    /// Parses the stochastic noise. Only present if a noise model other than the sine functions is given. The
+    /// correlation is the coefficient between consecutive steps for `ar1` and the rate of mean reversion for `ou`.
+    fn noise_from_config(config: &Ini, variant: &str) -> Result<Option<NoiseParam>, ConfigError> {
+        let model: String = subparse_or("noise_model", config, variant, "sine".to_string())?;
+        let model = match model.as_str() {
+            "sine" => return Ok(None),
+            "ar1" => NoiseModel::Ar1,
+            "ou" => NoiseModel::OrnsteinUhlenbeck,
+            _ => {
+                return Err(ConfigError::NotPermittedOption(format!(
+                    "{model} is not a permitted noise model"
+                )))
+            }
+        };
+        let correlation: BaseFloat = subparse_or("noise_correlation", config, variant, 0.9)?;
+        if model == NoiseModel::Ar1 && !(0.0..1.0).contains(&correlation) {
+            return Err(ConfigError::NotPermittedOption(format!(
+                "noise_correlation of {correlation} does not give a stationary AR(1) process"
+            )));
+        }
+        let neighbour_correlation: BaseFloat = subparse_or("noise_neighbour_correlation", config, variant, 0.0)?;
+        if !(0.0..=1.0).contains(&neighbour_correlation) {
+            return Err(ConfigError::NotPermittedOption(format!(
+                "noise_neighbour_correlation of {neighbour_correlation} is not between 0 and 1"
+            )));
+        }
+        Ok(Some(NoiseParam {
+            model,
+            correlation,
+            neighbour_correlation,
+        }))
+    }
+
+    /// Parses the outdoor temperature as (mean, seasonal amplitude, daily amplitude, coldest day). Uses the default
+    /// temperature if none is given.
+    fn outdoor_temperature_from_config(config: &Ini, variant: &str) -> Result<OutdoorTemperature, ConfigError> {
+        let temperature: Vec<Vec<BaseFloat>> =
+            gen_vec_lists::<BaseFloat>(&subparse_or("outdoor_temperature", config, variant, String::new())?)?;
+        match temperature.first() {
+            None => Ok(OutdoorTemperature::default()),
+            Some(t) if t.len() < 4 => Err(ConfigError::NumElementsTooLow(format!(
+                "Number of elements is too low. Expected 4, got {}",
+                t.len()
+            ))),
+            Some(t) => Ok(OutdoorTemperature {
+                mean:               t[0],
+                seasonal_amplitude: t[1],
+                daily_amplitude:    t[2],
+                coldest_day:        t[3] as BaseInt,
+            }),
+        }
+    }
+}
+
 impl<T: BoundaryUnitTrait> Boundaries<T> {
     /// Creates boundary from a config file.
     pub fn from_config_file(filepath: impl AsRef<Path>, section: &str) -> Result<Self, ConfigError> {
@@ -97,44 +471,81 @@ impl<T: BoundaryUnitTrait> Boundaries<T> {
         }
         let normal_low_float: BaseFloat = subparse("normal_low", &config, section)?;
         let normal_high_float: BaseFloat = subparse("normal_high", &config, section)?;
-        let lowerbands_vec: Vec<(BaseFloat, BaseFloat)> =
-            gen_vec_tuples_string::<BaseFloat>(&subparse::<String>("lowerbands", &config, section)?)?;
-        let upperbands_vec: Vec<(BaseFloat, BaseFloat)> =
-            gen_vec_tuples_string::<BaseFloat>(&subparse::<String>("upperbands", &config, section)?)?;
+        let hysteresis_float: BaseFloat = subparse_or("hysteresis", &config, section, 0.0)?;
+        let lowerbands_vec: Vec<Vec<BaseFloat>> =
+            gen_vec_lists::<BaseFloat>(&subparse::<String>("lowerbands", &config, section)?)?;
+        let upperbands_vec: Vec<Vec<BaseFloat>> =
+            gen_vec_lists::<BaseFloat>(&subparse::<String>("upperbands", &config, section)?)?;
+        let rate_limit_vec: Vec<Vec<BaseFloat>> =
+            gen_vec_lists::<BaseFloat>(&subparse_or("rate_limit", &config, section, String::new())?)?;
 
         let normal_low: T = ((normal_low_float * 1000.0) as BaseInt).into();
         let normal_high: T = ((normal_high_float * 1000.0) as BaseInt).into();
+        let hysteresis: T = ((hysteresis_float * 1000.0) as BaseInt).into();
 
-        let mut lowerbands: Vec<BoundaryBand<T>> = vec![];
-        for (t, minutes) in &lowerbands_vec {
-            let border: T = ((t * 1000.0) as BaseInt).into();
-            let max_time_allowed: Steps = Minutes(((minutes * 1000.0) as BaseInt) / 1000).into();
-            lowerbands.push(BoundaryBand {
-                border,
-                max_time_allowed,
-                time_passed: Steps(0),
-            });
-        }
-
-        let mut upperbands: Vec<BoundaryBand<T>> = vec![];
-        for (t, minutes) in &upperbands_vec {
-            let border: T = ((t * 1000.0) as BaseInt).into();
-            let max_time_allowed: Steps = Minutes(((minutes * 1000.0) as BaseInt) / 1000).into();
-            upperbands.push(BoundaryBand {
-                border,
-                max_time_allowed,
-                time_passed: Steps(0),
-            });
-        }
+        let lowerbands = lowerbands_vec
+            .iter()
+            .map(|b| Self::band_from_list(b, "lowerbands"))
+            .collect::<Result<Vec<BoundaryBand<T>>, ConfigError>>()?;
+        let upperbands = upperbands_vec
+            .iter()
+            .map(|b| Self::band_from_list(b, "upperbands"))
+            .collect::<Result<Vec<BoundaryBand<T>>, ConfigError>>()?;
+        let rate_limit = match rate_limit_vec.first().map(Vec::as_slice) {
+            None => None,
+            Some(&[max_change, window, minutes]) => {
+                let window: Steps = Minutes(((window * 1000.0) as BaseInt) / 1000).into();
+                if window < Steps(1) {
+                    return Err(ConfigError::NotPermittedOption(format!(
+                        "{section}: the window of rate_limit should be at least {} minutes",
+                        Steps::minutes_per_step()
+                    )));
+                }
+                Some(RateLimit {
+                    max_change: ((max_change * 1000.0) as BaseInt).into(),
+                    window,
+                    max_time_allowed: Minutes(((minutes * 1000.0) as BaseInt) / 1000).into(),
+                    time_passed: Steps(0),
+                })
+            }
+            Some(_) => {
+                return Err(ConfigError::ParsingError(format!(
+                    "{section}: rate_limit should be (max change, window in minutes, minutes)"
+                )))
+            }
+        };
         let normalband = NormalBand {
-            lower:  normal_low,
+            lower: normal_low,
             higher: normal_high,
+            hysteresis,
         };
         Ok(Self {
             normalband,
             lowerbands,
             upperbands,
+            rate_limit,
             state: GridBoundaryState::Normal,
+            rate_state: GridBoundaryState::Normal,
+        })
+    }
+
+    /// Creates a band from a list of its border, the minutes allowed and optionally the window in minutes.
+    fn band_from_list(list: &[BaseFloat], key: &str) -> Result<BoundaryBand<T>, ConfigError> {
+        let (t, minutes, window) = match *list {
+            [t, minutes] => (t, minutes, 0.0),
+            [t, minutes, window] => (t, minutes, window),
+            _ => {
+                return Err(ConfigError::ParsingError(format!(
+                    "{key} should contain (border, minutes) or (border, minutes, window in minutes)"
+                )))
+            }
+        };
+        Ok(BoundaryBand {
+            border:           ((t * 1000.0) as BaseInt).into(),
+            max_time_allowed: Minutes(((minutes * 1000.0) as BaseInt) / 1000).into(),
+            time_passed:      Steps(0),
+            window:           Minutes(((window * 1000.0) as BaseInt) / 1000).into(),
+            passed:           vec![],
         })
     }
 }
@@ -8,8 +8,8 @@ use rand::SeedableRng;
 
 use crate::attack::Attack;
 use crate::grid::Grid;
-use crate::model::ModelParameters;
-use crate::util::{subparse, ConfigError, Steps};
+use crate::model::{ModelParameters, Scenario, StopRule};
+use crate::util::{mHz, mPercent, subparse, subparse_or, ConfigError, RngStreams, Steps};
 
 impl ModelParameters {
     /// Create the `ModelParameters` from a "ModelParameters.ini"
@@ -64,12 +64,53 @@ impl ModelParameters {
             }
         };
 
+        // Paths are read as given, as parsing removes underscores
+        let checkpoint_file = config.get(variant, "checkpoint_file").filter(|f| !f.is_empty());
+        let checkpoint_interval: Steps = subparse_or("checkpoint_interval", &config, variant, Steps(0))?;
+        let resume_file = config.get(variant, "resume_file").filter(|f| !f.is_empty());
+        let scenario_file = config.get(variant, "scenario_file").filter(|f| !f.is_empty());
+        let graph_export_file = config.get(variant, "graph_export_file").filter(|f| !f.is_empty());
+
+        // Stop rules, each is off when its key is missing or 0
+        let mut stop_rules = vec![];
+        if stop_on_freq_error {
+            stop_rules.push(StopRule::FrequencyWarning);
+        }
+        let voltage_errors: usize = subparse_or("stop_on_voltage_errors", &config, variant, 0)?;
+        if voltage_errors > 0 {
+            stop_rules.push(StopRule::VoltageErrors(voltage_errors));
+        }
+        let reserve_exhausted: Steps = subparse_or("stop_on_reserve_exhausted", &config, variant, Steps(0))?;
+        if reserve_exhausted > Steps(0) {
+            stop_rules.push(StopRule::ReserveExhausted(reserve_exhausted));
+        }
+        let infection_share: mPercent = subparse_or("stop_on_infection_share", &config, variant, mPercent(0))?;
+        if infection_share > mPercent(0) {
+            stop_rules.push(StopRule::InfectionShare(infection_share));
+        }
+        let steady_steps: Steps = subparse_or("stop_on_steady_state_steps", &config, variant, Steps(0))?;
+        if steady_steps > Steps(0) {
+            let tolerance: mHz = subparse("stop_on_steady_state_tolerance", &config, variant)?;
+            stop_rules.push(StopRule::SteadyState {
+                tolerance,
+                steps: steady_steps,
+            });
+        }
+
         let attack = Attack::from_config(attack_file, &attack_variant, seed_prime)?;
         let grid = Grid::from_config(grid_file, &grid_variant)?;
+        let scenario = match scenario_file {
+            Some(scenario_file) => {
+                let scenario_variant: String = subparse("scenario_variant", &config, variant)?;
+                Scenario::from_config(scenario_file, &scenario_variant)?
+            }
+            None => Scenario::default(),
+        };
 
         Ok(Self {
             name,
             steps,
+            streams: RngStreams::new(seed_prime),
             seed: SmallRng::seed_from_u64(seed_prime),
             logfolder,
             logfile: chrono::Local::now().format(&logfile).to_string(),
@@ -81,7 +122,12 @@ impl ModelParameters {
             grid,
             loglevel,
             type_output,
-            stop_on_freq_error,
+            stop_rules,
+            checkpoint_file,
+            checkpoint_interval,
+            resume_file,
+            graph_export_file,
+            scenario,
         })
     }
 }
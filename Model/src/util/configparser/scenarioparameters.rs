@@ -0,0 +1,115 @@
+use std::path::Path;
+
+use configparser::ini::Ini;
+
+use crate::model::{EventTarget, Scenario, ScenarioEvent, ScenarioEventKind};
+use crate::util::{gen_vec_lists, subparse_or, BaseFloat, BaseInt, BaseUint, ConfigError, Steps, Watt};
+
+impl Scenario {
+    /// Create a [Scenario] from the given variant of the ScenarioParameters file. The events are sorted by step.
+    pub fn from_config(filepath: impl AsRef<Path>, variant: &str) -> Result<Self, ConfigError> {
+        let mut config = Ini::new();
+        match config.load(&filepath) {
+            Ok(_) => (),
+            Err(e) => return Err(ConfigError::LoadError(e)),
+        };
+
+        let mut events = vec![];
+        for (step, duration, target) in Self::targeted_events_from_config("generator_outage", &config, variant)? {
+            events.push(ScenarioEvent {
+                step,
+                kind: ScenarioEventKind::GeneratorOutage { target, duration },
+            });
+        }
+        for (step, duration, target) in Self::targeted_events_from_config("netstation_disconnection", &config, variant)?
+        {
+            events.push(ScenarioEvent {
+                step,
+                kind: ScenarioEventKind::NetstationDisconnection { target, duration },
+            });
+        }
+        for (step, duration, target) in Self::targeted_events_from_config("pv_curtailment", &config, variant)? {
+            events.push(ScenarioEvent {
+                step,
+                kind: ScenarioEventKind::PvCurtailment { target, duration },
+            });
+        }
+        for (step, change) in Self::power_events_from_config("bulk_load_change", &config, variant)? {
+            events.push(ScenarioEvent {
+                step,
+                kind: ScenarioEventKind::BulkLoadChange(change),
+            });
+        }
+        for (step, capacity) in Self::power_events_from_config("reserve_capacity_change", &config, variant)? {
+            events.push(ScenarioEvent {
+                step,
+                kind: ScenarioEventKind::ReserveCapacityChange(capacity),
+            });
+        }
+        let patch_release_out: Vec<Vec<BaseFloat>> =
+            gen_vec_lists::<BaseFloat>(&subparse_or("patch_release", &config, variant, String::new())?)?;
+        for release in patch_release_out {
+            check_num_elements(&release, 3)?;
+            let indices: Vec<BaseInt> = release[3..].iter().map(|i| *i as BaseInt).collect();
+            events.push(ScenarioEvent {
+                step: Steps(release[0] as BaseInt),
+                kind: ScenarioEventKind::PatchRelease {
+                    target: event_target(release[2] as BaseInt, &indices)?,
+                    share:  release[1],
+                },
+            });
+        }
+        events.sort_by_key(|e| e.step);
+        Ok(Self { events })
+    }
+
+    /// Parses the events with a target and a duration. Given as (step, duration, target, numbers...).
+    fn targeted_events_from_config(
+        key: &str,
+        config: &Ini,
+        variant: &str,
+    ) -> Result<Vec<(Steps, Steps, EventTarget)>, ConfigError> {
+        let events_out: Vec<Vec<BaseInt>> =
+            gen_vec_lists::<BaseInt>(&subparse_or(key, config, variant, String::new())?)?;
+        let mut events = vec![];
+        for event in events_out {
+            check_num_elements(&event, 3)?;
+            events.push((Steps(event[0]), Steps(event[1]), event_target(event[2], &event[3..])?));
+        }
+        Ok(events)
+    }
+
+    /// Parses the events that set a power. Given as (step, power).
+    fn power_events_from_config(key: &str, config: &Ini, variant: &str) -> Result<Vec<(Steps, Watt)>, ConfigError> {
+        let events_out: Vec<Vec<i64>> = gen_vec_lists::<i64>(&subparse_or(key, config, variant, String::new())?)?;
+        let mut events = vec![];
+        for event in events_out {
+            check_num_elements(&event, 2)?;
+            events.push((Steps(event[0] as BaseInt), Watt(event[1])));
+        }
+        Ok(events)
+    }
+}
+
+/// The target of an event, where 0 targets all agents, 1 the areas with the given numbers and 2 the agents with the
+/// given indices.
+fn event_target(target: BaseInt, numbers: &[BaseInt]) -> Result<EventTarget, ConfigError> {
+    match target {
+        0 => Ok(EventTarget::All),
+        1 => Ok(EventTarget::Areas(numbers.iter().map(|a| *a as usize).collect())),
+        2 => Ok(EventTarget::Agents(numbers.iter().map(|i| *i as BaseUint).collect())),
+        t => Err(ConfigError::NotPermittedOption(format!(
+            "Event target {t} is not permitted, use 0 for all agents, 1 for areas or 2 for agents"
+        ))),
+    }
+}
+
+fn check_num_elements<T>(event: &[T], expected: usize) -> Result<(), ConfigError> {
+    if event.len() < expected {
+        return Err(ConfigError::NumElementsTooLow(format!(
+            "Number of elements is too low. Expected {expected}, got {}",
+            event.len()
+        )));
+    }
+    Ok(())
+}
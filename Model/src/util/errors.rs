@@ -67,9 +67,6 @@ pub enum ModelError {
     #[error("NodeIndexError: {0}")]
     NodeIndexError(#[from] std::num::ParseIntError),
 
-    #[error("NeedToStop")]
-    NeedToStop,
-
     #[error("Agent: {agent} could not log their state. Error: {source}")]
     LogStateErrorAvro { agent: String, source: apache_avro::Error },
 
@@ -97,8 +94,14 @@ pub enum ModelError {
     #[error("Writing output went wrong: {0}")]
     HandlerError(#[from] JoinError),
 
+    #[error("Writing output went wrong: {0}")]
+    OutputError(String),
+
     #[error("ParamError: {context} - {msg}")]
     ParamError { msg: String, context: String },
+
+    #[error("Checkpoint could not be saved or restored: {0}")]
+    CheckpointError(String),
 }
 
 impl From<fast_log::error::LogError> for ModelError {
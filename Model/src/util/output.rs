@@ -2,31 +2,30 @@ mod avro;
 mod avrovalue;
 mod graphviz;
 mod json;
-
-use std::sync::Arc;
+mod network;
 
 pub use avro::*;
 pub use avrovalue::*;
 pub use graphviz::*;
 pub use json::*;
-use parking_lot::RwLock;
-use rand::rngs::SmallRng;
-use tokio::task::JoinHandle;
 
 use crate::model::Model;
 use crate::util::{ModelError, Steps};
-use rand::SeedableRng;
 
 impl Model {
-    /// Output the model in its current state.
-    pub fn output(&self, step: Steps, handles: &mut Vec<JoinHandle<()>>) -> Result<(), ModelError> {
+    /// Output the model in its current state. The output is written in the background, see [Model::flush_output].
+    /// The state kept in the layout is stored in the agents first, see
+    /// [GridLayout::store](crate::model::GridLayout::store).
+    pub fn output(&mut self, step: Steps) -> Result<(), ModelError> {
+        self.layout.store(&self.households, &self.connections);
+        let handles = &mut self.output_handles;
         match self.param.type_output.as_str() {
             "json" => {
-                let agent_values = Self::output_step_agents_json(&self.agents);
+                let agent_values = Self::output_step_agents_json(&self.agents)?;
                 Self::output_step_model_json(&self.param, step, agent_values, handles);
             }
             "avro" => {
-                let agent_values = Self::output_step_agents_avro(&self.agents);
+                let agent_values = Self::output_step_agents_avro(&self.agents)?;
                 Self::output_step_model_avro(&self.param, step, agent_values, handles);
             }
             _ => {
@@ -39,7 +38,3 @@ impl Model {
         Ok(())
     }
 }
-
-pub(crate) fn default_smallrng() -> Arc<RwLock<SmallRng>>{
-    Arc::new(RwLock::new(SmallRng::seed_from_u64(117)))
-}
\ No newline at end of file
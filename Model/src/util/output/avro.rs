@@ -1,5 +1,6 @@
 use std::fs::{create_dir_all, File};
 use std::io::Write;
+use std::thread::{self, JoinHandle};
 
 use apache_avro::schema::UnionSchema;
 use apache_avro::types::Value as AvroValue;
@@ -7,14 +8,13 @@ use apache_avro::{AvroSchema, Schema, Writer};
 use log::trace;
 #[cfg(not(feature = "single_thread"))]
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
-use tokio::task::JoinHandle;
 
-use crate::agent::{AgentKind, AgentList, Area, Household, Netstation, Root};
-use crate::grid::{Boundaries, PowerState};
+use crate::agent::{AgentKind, AgentList, Area, Battery, Connection, EvCharger, Generator, HeatPump, Household, Netstation, Root};
+use crate::grid::{Boundaries, InfectionState, PowerState};
 use crate::model::{Model, ModelParameters};
 #[cfg(feature = "single_thread")]
 use crate::norayon::prelude::*;
-use crate::util::{mHz, Steps, UtilError};
+use crate::util::{mHz, ModelError, Steps, UtilError};
 
 /// Get the AvroSchema needed to write the files. Is not stable yet.
 pub fn get_avro_schema() -> Schema {
@@ -22,13 +22,21 @@ pub fn get_avro_schema() -> Schema {
         (" \"AgentKind\"", AgentKind::get_schema().canonical_form()),
         (" \"PowerState\"", PowerState::get_schema().canonical_form()),
         (" \"Boundaries\"", Boundaries::<mHz>::get_schema().canonical_form()),
+        (" \"InfectionState\"", InfectionState::get_schema().canonical_form()),
     ];
 
     let root = Root::get_schema().canonical_form();
     let area = Area::get_schema().canonical_form();
     let ns = Netstation::get_schema().canonical_form();
     let hh = Household::get_schema().canonical_form();
-    let mut agents = vec![root, area, ns, hh];
+    let generator = Generator::get_schema().canonical_form();
+    let ev_charger = EvCharger::get_schema().canonical_form();
+    let heat_pump = HeatPump::get_schema().canonical_form();
+    let battery = Battery::get_schema().canonical_form();
+    let connection = Connection::get_schema().canonical_form();
+    let mut agents = vec![
+        root, area, ns, hh, generator, ev_charger, heat_pump, battery, connection,
+    ];
 
     // println!("{}", serde_json::to_string(&agents[0]).unwrap());
 
@@ -52,25 +60,23 @@ pub fn get_avro_schema() -> Schema {
 
 impl Model {
     /// Output the state of the model in avro format by transforming each agent into an AvroValue.
-    pub fn output_step_agents_avro(agents: &AgentList) -> Vec<(Schema, AvroValue)> {
-        agents
-            .par_iter()
-            .map(|a| a.read_arc_recursive().get_avro().unwrap())
-            .collect()
+    pub fn output_step_agents_avro(agents: &AgentList) -> Result<Vec<(Schema, AvroValue)>, ModelError> {
+        agents.par_iter().map(|a| a.read_arc_recursive().get_avro()).collect()
     }
 
-    /// Log the state of the model in this step.
+    /// Log the state of the model in this step. The file is written by a thread, which returns the error of writing
+    /// it, if any.
     pub fn output_step_model_avro(
         param: &ModelParameters,
         step: Steps,
         agent_values: Vec<(Schema, AvroValue)>,
-        handles: &mut Vec<JoinHandle<()>>,
+        handles: &mut Vec<JoinHandle<Result<(), ModelError>>>,
     ) {
         let f1 = param.outputdatafolder.clone();
         let f2 = param.outputdatafile.clone();
         let codec = param.outputcodec;
 
-        handles.push(tokio::spawn(async move {
+        handles.push(thread::spawn(move || {
             let mut outputfile = {
                 let folder: &str = &f1;
                 let model: &str = &f2;
@@ -86,16 +92,18 @@ impl Model {
                     Ok(v) => Ok(v),
                     Err(e) => Err(UtilError::FileSystemError(format!("Path: {filepath} - {e}",))),
                 }
-            }
-            .unwrap();
+            }?;
             let mut output_vec: Vec<u8> = vec![];
             let schema = Schema::Boolean; //// TODO: ...
             let mut writer = Writer::with_codec(&schema, &mut output_vec, codec);
             for a in agent_values {
-                writer.append_value_ref(&a.1).unwrap();
+                writer
+                    .append_value_ref(&a.1)
+                    .map_err(|e| ModelError::OutputError(e.to_string()))?;
             }
-            writer.flush().unwrap();
-            outputfile.write_all(&output_vec).unwrap();
+            writer.flush().map_err(|e| ModelError::OutputError(e.to_string()))?;
+            outputfile.write_all(&output_vec).map_err(UtilError::from)?;
+            Ok(())
         }));
     }
 }
@@ -121,10 +129,10 @@ mod agent_tests {
     fn format_test_avro_serialize() {
         let mut param = ModelParameters::test();
         let agent = Root::new(117, &param.grid);
-        let agent2 = Area::new(117);
+        let agent2 = Area::new(117, &param.grid);
         let agent3 = Netstation::new(117, &param.grid);
-        let agent4 = Household::new(117, PowerGeneration::new_no_pv(Some(117), &mut param).unwrap());
-        let agent5 = Household::new(117, PowerGeneration::new_pv(Some(117), &mut param).unwrap());
+        let agent4 = Household::new(117, PowerGeneration::new_no_pv(Some(117), None, &mut param).unwrap());
+        let agent5 = Household::new(117, PowerGeneration::new_pv(Some(117), None, &mut param).unwrap());
 
         let mut out = vec![];
         let schema = get_avro_schema();
@@ -7,7 +7,7 @@ use apache_avro::Schema;
 
 use crate::agent::AgentKind;
 use crate::grid::{Boundaries, BoundaryBand, FreqState, GridBoundaryState, InfectionState, PowerGeneration, PowerState, SineParam, VoltState};
-use crate::util::{mHz, mVolt, BaseInt, Steps, Watt};
+use crate::util::{mHz, mPercent, mVolt, BaseInt, Steps, Watt, WattHour};
 
 type Names = HashMap<Name, Schema>;
 impl AvroSchemaComponent for Steps {
@@ -20,6 +20,11 @@ impl AvroSchemaComponent for Watt {
         i64::get_schema_in_ctxt(named_schemas, enclosing_namespace)
     }
 }
+impl AvroSchemaComponent for WattHour {
+    fn get_schema_in_ctxt(named_schemas: &mut Names, enclosing_namespace: &Namespace) -> Schema {
+        i64::get_schema_in_ctxt(named_schemas, enclosing_namespace)
+    }
+}
 impl AvroSchemaComponent for mHz {
     fn get_schema_in_ctxt(named_schemas: &mut Names, enclosing_namespace: &Namespace) -> Schema {
         BaseInt::get_schema_in_ctxt(named_schemas, enclosing_namespace)
@@ -31,6 +36,12 @@ impl AvroSchemaComponent for mVolt {
     }
 }
 
+impl AvroSchemaComponent for mPercent {
+    fn get_schema_in_ctxt(named_schemas: &mut Names, enclosing_namespace: &Namespace) -> Schema {
+        BaseInt::get_schema_in_ctxt(named_schemas, enclosing_namespace)
+    }
+}
+
 impl From<&PowerState> for AvroValue {
     fn from(value: &PowerState) -> Self {
         let powerstate: Vec<(String, Self)> = vec![
@@ -93,6 +104,7 @@ impl From<&Boundaries<mVolt>> for AvroValue {
         let normalband = vec![
             ("lower".to_string(), Self::Int(value.normalband.lower.0)),
             ("higher".to_string(), Self::Int(value.normalband.higher.0)),
+            ("hysteresis".to_string(), Self::Int(value.normalband.hysteresis.0)),
         ];
         let state = match value.state {
             GridBoundaryState::TooLow => Self::Enum(0, "TooLow".to_string()),
@@ -128,6 +140,7 @@ impl From<&Boundaries<mHz>> for AvroValue {
         let normalband = vec![
             ("lower".to_string(), Self::Int(value.normalband.lower.0)),
             ("higher".to_string(), Self::Int(value.normalband.higher.0)),
+            ("hysteresis".to_string(), Self::Int(value.normalband.hysteresis.0)),
         ];
         let lowerband: Vec<Self> = value.lowerbands.iter().map(Self::from).collect();
         let upperband: Vec<Self> = value.upperbands.iter().map(Self::from).collect();
@@ -146,6 +159,7 @@ impl From<&BoundaryBand<mVolt>> for AvroValue {
             ("border".to_string(), Self::Int(value.border.0)),
             ("max_time_allowed".to_string(), Self::Int(value.max_time_allowed.0)),
             ("time_passed".to_string(), Self::Int(value.time_passed.0)),
+            ("window".to_string(), Self::Int(value.window.0)),
         ];
         Self::Record(res)
     }
@@ -156,6 +170,7 @@ impl From<&BoundaryBand<mHz>> for AvroValue {
             ("border".to_string(), Self::Int(value.border.0)),
             ("max_time_allowed".to_string(), Self::Int(value.max_time_allowed.0)),
             ("time_passed".to_string(), Self::Int(value.time_passed.0)),
+            ("window".to_string(), Self::Int(value.window.0)),
         ];
         Self::Record(res)
     }
@@ -190,6 +205,10 @@ impl From<&AgentKind> for AvroValue {
             AgentKind::Area => Self::Enum(2, "Area".to_string()),
             AgentKind::Netstation => Self::Enum(3, "Netstation".to_string()),
             AgentKind::Household => Self::Enum(4, "Household".to_string()),
+            AgentKind::Generator => Self::Enum(5, "Generator".to_string()),
+            AgentKind::EvCharger => Self::Enum(6, "EvCharger".to_string()),
+            AgentKind::HeatPump => Self::Enum(7, "HeatPump".to_string()),
+            AgentKind::Battery => Self::Enum(8, "Battery".to_string()),
         }
     }
 }
@@ -1,5 +1,6 @@
 use std::fs::{create_dir_all, File};
 use std::io::{BufWriter, Write};
+use std::thread::{self, JoinHandle};
 
 use apache_avro::Codec;
 use flate2::write::GzEncoder;
@@ -7,36 +8,43 @@ use flate2::Compression;
 use log::trace;
 #[cfg(not(feature = "single_thread"))]
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
-use tokio::task::JoinHandle;
 
 use crate::agent::AgentList;
 use crate::model::{Model, ModelParameters};
 #[cfg(feature = "single_thread")]
 use crate::norayon::prelude::*;
-use crate::util::{Steps, UtilError};
+use crate::util::{ModelError, Steps, UtilError};
 
 impl Model {
     /// Transforms the vector of agents to a vector of JSON values in bytes.
     #[inline]
-    pub fn output_step_agents_json(agents: &AgentList) -> Vec<Vec<u8>> {
-        agents
-            .par_iter()
-            .map(|a| a.read_arc_recursive().get_json().unwrap())
-            .collect()
+    pub fn output_step_agents_json(agents: &AgentList) -> Result<Vec<Vec<u8>>, ModelError> {
+        agents.par_iter().map(|a| a.read_arc_recursive().get_json()).collect()
     }
 
-    /// Log the state of the model in this step.
+    /// Write the JSON values of the agents as one array.
+    fn write_json_entries(out: &mut impl Write, agent_values: &[Vec<u8>]) -> std::io::Result<()> {
+        out.write_all(b"[")?;
+        for entry in agent_values {
+            out.write_all(entry)?;
+            out.write_all(b",\n")?;
+        }
+        out.write_all(b"]")
+    }
+
+    /// Log the state of the model in this step. The file is written by a thread, which returns the error of writing
+    /// it, if any.
     pub fn output_step_model_json(
         param: &ModelParameters,
         step: Steps,
         agent_values: Vec<Vec<u8>>,
-        handles: &mut Vec<JoinHandle<()>>,
+        handles: &mut Vec<JoinHandle<Result<(), ModelError>>>,
     ) {
         let f1 = param.outputdatafolder.clone();
         let f2 = param.outputdatafile.clone();
         let codec = param.outputcodec;
 
-        handles.push(tokio::spawn(async move {
+        handles.push(thread::spawn(move || {
             let outputfile = {
                 let folder: &str = &f1;
                 let model: &str = &f2;
@@ -59,27 +67,20 @@ impl Model {
                     Ok(v) => Ok(v),
                     Err(e) => Err(UtilError::FileSystemError(format!("Path: {filepath} - {e}",))),
                 }
-            }
-            .unwrap();
+            }?;
             let mut bf = BufWriter::new(outputfile);
             if codec == Codec::Null {
-                bf.write_all(b"[").unwrap();
-                for entry in &agent_values {
-                    bf.write_all(entry).unwrap();
-                    bf.write_all(b",\n").unwrap();
-                }
-                bf.write_all(b"]").unwrap();
-                bf.flush().unwrap();
+                Self::write_json_entries(&mut bf, &agent_values).map_err(UtilError::from)?;
+                bf.flush().map_err(UtilError::from)?;
             } else {
                 let mut compresser = GzEncoder::new(bf, Compression::default());
-                compresser.write_all(b"[").unwrap();
-                for entry in &agent_values {
-                    compresser.write_all(entry).unwrap();
-                    compresser.write_all(b",\n").unwrap();
-                }
-                compresser.write_all(b"]").unwrap();
-                compresser.flush().unwrap();
+                Self::write_json_entries(&mut compresser, &agent_values).map_err(UtilError::from)?;
+                compresser
+                    .finish()
+                    .and_then(|mut bf| bf.flush())
+                    .map_err(UtilError::from)?;
             }
+            Ok(())
         }));
     }
 }
@@ -96,10 +97,10 @@ mod agent_tests {
     fn format_print_json() {
         let mut param = ModelParameters::test();
         let agent = Root::new(117, &param.grid);
-        let agent2 = Area::new(117);
+        let agent2 = Area::new(117, &param.grid);
         let agent3 = Netstation::new(117, &param.grid);
-        let agent4 = Household::new(117, PowerGeneration::new_no_pv(Some(117), &mut param).unwrap());
-        let agent5 = Household::new(117, PowerGeneration::new_pv(Some(117), &mut param).unwrap());
+        let agent4 = Household::new(117, PowerGeneration::new_no_pv(Some(117), None, &mut param).unwrap());
+        let agent5 = Household::new(117, PowerGeneration::new_pv(Some(117), None, &mut param).unwrap());
 
         let s = serde_json::to_string(&agent).expect("msg");
         let s2 = serde_json::to_string(&agent2).expect("msg");
@@ -113,4 +114,19 @@ mod agent_tests {
         println!("{s4}");
         println!("{s5}");
     }
+
+    #[test]
+    fn write_error_returned() {
+        // A folder below a file can not be created, so writing the step fails
+        let blocker = std::env::temp_dir().join(format!("output_blocker_{}", std::process::id()));
+        std::fs::write(&blocker, b"").unwrap();
+        let mut param = ModelParameters::test();
+        param.outputdatafolder = blocker.to_string_lossy().to_string();
+
+        let mut handles = vec![];
+        Model::output_step_model_json(&param, Steps(0), vec![b"{}".to_vec()], &mut handles);
+        let written = handles.pop().unwrap().join().unwrap();
+        std::fs::remove_file(&blocker).unwrap();
+        assert!(written.is_err());
+    }
 }
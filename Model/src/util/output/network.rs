@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+
+use crate::agent::{AgentKind, AgentTrait};
+use crate::grid::InfectionState;
+use crate::model::{Model, TREE_EDGE};
+use crate::util::{BaseUint, UtilError, Watt, WattHour};
+
+/// The attributes of an agent that are exported with the graph.
+#[derive(Debug, Serialize)]
+struct NodeAttributes<'a> {
+    id:              BaseUint,
+    kind:            AgentKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name:            Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pv:              Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    infection_state: Option<InfectionState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    capacity:        Option<Watt>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    energy_capacity: Option<WattHour>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rated_power:     Option<Watt>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    longitude:       Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latitude:        Option<f64>,
+}
+
+/// The attributes of an agent that only some agents have, by index.
+#[derive(Default)]
+struct Extras<'a> {
+    capacity:        HashMap<BaseUint, Watt>,
+    energy_capacity: HashMap<BaseUint, WattHour>,
+    rated_power:     HashMap<BaseUint, Watt>,
+    name:            HashMap<BaseUint, &'a str>,
+    coordinates:     HashMap<BaseUint, (f64, f64)>,
+}
+
+/// Name, type and attribute of the GraphML keys of the nodes.
+const GRAPHML_NODE_KEYS: [(&str, &str); 9] = [
+    ("kind", "string"),
+    ("name", "string"),
+    ("pv", "boolean"),
+    ("infection_state", "string"),
+    ("capacity", "long"),
+    ("energy_capacity", "long"),
+    ("rated_power", "long"),
+    ("longitude", "double"),
+    ("latitude", "double"),
+];
+
+impl Model {
+    /// Export the graph of the model with the attributes of the agents, in GraphML if the file ends with `.graphml`
+    /// and in the JSON node-link format otherwise.
+    pub fn export_graph<T>(&self, filename: &T) -> Result<(), UtilError>
+    where
+        T: AsRef<Path> + Display,
+    {
+        if filename.as_ref().extension().is_some_and(|e| e == "graphml") {
+            self.write_graphml(filename)
+        } else {
+            self.write_node_link_json(filename)
+        }
+    }
+
+    /// Write the graph of the model to a GraphML file. The nodes carry the kind of the agent, whether a household has
+    /// PV and its infection state, the capacity of connections and generators, the energy capacity of batteries, the
+    /// rated power of EV chargers and heat pumps, and the name and location of imported areas and netstations. The
+    /// edges are either part of the tree or a horizontal link.
+    ///
+    /// The file is written while iterating the agents, so it scales to large grids.
+    pub fn write_graphml<T>(&self, filename: &T) -> Result<(), UtilError>
+    where
+        T: AsRef<Path> + Display,
+    {
+        let mut bf = BufWriter::new(create_file(filename)?);
+        writeln!(bf, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(bf, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+        for (name, attr_type) in GRAPHML_NODE_KEYS {
+            writeln!(
+                bf,
+                r#"  <key id="{name}" for="node" attr.name="{name}" attr.type="{attr_type}"/>"#
+            )?;
+        }
+        writeln!(
+            bf,
+            r#"  <key id="edge_kind" for="edge" attr.name="kind" attr.type="string"/>"#
+        )?;
+        writeln!(bf, r#"  <graph id="grid" edgedefault="directed">"#)?;
+
+        let extras = self.extras();
+        for agent in &self.agents {
+            let node = Self::node_attributes(&*agent.read(), &extras);
+            write!(
+                bf,
+                r#"    <node id="n{}"><data key="kind">{:?}</data>"#,
+                node.id, node.kind
+            )?;
+            if let Some(name) = node.name {
+                write!(bf, r#"<data key="name">{}</data>"#, escape_xml(name))?;
+            }
+            if let Some(pv) = node.pv {
+                write!(bf, r#"<data key="pv">{pv}</data>"#)?;
+            }
+            if let Some(state) = node.infection_state {
+                write!(bf, r#"<data key="infection_state">{state:?}</data>"#)?;
+            }
+            if let Some(capacity) = node.capacity {
+                write!(bf, r#"<data key="capacity">{}</data>"#, capacity.0)?;
+            }
+            if let Some(energy_capacity) = node.energy_capacity {
+                write!(bf, r#"<data key="energy_capacity">{}</data>"#, energy_capacity.0)?;
+            }
+            if let Some(rated_power) = node.rated_power {
+                write!(bf, r#"<data key="rated_power">{}</data>"#, rated_power.0)?;
+            }
+            if let (Some(longitude), Some(latitude)) = (node.longitude, node.latitude) {
+                write!(
+                    bf,
+                    r#"<data key="longitude">{longitude}</data><data key="latitude">{latitude}</data>"#
+                )?;
+            }
+            writeln!(bf, "</node>")?;
+        }
+        for edge in self.graph.edge_references() {
+            writeln!(
+                bf,
+                r#"    <edge source="n{}" target="n{}"><data key="edge_kind">{}</data></edge>"#,
+                edge.source().index(),
+                edge.target().index(),
+                edge_kind(*edge.weight())
+            )?;
+        }
+        writeln!(bf, "  </graph>")?;
+        writeln!(bf, "</graphml>")?;
+        Ok(bf.flush()?)
+    }
+
+    /// Write the graph of the model to a JSON file in the node-link format, with the same attributes as
+    /// [Model::write_graphml]. The format is read by e.g. `networkx.node_link_graph`.
+    pub fn write_node_link_json<T>(&self, filename: &T) -> Result<(), UtilError>
+    where
+        T: AsRef<Path> + Display,
+    {
+        let mut bf = BufWriter::new(create_file(filename)?);
+        write!(bf, r#"{{"directed":true,"multigraph":false,"graph":{{}},"nodes":["#)?;
+        let extras = self.extras();
+        for (i, agent) in self.agents.iter().enumerate() {
+            if i > 0 {
+                bf.write_all(b",\n")?;
+            }
+            let node = Self::node_attributes(&*agent.read(), &extras);
+            serde_json::to_writer(&mut bf, &node).map_err(std::io::Error::from)?;
+        }
+        write!(bf, r#"],"links":["#)?;
+        for (i, edge) in self.graph.edge_references().enumerate() {
+            if i > 0 {
+                bf.write_all(b",\n")?;
+            }
+            write!(
+                bf,
+                r#"{{"source":{},"target":{},"kind":"{}"}}"#,
+                edge.source().index(),
+                edge.target().index(),
+                edge_kind(*edge.weight())
+            )?;
+        }
+        write!(bf, "]}}")?;
+        Ok(bf.flush()?)
+    }
+
+    /// The capacities of the connections, generators and batteries, the rated power of the devices, and the names and
+    /// locations from the imported topology.
+    fn extras(&self) -> Extras<'_> {
+        let mut extras = Extras::default();
+        for connection in &self.connections {
+            let connection = connection.read();
+            extras.capacity.insert(connection.index, connection.capacity);
+        }
+        for generator in &self.generators {
+            let generator = generator.read();
+            extras.capacity.insert(generator.index, generator.capacity);
+        }
+        for battery in &self.batteries {
+            let battery = battery.read();
+            extras.energy_capacity.insert(battery.index, battery.capacity);
+        }
+        for ev_charger in &self.ev_chargers {
+            let ev_charger = ev_charger.read();
+            extras.rated_power.insert(ev_charger.index, ev_charger.charging_power);
+        }
+        for heat_pump in &self.heat_pumps {
+            let heat_pump = heat_pump.read();
+            extras.rated_power.insert(heat_pump.index, heat_pump.electrical_power);
+        }
+        if let Some(topology) = &self.param.grid.topology {
+            for (area, area_topology) in self.areas.iter().zip(&topology.areas) {
+                extras.name.insert(area.read().index, &area_topology.name);
+            }
+            let netstation_topologies = topology.areas.iter().flat_map(|a| &a.netstations);
+            for (netstation, netstation_topology) in self.netstations.iter().zip(netstation_topologies) {
+                let index = netstation.read().index;
+                extras.name.insert(index, &netstation_topology.name);
+                if let Some(coordinates) = netstation_topology.coordinates {
+                    extras.coordinates.insert(index, coordinates);
+                }
+            }
+        }
+        extras
+    }
+
+    fn node_attributes<'a>(agent: &(dyn AgentTrait + Send + Sync), extras: &Extras<'a>) -> NodeAttributes<'a> {
+        let id = *agent.index();
+        let power_gen = agent.power_gen();
+        let coordinates = extras.coordinates.get(&id);
+        NodeAttributes {
+            id,
+            kind: *agent.kind(),
+            name: extras.name.get(&id).copied(),
+            pv: power_gen.map(|p| p.has_pv()),
+            infection_state: power_gen.map(|p| p.infection_state),
+            capacity: extras.capacity.get(&id).copied(),
+            energy_capacity: extras.energy_capacity.get(&id).copied(),
+            rated_power: extras.rated_power.get(&id).copied(),
+            longitude: coordinates.map(|c| c.0),
+            latitude: coordinates.map(|c| c.1),
+        }
+    }
+}
+
+fn create_file<T>(filename: &T) -> Result<File, UtilError>
+where
+    T: AsRef<Path> + Display,
+{
+    File::create(filename).map_err(|e| UtilError::FileSystemError(format!("Path: {filename} - {e}")))
+}
+
+const fn edge_kind(weight: i32) -> &'static str {
+    if weight == TREE_EDGE {
+        "tree"
+    } else {
+        "link"
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod network_tests {
+    use super::*;
+    use crate::agent::HeatPumpParam;
+    use crate::model::ModelParameters;
+
+    #[test]
+    fn export_graphml_and_json() {
+        let mut param = ModelParameters::test();
+        param.grid.ns_line_capacity = Watt(1_000);
+        param.grid.battery_adoption = 1.0;
+        param.grid.battery_capacity = WattHour(10_000);
+        param.grid.heat_pump = Some(HeatPumpParam {
+            adoption:         1.0,
+            electrical_power: Watt(3_000),
+            cop:              3.0,
+            deadband:         1.0,
+            setpoint:         (20.0, 1.0),
+            resistance:       (0.005, 0.001),
+            capacitance:      (2.0e7, 1.0e6),
+        });
+        let model = Model::new(param).unwrap();
+        assert!(!model.batteries.is_empty() && !model.heat_pumps.is_empty());
+        let folder = std::env::temp_dir();
+        let pid = std::process::id();
+        let graphml_file = folder.join(format!("export_test_{pid}.graphml")).display().to_string();
+        let json_file = folder.join(format!("export_test_{pid}.json")).display().to_string();
+        model.export_graph(&graphml_file).unwrap();
+        model.export_graph(&json_file).unwrap();
+        let graphml = std::fs::read_to_string(&graphml_file).unwrap();
+        let json = std::fs::read_to_string(&json_file).unwrap();
+        std::fs::remove_file(&graphml_file).ok();
+        std::fs::remove_file(&json_file).ok();
+
+        assert_eq!(graphml.matches("<node ").count(), model.agents.len());
+        assert_eq!(graphml.matches("<edge ").count(), model.graph.edge_count());
+        assert!(graphml.contains(r#"<data key="capacity">1000</data>"#));
+        assert_eq!(
+            graphml.matches(r#"<data key="energy_capacity">10000</data>"#).count(),
+            model.batteries.len()
+        );
+        assert_eq!(
+            graphml.matches(r#"<data key="rated_power">3000</data>"#).count(),
+            model.heat_pumps.len()
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let nodes = json["nodes"].as_array().unwrap();
+        assert_eq!(nodes.len(), model.agents.len());
+        assert_eq!(json["links"].as_array().unwrap().len(), model.graph.edge_count());
+        let households = nodes.iter().filter(|n| n["kind"] == "Household");
+        assert!(households.clone().all(|n| n["pv"].is_boolean()));
+        assert_eq!(households.count(), model.households.len());
+    }
+}
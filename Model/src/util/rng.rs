@@ -0,0 +1,109 @@
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+#[allow(unused_imports)]
+use crate::model::Model;
+use crate::util::{BaseUint, Steps};
+
+/// The purpose a stream of random numbers is used for. Streams of different purposes are independent, so adding draws
+/// for one purpose does not change the draws of another.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RngPurpose {
+    /// The number of children of an area or netstation.
+    Topology,
+    /// The horizontal links between netstations or areas.
+    Link,
+    /// The household and its power generation and load.
+    Generation,
+    /// The home batteries, EV chargers and heat pumps, and the daily visits of the vehicles.
+    Device,
+    /// The stochastic noise shared by the households under a netstation.
+    Noise,
+    /// Whether a device is infected.
+    Infection,
+    /// Whether a device is patched.
+    Patching,
+    /// The draws of the events of a scenario.
+    Scenario,
+}
+
+/// Streams of random numbers derived from a single master seed.
+///
+/// Each stream is keyed by its purpose, the index of the agent and optionally a step. A stream only depends on its key
+/// and the master seed, so the draws of an agent do not depend on the draws of any other agent or on the order in
+/// which rayon runs the agents. The results of a [Model] are therefore identical regardless of the number of threads.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RngStreams {
+    /// The seed all streams are derived from.
+    pub master_seed: u64,
+}
+
+impl RngStreams {
+    /// Creates the streams from the master seed.
+    pub const fn new(master_seed: u64) -> Self { Self { master_seed } }
+
+    /// The stream of the agent with the given index for the given purpose.
+    pub fn stream(&self, purpose: RngPurpose, index: BaseUint) -> SmallRng {
+        SmallRng::seed_from_u64(self.key(purpose, index, None))
+    }
+
+    /// The stream of the agent with the given index for the given purpose, at the given step.
+    pub fn stream_at(&self, purpose: RngPurpose, index: BaseUint, step: Steps) -> SmallRng {
+        SmallRng::seed_from_u64(self.key(purpose, index, Some(step)))
+    }
+
+    /// A seed for the agent with the given index and purpose, e.g. for a stochastic process that keeps its own stream.
+    pub fn seed(&self, purpose: RngPurpose, index: BaseUint) -> i64 { self.key(purpose, index, None) as i64 }
+
+    /// The stream of a process that keeps its own seed, see [RngStreams::seed], at the given step.
+    pub fn seeded_at(seed: i64, step: Steps) -> SmallRng {
+        SmallRng::seed_from_u64(mix(mix(seed as u64) ^ step.0 as u64))
+    }
+
+    fn key(&self, purpose: RngPurpose, index: BaseUint, step: Option<Steps>) -> u64 {
+        let key = mix(mix(self.master_seed ^ purpose as u64) ^ u64::from(index));
+        step.map_or(key, |step| mix(key ^ step.0 as u64))
+    }
+}
+
+/// The finaliser of SplitMix64, which spreads keys that differ by a single bit over the whole range.
+const fn mix(key: u64) -> u64 {
+    let mut z = key.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod rng_tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn streams_are_keyed() {
+        let streams = RngStreams::new(117);
+        let draw = |mut stream: SmallRng| stream.gen::<u64>();
+        assert_eq!(
+            draw(streams.stream(RngPurpose::Generation, 3)),
+            draw(RngStreams::new(117).stream(RngPurpose::Generation, 3))
+        );
+        assert_ne!(
+            draw(streams.stream(RngPurpose::Generation, 3)),
+            draw(streams.stream(RngPurpose::Generation, 4))
+        );
+        assert_ne!(
+            draw(streams.stream(RngPurpose::Generation, 3)),
+            draw(streams.stream(RngPurpose::Device, 3))
+        );
+        assert_ne!(
+            draw(streams.stream_at(RngPurpose::Infection, 3, Steps(1))),
+            draw(streams.stream_at(RngPurpose::Infection, 3, Steps(2)))
+        );
+        assert_ne!(
+            draw(streams.stream(RngPurpose::Generation, 3)),
+            draw(RngStreams::new(118).stream(RngPurpose::Generation, 3))
+        );
+    }
+}
@@ -32,6 +32,11 @@ newtype! {
     /// A newType regarding milliVolts
 pub struct mVolt(pub BaseInt);}
 
+newtype! {
+    #[allow(non_camel_case_types)]
+    /// A newType regarding thousandths of a percent
+pub struct mPercent(pub BaseInt);}
+
 newtype! {
 /// A newType regarding minutes
     pub struct Minutes(pub BaseInt);}
@@ -40,6 +45,10 @@ newtype! {
 /// A newType regarding Watts
 pub struct Watt(pub i64);}
 
+newtype! {
+/// A newType regarding Watt hours
+pub struct WattHour(pub i64);}
+
 newtype! {
             /// Currently one step = 15 minutes
 pub struct Steps(pub BaseInt);}
@@ -69,6 +78,28 @@ impl Steps {
     pub const fn steps_per_day() -> Self { Self(1440 / Self::minutes_per_step().0) }
 }
 
+impl WattHour {
+    /// Energy that is transferred with the given power during a single step.
+    #[inline]
+    pub const fn per_step(power: Watt) -> Self { Self(power.0 * Steps::minutes_per_step().0 as i64 / 60) }
+
+    /// Power that transfers this amount of energy during a single step.
+    #[inline]
+    pub const fn as_step_power(&self) -> Watt { Watt(self.0 * 60 / Steps::minutes_per_step().0 as i64) }
+}
+
+impl mPercent {
+    /// The share of the part in the total. Zero if the total is zero.
+    #[inline]
+    pub fn share(part: BaseInt, total: BaseInt) -> Self {
+        if total == 0 {
+            Self(0)
+        } else {
+            Self((i64::from(part) * 100_000 / i64::from(total)) as BaseInt)
+        }
+    }
+}
+
 impl From<Minutes> for Steps {
     fn from(i: Minutes) -> Self { Self(i.0 / Self::minutes_per_step().0) }
 }
@@ -81,7 +112,9 @@ impl StructTraitBound for Steps {}
 impl StructTraitBound for Minutes {}
 impl StructTraitBound for mVolt {}
 impl StructTraitBound for mHz {}
+impl StructTraitBound for mPercent {}
 impl StructTraitBound for Watt {}
+impl StructTraitBound for WattHour {}
 
 // -------------------------------
 
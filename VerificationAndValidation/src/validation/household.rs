@@ -53,10 +53,13 @@ fn plot_year_usage(folder: &String, data: Vec<f64>) {
     plot.write_html(format!("./output/validation/household/usage_histogram.html"));
 }
 
+/// The standard profiles of MFFBAS within the validation folder. The model reads the same file for its consumption
+/// profiles, see `profile_file` in the GridParameters file.
+pub const PROFILE_FILE: &str = "MFFBAS/Profielen elektriciteit 2023/Standaardprofielen elektriciteit 2023 versie 1.00.csv";
+
 #[tokio::main]
 pub async fn day_usage(folder: &String) {
-    let filename =
-        format!("./{folder}/MFFBAS/Profielen elektriciteit 2023/Standaardprofielen elektriciteit 2023 versie 1.00.csv");
+    let filename = format!("./{folder}/{PROFILE_FILE}");
     let df = CsvReader::from_path(filename)
         .unwrap()
         .with_delimiter(b';')